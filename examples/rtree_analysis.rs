@@ -88,6 +88,14 @@ fn run_compare_operations_bench() {
     print_measurements("successful lookup", &succsessful_lookup_times);
     print_measurements("unsuccessful lookup", &unsuccsessful_lookup_times);
 
+    let statistics = tree.statistics();
+    println!();
+    for level in &statistics.levels {
+        println!("depth {}: {} nodes, fill factor {:.2}, overlap {}, dead space {}",
+                 level.depth, level.node_count, level.average_fill_factor,
+                 level.overlap, level.dead_space);
+    }
+
     println!("Done!");
 }
 
@@ -6,9 +6,10 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use num::{Signed, zero, one};
+use num::{Signed, Float, zero, one};
 use point_traits::{PointN, PointNExtensions};
-use misc::max_inline;
+use traits::SpadeFloat;
+use misc::{max_inline, min_inline};
 
 /// An axis aligned minimal bounding rectangle.
 ///
@@ -141,6 +142,14 @@ impl <V> BoundingRect<V> where V: PointN {
         self.min_point(point).sub(point).length2()
     }
 
+    #[doc(hidden)]
+    pub fn min_dist2_rect(&self, other: &BoundingRect<V>) -> V::Scalar {
+        let below = other.lower.sub(&self.upper);
+        let above = self.lower.sub(&other.upper);
+        let gap: V = below.component_wise(&above, |b, a| max_inline(max_inline(b, a), zero()));
+        gap.length2()
+    }
+
     #[doc(hidden)]
     pub fn max_dist2(&self, point: &V) -> V::Scalar {
         let l = self.lower();
@@ -178,3 +187,36 @@ impl <V> BoundingRect<V> where V: PointN {
         result
     }
 }
+
+impl <V> BoundingRect<V> where V: PointN, V::Scalar: SpadeFloat {
+
+    /// Intersects this rectangle with a ray.
+    ///
+    /// The ray starts at `origin` and contains all points `origin + direction * t`
+    /// for non negative `t`. Returns the smallest such `t` for which the point lies
+    /// within this rectangle or `None` if the ray misses the rectangle. Returns zero
+    /// if the origin is contained in the rectangle.
+    pub fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        let mut t_min = zero();
+        let mut t_max = Float::infinity();
+        for i in 0 .. V::dimensions() {
+            let (o, d) = (*origin.nth(i), *direction.nth(i));
+            let (lower, upper) = (*self.lower.nth(i), *self.upper.nth(i));
+            if d == zero() {
+                // The ray is parallel to this slab
+                if o < lower || o > upper {
+                    return None;
+                }
+                continue;
+            }
+            let (t1, t2) = ((lower - o) / d, (upper - o) / d);
+            let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+            t_min = max_inline(t_min, near);
+            t_max = min_inline(t_max, far);
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+}
@@ -0,0 +1,372 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Density based clustering of r*-tree items.
+//!
+//! Both algorithms find dense regions of items. An item's neighborhood contains
+//! all items whose squared distance to it is smaller than a given `radius2`,
+//! including the item itself. An item whose neighborhood contains at least
+//! `min_points` items is a *core item*.
+//!
+//! * `dbscan` assigns a cluster label to each item. Clusters are formed by
+//!   core items that lie within each other's neighborhood and by all items in
+//!   their neighborhoods, all other items are noise.
+//! * `optics` orders all items such that items of dense regions are adjacent and
+//!   computes a reachability distance for each item. The ordering can be used to
+//!   find clusters of varying density.
+//!
+//! Neighborhoods are found with circle queries on a tree of the items' positions
+//! that is loaded once per run, all query buffers are reused during a run.
+
+use std::collections::BinaryHeap;
+use traits::{SpatialObject, HasPosition};
+use point_traits::PointN;
+use misc::{max_inline, MinHeapEntry};
+use rtree::{RTree, Aggregate};
+
+type Scalar<T> = <<T as HasPosition>::Point as PointN>::Scalar;
+
+/// The result of `dbscan`.
+#[derive(Clone, Debug)]
+pub struct Clustering<'a, T>
+    where T: 'a {
+    /// All items, in the iteration order of the tree.
+    pub items: Vec<&'a T>,
+    /// The cluster label of each item, `None` marks noise.
+    ///
+    /// `labels[i]` is the label of `items[i]`, clusters are numbered from zero.
+    pub labels: Vec<Option<usize>>,
+    /// The number of clusters.
+    pub cluster_count: usize,
+}
+
+impl <'a, T> Clustering<'a, T> {
+    /// Returns the items of each cluster.
+    pub fn clusters(&self) -> Vec<Vec<&'a T>> {
+        let mut result = vec![Vec::new(); self.cluster_count];
+        for (item, label) in self.items.iter().zip(self.labels.iter()) {
+            if let Some(cluster) = *label {
+                result[cluster].push(*item);
+            }
+        }
+        result
+    }
+
+    /// Returns all items that do not belong to any cluster.
+    pub fn noise(&self) -> Vec<&'a T> {
+        self.items.iter().zip(self.labels.iter())
+            .filter(|&(_, label)| label.is_none())
+            .map(|(item, _)| *item)
+            .collect()
+    }
+}
+
+/// The result of `optics`.
+#[derive(Clone, Debug)]
+pub struct OpticsOrdering<'a, T>
+    where T: HasPosition + 'a {
+    /// All items in the order in which they were processed.
+    pub items: Vec<&'a T>,
+    /// The squared reachability distance of each item.
+    ///
+    /// `None` marks an item that is not reachable from any previous item, i.e.
+    /// the start of a new dense region.
+    pub reachability_distances2: Vec<Option<Scalar<T>>>,
+    /// The squared core distance of each item, `None` if the item is no core item.
+    ///
+    /// The core distance is the distance to the `min_points`-th closest item
+    /// of the item's neighborhood, counting the item itself.
+    pub core_distances2: Vec<Option<Scalar<T>>>,
+}
+
+// An item's position together with the item's index
+struct IndexedPosition<V> {
+    index: usize,
+    position: V,
+}
+
+impl <V: PointN> HasPosition for IndexedPosition<V> {
+    type Point = V;
+    fn position(&self) -> V {
+        self.position.clone()
+    }
+}
+
+// Finds the neighborhoods of a tree's items by their index
+struct Neighborhoods<'a, T>
+    where T: HasPosition + 'a {
+    items: Vec<&'a T>,
+    // The items' positions are loaded into a separate tree once, this
+    // allows to look up the index of each found neighbor
+    positions: RTree<IndexedPosition<T::Point>>,
+    radius2: Scalar<T>,
+}
+
+impl <'a, T> Neighborhoods<'a, T>
+    where T: HasPosition + 'a {
+    fn new<A>(tree: &'a RTree<T, A>, radius2: Scalar<T>) -> Neighborhoods<'a, T>
+        where A: Aggregate<T> {
+        let items: Vec<_> = tree.iter().collect();
+        let positions = RTree::bulk_load(items.iter().enumerate()
+            .map(|(index, item)| IndexedPosition { index, position: item.position() })
+            .collect());
+        Neighborhoods {
+            items,
+            positions,
+            radius2,
+        }
+    }
+
+    // Replaces the content of result with an item's neighbors
+    fn find<'b>(&'b self, index: usize, result: &mut Vec<&'b IndexedPosition<T::Point>>) {
+        result.clear();
+        self.positions.lookup_in_circle_into(result, &self.items[index].position(),
+                                             &self.radius2);
+    }
+}
+
+/// Clusters the items of an r*-tree with DBSCAN.
+///
+/// See the module documentation for a description of `radius2` and `min_points`.
+/// A non core item that lies in the neighborhoods of several clusters is assigned
+/// to the cluster that reaches it first.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::RTree;
+/// use spade::clustering::dbscan;
+/// use cgmath::Point2;
+///
+/// # fn main() {
+/// let mut tree = RTree::new();
+/// for i in 0 .. 10 {
+///   tree.insert(Point2::new(i as f64 * 0.1, 0.0));
+///   tree.insert(Point2::new(i as f64 * 0.1, 5.0));
+/// }
+/// tree.insert(Point2::new(10.0, 10.0));
+/// let clustering = dbscan(&tree, &0.04, 3);
+/// assert_eq!(clustering.cluster_count, 2);
+/// assert_eq!(clustering.noise(), vec![&Point2::new(10.0, 10.0)]);
+/// # }
+/// ```
+pub fn dbscan<'a, T, A>(tree: &'a RTree<T, A>, radius2: &Scalar<T>, min_points: usize)
+                        -> Clustering<'a, T>
+    where T: HasPosition,
+          A: Aggregate<T> {
+    let neighborhoods = Neighborhoods::new(tree, radius2.clone());
+    let size = neighborhoods.items.len();
+    let mut labels = vec![None; size];
+    let mut visited = vec![false; size];
+    let mut cluster_count = 0;
+    let mut neighbors = Vec::new();
+    let mut queue = Vec::new();
+    for index in 0 .. size {
+        if visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        neighborhoods.find(index, &mut neighbors);
+        if neighbors.len() < min_points {
+            // Noise, unless a cluster reaches this item later on
+            continue;
+        }
+        let cluster = cluster_count;
+        cluster_count += 1;
+        labels[index] = Some(cluster);
+        queue.clear();
+        queue.extend(neighbors.iter().map(|n| n.index));
+        while let Some(neighbor) = queue.pop() {
+            if labels[neighbor].is_none() {
+                labels[neighbor] = Some(cluster);
+            }
+            if visited[neighbor] {
+                continue;
+            }
+            visited[neighbor] = true;
+            neighborhoods.find(neighbor, &mut neighbors);
+            if neighbors.len() >= min_points {
+                queue.extend(neighbors.iter().map(|n| n.index));
+            }
+        }
+    }
+    Clustering {
+        items: neighborhoods.items,
+        labels,
+        cluster_count,
+    }
+}
+
+/// Computes the OPTICS ordering of an r*-tree's items.
+///
+/// `radius2` limits the neighborhood of each item, see the module documentation.
+/// Items are processed in order of their smallest reachability distance from any
+/// processed core item, a new dense region starts with an item of reachability
+/// `None`. Clusters for any squared radius up to `radius2` can be extracted from
+/// the ordering, e.g. by splitting it at items whose reachability exceeds that radius.
+pub fn optics<'a, T, A>(tree: &'a RTree<T, A>, radius2: &Scalar<T>, min_points: usize)
+                        -> OpticsOrdering<'a, T>
+    where T: HasPosition,
+          A: Aggregate<T> {
+    let neighborhoods = Neighborhoods::new(tree, radius2.clone());
+    let size = neighborhoods.items.len();
+    let mut reachability: Vec<Option<Scalar<T>>> = vec![None; size];
+    let mut processed = vec![false; size];
+    let mut result = OpticsOrdering {
+        items: Vec::with_capacity(size),
+        reachability_distances2: Vec::with_capacity(size),
+        core_distances2: Vec::with_capacity(size),
+    };
+    let mut neighbors = Vec::new();
+    let mut distances = Vec::new();
+    let mut sorted = Vec::new();
+    let mut seeds = BinaryHeap::new();
+    // The number of closer neighbors that determine the core distance
+    let core_index = if min_points > 0 { min_points - 1 } else { 0 };
+    for start in 0 .. size {
+        let mut next = if processed[start] { None } else { Some(start) };
+        while let Some(index) = next {
+            processed[index] = true;
+            let item = neighborhoods.items[index];
+            neighborhoods.find(index, &mut neighbors);
+            let position = item.position();
+            distances.clear();
+            distances.extend(neighbors.iter()
+                             .map(|n| neighborhoods.items[n.index].distance2(&position)));
+            sorted.clear();
+            sorted.extend(distances.iter().cloned());
+            sorted.sort_by(|l, r| l.partial_cmp(r).unwrap());
+            let core_distance2 = sorted.get(core_index).cloned();
+            result.items.push(item);
+            result.reachability_distances2.push(reachability[index].clone());
+            result.core_distances2.push(core_distance2.clone());
+
+            if let Some(core_distance2) = core_distance2 {
+                for (neighbor, distance2) in neighbors.iter().zip(distances.iter()) {
+                    let neighbor = neighbor.index;
+                    if processed[neighbor] {
+                        continue;
+                    }
+                    let new_reachability = max_inline(core_distance2.clone(), distance2.clone());
+                    if reachability[neighbor].as_ref()
+                        .map(|r| new_reachability < *r).unwrap_or(true) {
+                        reachability[neighbor] = Some(new_reachability.clone());
+                        seeds.push(MinHeapEntry { distance2: new_reachability, entry: neighbor });
+                    }
+                }
+            }
+            next = None;
+            while let Some(MinHeapEntry { distance2, entry: seed }) = seeds.pop() {
+                // Skip seeds that were processed or improved in the meantime
+                if !processed[seed] && reachability[seed].as_ref() == Some(&distance2) {
+                    next = Some(seed);
+                    break;
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dbscan, optics};
+    use rtree::RTree;
+    use cgmath::Point2;
+    use testutils::*;
+
+    // Creates dense blobs of 100 points around (0, 0), (10, 0) and (0, 10)
+    // and 5 isolated points
+    fn blobs() -> Vec<Point2<f64>> {
+        let mut points = Vec::new();
+        for (index, center) in [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)].iter().enumerate() {
+            for p in random_points_with_seed::<f64>(100, [1, 2, 3, index as u32]) {
+                points.push(Point2::new(center.0 + p.x, center.1 + p.y));
+            }
+        }
+        for i in 0 .. 5 {
+            points.push(Point2::new(20.0 + 5.0 * i as f64, -20.0));
+        }
+        points
+    }
+
+    fn blob_index(point: &Point2<f64>) -> Option<usize> {
+        if point.y < -10.0 {
+            None
+        } else if point.x > 5.0 {
+            Some(1)
+        } else if point.y > 5.0 {
+            Some(2)
+        } else {
+            Some(0)
+        }
+    }
+
+    #[test]
+    fn test_dbscan() {
+        let points = blobs();
+        let mut tree = RTree::new();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let clustering = dbscan(&tree, &0.25, 4);
+        assert_eq!(clustering.items.len(), points.len());
+        assert_eq!(clustering.cluster_count, 3);
+        assert_eq!(clustering.noise().len(), 5);
+        let clusters = clustering.clusters();
+        for cluster in &clusters {
+            assert_eq!(cluster.len(), 100);
+            let blob = blob_index(cluster[0]);
+            assert!(cluster.iter().all(|p| blob_index(p) == blob));
+        }
+        for (item, label) in clustering.items.iter().zip(clustering.labels.iter()) {
+            assert_eq!(label.is_none(), blob_index(item).is_none());
+        }
+
+        let sparse = dbscan(&tree, &1e-10, 2);
+        assert_eq!(sparse.cluster_count, 0);
+        assert_eq!(sparse.noise().len(), points.len());
+        let single = dbscan(&tree, &1000.0, 1);
+        assert_eq!(single.cluster_count, 1);
+        let empty: RTree<Point2<f64>> = RTree::new();
+        assert_eq!(dbscan(&empty, &1.0, 3).items.len(), 0);
+    }
+
+    #[test]
+    fn test_optics() {
+        let points = blobs();
+        let mut tree = RTree::new();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let ordering = optics(&tree, &1.0, 4);
+        assert_eq!(ordering.items.len(), points.len());
+        assert_eq!(ordering.reachability_distances2.len(), points.len());
+        assert_eq!(ordering.reachability_distances2[0], None);
+        let mut sorted: Vec<_> = ordering.items.iter().map(|p| (p.x, p.y)).collect();
+        sorted.sort_by(|l, r| l.partial_cmp(r).unwrap());
+        sorted.dedup();
+        assert_eq!(sorted.len(), points.len());
+        // Each blob is reachable from its own items and forms a contiguous range
+        let mut starts = 0;
+        for (index, item) in ordering.items.iter().enumerate() {
+            match ordering.reachability_distances2[index] {
+                Some(reachability) => {
+                    assert!(reachability < 1.0);
+                    assert_eq!(blob_index(item), blob_index(ordering.items[index - 1]));
+                },
+                None => starts += 1,
+            }
+        }
+        assert_eq!(starts, 3 + 5);
+        for core_distance2 in ordering.core_distances2.iter().filter_map(|c| c.as_ref()) {
+            assert!(*core_distance2 < 1.0);
+        }
+    }
+}
@@ -6,6 +6,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use super::stable_handles::{HandleSlots, StableHandle};
+
 /// Handle to a vertex.
 ///
 /// This handle is "fixed", meaning it is intended to be used for
@@ -25,14 +27,74 @@ pub type FixedEdgeHandle = usize;
 /// face handles.
 pub type FixedFaceHandle = usize;
 
+/// Data stored for each face of a triangulation.
+///
+/// A triangulation splits, merges and changes its faces while vertices are inserted
+/// or removed. The methods of this trait are called for each of these operations and
+/// can be used to keep the data consistent, they do nothing by default.
+/// New faces are always initialized with `Default::default()` before they are passed
+/// to any of these methods.
+pub trait FaceData: Default {
+    /// Called when a face is split into two faces.
+    ///
+    /// `self` refers to the split face, `new` to the face that was created by the split.
+    fn split(&mut self, new: &mut Self) {
+        let _ = new;
+    }
+
+    /// Called when two faces are merged into one face.
+    ///
+    /// `self` refers to the remaining face, `removed` to the data of the face that was removed.
+    fn merge(&mut self, removed: Self) {
+        let _ = removed;
+    }
+
+    /// Called when the edge between two faces is flipped, changing both faces.
+    fn flip(&mut self, other: &mut Self) {
+        let _ = other;
+    }
+}
+
+/// Data stored for each undirected edge of a triangulation.
+///
+/// Both directed edges of an undirected edge share the same data.
+/// Similar to `FaceData`, the methods of this trait are called whenever an
+/// edge is changed and do nothing by default. New edges are initialized with
+/// `Default::default()`.
+pub trait EdgeData: Default {
+    /// Called when an edge is split into two edges.
+    ///
+    /// `self` refers to the split edge, `new` to the edge that was created by the split.
+    fn split(&mut self, new: &mut Self) {
+        let _ = new;
+    }
+
+    /// Called when an edge is flipped and connects two other vertices.
+    fn flip(&mut self) { }
+}
+
+impl FaceData for () { }
+
+impl EdgeData for () { }
+
 pub struct VertexRemovalResult<V> {
     pub updated_vertex: Option<FixedVertexHandle>,
     pub data: V,
 }
 
-#[derive(Debug, Copy, Clone)]
-struct FaceEntry {
+#[derive(Debug, Clone)]
+struct FaceEntry<F> {
     adjacent_edge: Option<FixedEdgeHandle>,
+    data: F,
+}
+
+impl <F> FaceEntry<F> where F: Default {
+    fn new(adjacent_edge: Option<FixedEdgeHandle>) -> FaceEntry<F> {
+        FaceEntry {
+            adjacent_edge,
+            data: Default::default(),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -60,21 +122,54 @@ struct HalfEdgeEntry {
 }
     
 
+#[derive(Clone, Debug)]
+struct StableSlots {
+    vertices: HandleSlots,
+    edges: HandleSlots,
+    faces: HandleSlots,
+}
+
 #[derive(Clone)]
-pub struct DCEL<V> {
+pub struct DCEL<V, E = (), F = ()> {
     vertices: Vec<VertexEntry<V>>,
-    faces: Vec<FaceEntry>,
+    faces: Vec<FaceEntry<F>>,
     edges: Vec<HalfEdgeEntry>,
+    edge_data: Vec<E>,
+    stable: Option<Box<StableSlots>>,
 }
 
-impl <V> DCEL<V> {
+impl <V, E, F> DCEL<V, E, F> {
 
-    pub fn new() -> DCEL<V> {
-        DCEL {
-            vertices: Vec::new(),
-            edges: Vec::new(),
-            faces: vec![FaceEntry { adjacent_edge: None } ],
-        }
+    pub fn has_stable_handles(&self) -> bool {
+        self.stable.is_some()
+    }
+
+    fn stable_slots(&self) -> &StableSlots {
+        self.stable.as_ref().expect("Stable handles are not enabled")
+    }
+
+    pub fn stable_vertex(&self, handle: FixedVertexHandle) -> StableHandle {
+        self.stable_slots().vertices.stable(handle)
+    }
+
+    pub fn stable_edge(&self, handle: FixedEdgeHandle) -> StableHandle {
+        self.stable_slots().edges.stable(handle)
+    }
+
+    pub fn stable_face(&self, handle: FixedFaceHandle) -> StableHandle {
+        self.stable_slots().faces.stable(handle)
+    }
+
+    pub fn resolve_vertex(&self, handle: StableHandle) -> Option<FixedVertexHandle> {
+        self.stable_slots().vertices.resolve(handle)
+    }
+
+    pub fn resolve_edge(&self, handle: StableHandle) -> Option<FixedEdgeHandle> {
+        self.stable_slots().edges.resolve(handle)
+    }
+
+    pub fn resolve_face(&self, handle: StableHandle) -> Option<FixedFaceHandle> {
+        self.stable_slots().faces.resolve(handle)
     }
 
     pub fn num_vertices(&self) -> usize {
@@ -89,15 +184,15 @@ impl <V> DCEL<V> {
         self.faces.len()
     }
 
-    pub fn vertex(&self, handle: FixedVertexHandle) -> VertexHandle<V> {
+    pub fn vertex(&self, handle: FixedVertexHandle) -> VertexHandle<V, E, F> {
         VertexHandle::new(self, handle)
     }
 
-    pub fn edge(&self, handle: FixedEdgeHandle) -> EdgeHandle<V> {
+    pub fn edge(&self, handle: FixedEdgeHandle) -> EdgeHandle<V, E, F> {
         EdgeHandle::new(self, handle)
     }
 
-    pub fn face(&self, handle: FixedFaceHandle) -> FaceHandle<V> {
+    pub fn face(&self, handle: FixedFaceHandle) -> FaceHandle<V, E, F> {
         FaceHandle::new(self, handle)
     }
 
@@ -105,8 +200,110 @@ impl <V> DCEL<V> {
         &mut self.vertices[handle].data
     }
 
+    pub fn face_data_mut(&mut self, handle: FixedFaceHandle) -> &mut F {
+        &mut self.faces[handle].data
+    }
+
+    pub fn edge_data_mut(&mut self, handle: FixedEdgeHandle) -> &mut E {
+        &mut self.edge_data[handle / 2]
+    }
+
+    pub fn edges(&self) -> EdgesIterator<V, E, F> {
+        EdgesIterator::new(&self)
+    }
+
+    pub fn vertices(&self) -> VerticesIterator<V, E, F> {
+        VerticesIterator::new(&self)
+    }
+
+    pub fn fixed_vertices(&self) -> FixedVerticesIterator {
+        (0 .. self.num_vertices())
+    }
+
+    pub fn faces(&self) -> FacesIterator<V, E, F> {
+        FacesIterator::new(&self)
+    }
+
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub fn print(&self) {
+        for (index, edge) in self.edges.iter().enumerate() {
+            println!("edge {}: {:#?}", index, edge);
+        }
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            println!("vertex {}: {:?}", index, vertex.out_edge);
+        }
+        for (index, face) in self.faces.iter().enumerate() {
+            println!("face {}: {:?}", index, face.adjacent_edge);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn sanity_check(&self) {
+        for (index, face) in self.faces.iter().enumerate() {
+            if let Some(adj) = face.adjacent_edge {
+                assert_eq!(self.edges[adj].face, index);
+            }
+        }
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            if let Some(out_edge) = vertex.out_edge {
+                assert_eq!(self.edges[out_edge].origin, index);
+            }
+        }
+        for edge in self.edges() {
+            assert_eq!(edge, edge.o_next().o_prev());
+            assert_eq!(edge, edge.o_prev().o_next());
+            assert_eq!(edge, edge.sym().sym());
+        }
+    }
+}
+
+impl <V, E, F> DCEL<V, E, F> where E: EdgeData, F: FaceData {
+
+    pub fn new() -> DCEL<V, E, F> {
+        DCEL {
+            vertices: Vec::new(),
+            edges: Vec::new(),
+            faces: vec![FaceEntry::new(None)],
+            edge_data: Vec::new(),
+            stable: None,
+        }
+    }
+
+    pub fn enable_stable_handles(&mut self) {
+        if self.stable.is_none() {
+            self.stable = Some(Box::new(StableSlots {
+                vertices: HandleSlots::with_len(self.vertices.len()),
+                edges: HandleSlots::with_len(self.edges.len()),
+                faces: HandleSlots::with_len(self.faces.len()),
+            }));
+        }
+    }
+
+    pub fn rebind_stable_vertex(&mut self, vertex: FixedVertexHandle, handle: StableHandle) {
+        if let Some(ref mut stable) = self.stable {
+            stable.vertices.rebind(vertex, handle);
+        }
+    }
+
+    fn push_edge_pair(&mut self, edge: HalfEdgeEntry, twin: HalfEdgeEntry, data: E) {
+        // Directed edges are allocated in pairs, the twin of edge e is always e ^ 1.
+        // Edge data is indexed by e / 2.
+        debug_assert_eq!(self.edges.len() % 2, 0);
+        self.edges.push(edge);
+        self.edges.push(twin);
+        self.edge_data.push(data);
+        if let Some(ref mut stable) = self.stable {
+            stable.edges.push();
+            stable.edges.push();
+        }
+    }
+
     pub fn insert_vertex(&mut self, vertex: V) -> FixedVertexHandle {
         self.vertices.push(VertexEntry::new(vertex));
+        if let Some(ref mut stable) = self.stable {
+            stable.vertices.push();
+        }
         self.vertices.len() - 1
     }
 
@@ -124,8 +321,6 @@ impl <V> DCEL<V> {
             origin: v0,
             face: face,
         };
-        self.edges.push(edge);
-
         let twin = HalfEdgeEntry {
             next: edge_index,
             prev: edge_index,
@@ -133,7 +328,7 @@ impl <V> DCEL<V> {
             origin: v1,
             face: face,
         };
-        self.edges.push(twin);
+        self.push_edge_pair(edge, twin, Default::default());
 
         self.vertices[v0].out_edge = Some(edge_index);
         self.vertices[v1].out_edge = Some(twin_index);
@@ -157,8 +352,6 @@ impl <V> DCEL<V> {
             origin: self.edges[prev.twin].origin,
             face: prev.face,
         };
-        self.edges.push(edge);
-
         let twin = HalfEdgeEntry {
             next: prev.next,
             prev: edge_index,
@@ -166,7 +359,7 @@ impl <V> DCEL<V> {
             origin: vertex,
             face: prev.face,
         };
-        self.edges.push(twin);
+        self.push_edge_pair(edge, twin, Default::default());
 
         self.edges[prev_handle].next = edge_index;
         self.edges[prev.next].prev = twin_index;
@@ -177,8 +370,14 @@ impl <V> DCEL<V> {
 
     pub fn clear_edges_and_faces(&mut self) {
         self.edges.clear();
+        self.edge_data.clear();
         self.faces.clear();
-        self.faces.push(FaceEntry { adjacent_edge: None });
+        self.faces.push(FaceEntry::new(None));
+        if let Some(ref mut stable) = self.stable {
+            stable.edges.clear();
+            stable.faces.clear();
+            stable.faces.push();
+        }
         for vertex in &mut self.vertices {
             vertex.out_edge = None;
         }
@@ -190,6 +389,9 @@ impl <V> DCEL<V> {
             self.remove_edge(out_edge, remaining_face);
         }
         let data = self.vertices.swap_remove(vertex_handle).data;
+        if let Some(ref mut stable) = self.stable {
+            stable.vertices.swap_remove(vertex_handle);
+        }
         let updated_vertex = if self.vertices.len() == vertex_handle {
             None
         } else {
@@ -208,6 +410,24 @@ impl <V> DCEL<V> {
         }
     }
 
+    pub fn swap_vertices(&mut self, v0: FixedVertexHandle, v1: FixedVertexHandle) {
+        if v0 == v1 {
+            return;
+        }
+        let out_edges0: Vec<_> = self.vertex(v0).ccw_out_edges().map(|e| e.fix()).collect();
+        let out_edges1: Vec<_> = self.vertex(v1).ccw_out_edges().map(|e| e.fix()).collect();
+        self.vertices.swap(v0, v1);
+        if let Some(ref mut stable) = self.stable {
+            stable.vertices.swap(v0, v1);
+        }
+        for e in out_edges0 {
+            self.edges[e].origin = v1;
+        }
+        for e in out_edges1 {
+            self.edges[e].origin = v0;
+        }
+    }
+
     fn connect_edge_to_edge(&mut self, prev_edge_handle: FixedEdgeHandle,
                             next_edge_handle: FixedEdgeHandle) -> FixedEdgeHandle {
         let edge_index = self.edges.len();
@@ -221,8 +441,6 @@ impl <V> DCEL<V> {
             origin: self.edges[prev_edge.twin].origin,
             face: next_edge.face,
         };
-        self.edges.push(edge);
-
         let twin = HalfEdgeEntry {
             next: prev_edge.next,
             prev: next_edge.prev,
@@ -230,7 +448,7 @@ impl <V> DCEL<V> {
             origin: next_edge.origin,
             face: next_edge.face,
         };
-        self.edges.push(twin);
+        self.push_edge_pair(edge, twin, Default::default());
         
         self.edges[next_edge_handle].prev = edge_index;
         self.edges[prev_edge_handle].next = edge_index;
@@ -275,8 +493,14 @@ impl <V> DCEL<V> {
         self.vertices[twin.origin].out_edge = Some(new_twin_index);
         self.vertices[split_vertex].out_edge = Some(new_edge_index);
 
-        self.edges.push(new_edge);
-        self.edges.push(new_twin);
+        if let Some(ref mut stable) = self.stable {
+            // The split edge ends at the split vertex now
+            stable.edges.renew(edge_handle);
+            stable.edges.renew(edge.twin);
+        }
+        let mut new_data = Default::default();
+        self.edge_data[edge_handle / 2].split(&mut new_data);
+        self.push_edge_pair(new_edge, new_twin, new_data);
         new_edge_index
     }
 
@@ -331,27 +555,45 @@ impl <V> DCEL<V> {
             self.swap_out_edge(edge.twin);
             self.swap_out_edge(edge_handle);
         }
+        // The last edge pair was moved into the removed pair's place
+        self.edge_data.swap_remove(edge_handle / 2);
         if edge.face != twin.face {
             let neighs: Vec<_> = self.face(to_keep.face).adjacent_edges().map(|e| e.fix()).collect();
             for n in neighs {
                 self.edges[n].face = to_keep.face
             }
-            self.remove_face(to_remove.face);
+            if remaining_face != Some(to_keep.face) {
+                // The merged face replaces both faces
+                if let Some(ref mut stable) = self.stable {
+                    stable.faces.renew(to_keep.face);
+                }
+            }
+            let removed_data = self.remove_face(to_remove.face);
+            // The last face may have been moved into the removed face's place
+            let keep = if to_keep.face == self.faces.len() { to_remove.face } else { to_keep.face };
+            self.faces[keep].data.merge(removed_data);
         }
     }
 
-    fn remove_face(&mut self, face: FixedFaceHandle) {
-        self.faces.swap_remove(face);
+    fn remove_face(&mut self, face: FixedFaceHandle) -> F {
+        let data = self.faces.swap_remove(face).data;
+        if let Some(ref mut stable) = self.stable {
+            stable.faces.swap_remove(face);
+        }
         if self.faces.len() > face {
             let neighs: Vec<_> = self.face(face).adjacent_edges().map(|e| e.fix()).collect();
             for n in neighs {
                 self.edges[n].face = face;
             }
         }
+        data
     }
 
     fn swap_out_edge(&mut self, edge_handle: FixedEdgeHandle) {
         self.edges.swap_remove(edge_handle);
+        if let Some(ref mut stable) = self.stable {
+            stable.edges.swap_remove(edge_handle);
+        }
         if self.edges.len() > edge_handle {
             // Update edge index
             let old_handle = self.edges.len();
@@ -374,10 +616,18 @@ impl <V> DCEL<V> {
         let edge_index = self.connect_edge_to_edge(prev_edge_handle, next_edge_handle);
         
         let new_face = self.num_faces();
-
-        self.faces.push(FaceEntry {
-            adjacent_edge: Some(edge_index)
-        });
+        let split_face = self.edges[edge_index].face;
+
+        let mut new_entry = FaceEntry::new(Some(edge_index));
+        self.faces[split_face].data.split(&mut new_entry.data);
+        self.faces.push(new_entry);
+        if let Some(ref mut stable) = self.stable {
+            stable.faces.push();
+            // The split face has shrunk, only the outer face keeps its handle
+            if split_face != 0 {
+                stable.faces.renew(split_face);
+            }
+        }
 
         // Set the face to the left of the new edge
         let mut cur_edge = edge_index;
@@ -398,22 +648,6 @@ impl <V> DCEL<V> {
         self.vertices[handle].data = data;
     }
 
-    pub fn edges(&self) -> EdgesIterator<V> {
-        EdgesIterator::new(&self)
-    }
-
-    pub fn vertices(&self) -> VerticesIterator<V> {
-        VerticesIterator::new(&self)
-    }
-
-    pub fn fixed_vertices(&self) -> FixedVerticesIterator {
-        (0 .. self.num_vertices())
-    }
-
-    pub fn faces(&self) -> FacesIterator<V> {
-        FacesIterator::new(&self)
-    }
-
     pub fn flip_cw(&mut self, e: FixedEdgeHandle) {
         let en = self.edges[e].next;
         let ep = self.edges[e].prev;
@@ -446,39 +680,31 @@ impl <V> DCEL<V> {
 
         self.edges[tp].face = self.edges[e].face;
         self.edges[ep].face = self.edges[t].face;
-    }
 
-    #[doc(hidden)]
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        for (index, edge) in self.edges.iter().enumerate() {
-            println!("edge {}: {:#?}", index, edge);
+        let (f0, f1) = (self.edges[e].face, self.edges[t].face);
+        {
+            let (face0, face1) = get_two_mut(&mut self.faces, f0, f1);
+            face0.data.flip(&mut face1.data);
         }
-        for (index, vertex) in self.vertices.iter().enumerate() {
-            println!("vertex {}: {:?}", index, vertex.out_edge);
-        }
-        for (index, face) in self.faces.iter().enumerate() {
-            println!("face {}: {:?}", index, face);
+        self.edge_data[e / 2].flip();
+        if let Some(ref mut stable) = self.stable {
+            // The flipped edge and both faces refer to different elements now
+            stable.edges.renew(e);
+            stable.edges.renew(t);
+            stable.faces.renew(f0);
+            stable.faces.renew(f1);
         }
     }
+}
 
-    #[cfg(test)]
-    pub fn sanity_check(&self) {
-        for (index, face) in self.faces.iter().enumerate() {
-            if let Some(adj) = face.adjacent_edge {
-                assert_eq!(self.edges[adj].face, index);
-            }
-        }
-        for (index, vertex) in self.vertices.iter().enumerate() {
-            if let Some(out_edge) = vertex.out_edge {
-                assert_eq!(self.edges[out_edge].origin, index);
-            }
-        }
-        for edge in self.edges() {
-            assert_eq!(edge, edge.o_next().o_prev());
-            assert_eq!(edge, edge.o_prev().o_next());
-            assert_eq!(edge, edge.sym().sym());
-        }
+fn get_two_mut<T>(slice: &mut [T], i0: usize, i1: usize) -> (&mut T, &mut T) {
+    assert!(i0 != i1);
+    if i0 < i1 {
+        let (left, right) = slice.split_at_mut(i1);
+        (&mut left[i0], &mut right[0])
+    } else {
+        let (left, right) = slice.split_at_mut(i0);
+        (&mut right[0], &mut left[i1])
     }
 }
 
@@ -487,20 +713,20 @@ impl <V> DCEL<V> {
 /// The iterator will traverse the edges in oriented order.
 /// This order is counterclockwise for right handed coordinate systems
 /// or clockwise for left handed systems.
-pub struct ONextIterator<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct ONextIterator<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     cur_until: Option<(FixedEdgeHandle, FixedEdgeHandle)>
 }
 
-impl <'a, V> ONextIterator<'a, V> where V: 'a {
-    fn new_empty(dcel: &'a DCEL<V>) -> ONextIterator<'a, V> {
+impl <'a, V, E, F> ONextIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new_empty(dcel: &'a DCEL<V, E, F>) -> ONextIterator<'a, V, E, F> {
         ONextIterator {
             dcel: dcel,
             cur_until: None,
         }
     }
 
-    fn new(dcel: &'a DCEL<V>, edge: FixedEdgeHandle) -> ONextIterator<'a, V> {
+    fn new(dcel: &'a DCEL<V, E, F>, edge: FixedEdgeHandle) -> ONextIterator<'a, V, E, F> {
         let edge = dcel.edge(edge);
         ONextIterator {
             dcel: dcel,
@@ -509,10 +735,10 @@ impl <'a, V> ONextIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Iterator for ONextIterator<'a, V> where V: 'a {
-    type Item = EdgeHandle<'a, V>;
+impl <'a, V, E, F> Iterator for ONextIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    type Item = EdgeHandle<'a, V, E, F>;
 
-    fn next(&mut self) -> Option<EdgeHandle<'a, V>> {
+    fn next(&mut self) -> Option<EdgeHandle<'a, V, E, F>> {
         if let Some((cur, until)) = self.cur_until {
             let cur_handle = self.dcel.edge(cur);
             if cur == until {
@@ -528,8 +754,8 @@ impl <'a, V> Iterator for ONextIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> DoubleEndedIterator for ONextIterator<'a, V> where V: 'a {
-    fn next_back(&mut self) -> Option<EdgeHandle<'a, V>> {
+impl <'a, V, E, F> DoubleEndedIterator for ONextIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn next_back(&mut self) -> Option<EdgeHandle<'a, V, E, F>> {
         if let Some((cur, until)) = self.cur_until {
             let until_handle = self.dcel.edge(until);
             if cur == until {
@@ -550,13 +776,13 @@ impl <'a, V> DoubleEndedIterator for ONextIterator<'a, V> where V: 'a {
 /// The edges will be iterated in counterclockwise order. Note that
 /// this assumes that you use a right handed coordinate system,
 /// otherwise the sense of orientation is inverted.
-pub struct CCWIterator<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct CCWIterator<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     cur_until: Option<(FixedEdgeHandle, FixedEdgeHandle)>,
 }
 
-impl <'a, V> CCWIterator<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>, vertex: FixedVertexHandle) -> CCWIterator<'a, V> {
+impl <'a, V, E, F> CCWIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>, vertex: FixedVertexHandle) -> CCWIterator<'a, V, E, F> {
         let cur_until = if let Some(edge) = dcel.vertex(vertex).out_edge() {
             Some((edge.ccw().fix(), edge.fix()))
         } else {
@@ -568,7 +794,7 @@ impl <'a, V> CCWIterator<'a, V> where V: 'a {
         }
     }
 
-    fn from_edge(dcel: &'a DCEL<V>, edge: FixedEdgeHandle) -> CCWIterator<'a, V> {
+    fn from_edge(dcel: &'a DCEL<V, E, F>, edge: FixedEdgeHandle) -> CCWIterator<'a, V, E, F> {
         let edge = dcel.edge(edge);
         CCWIterator {
             dcel: dcel,
@@ -577,10 +803,10 @@ impl <'a, V> CCWIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Iterator for CCWIterator<'a, V> where V: 'a {
-    type Item = EdgeHandle<'a, V>;
+impl <'a, V, E, F> Iterator for CCWIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    type Item = EdgeHandle<'a, V, E, F>;
 
-    fn next(&mut self) -> Option<EdgeHandle<'a, V>> {
+    fn next(&mut self) -> Option<EdgeHandle<'a, V, E, F>> {
         if let Some((cur, until)) = self.cur_until {
             let cur_handle = self.dcel.edge(cur);
             if cur == until {
@@ -596,8 +822,8 @@ impl <'a, V> Iterator for CCWIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> DoubleEndedIterator for CCWIterator<'a, V> where V: 'a {
-    fn next_back(&mut self) -> Option<EdgeHandle<'a, V>> {
+impl <'a, V, E, F> DoubleEndedIterator for CCWIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn next_back(&mut self) -> Option<EdgeHandle<'a, V, E, F>> {
         if let Some((cur, until)) = self.cur_until {
             let until_handle = self.dcel.edge(until);
             if cur == until {
@@ -614,13 +840,13 @@ impl <'a, V> DoubleEndedIterator for CCWIterator<'a, V> where V: 'a {
 }
 
 
-pub struct FacesIterator<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct FacesIterator<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     current: FixedFaceHandle,
 }
 
-impl <'a, V> FacesIterator<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>) -> FacesIterator<'a, V> {
+impl <'a, V, E, F> FacesIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>) -> FacesIterator<'a, V, E, F> {
         FacesIterator {
             dcel: dcel,
             current: 0,
@@ -628,10 +854,10 @@ impl <'a, V> FacesIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Iterator for FacesIterator<'a, V> where V: 'a {
-    type Item = FaceHandle<'a, V>;
+impl <'a, V, E, F> Iterator for FacesIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    type Item = FaceHandle<'a, V, E, F>;
     
-    fn next(&mut self) -> Option<FaceHandle<'a, V>> {
+    fn next(&mut self) -> Option<FaceHandle<'a, V, E, F>> {
         if self.current < self.dcel.num_faces() {
             let result = FaceHandle::new(self.dcel, self.current);
             self.current += 1;
@@ -644,13 +870,13 @@ impl <'a, V> Iterator for FacesIterator<'a, V> where V: 'a {
 
 type FixedVerticesIterator = ::std::ops::Range<usize>;
 
-pub struct VerticesIterator<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct VerticesIterator<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     current: FixedVertexHandle,
 }
 
-impl <'a, V> VerticesIterator<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>) -> VerticesIterator<'a, V> {
+impl <'a, V, E, F> VerticesIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>) -> VerticesIterator<'a, V, E, F> {
         VerticesIterator {
             dcel: dcel,
             current: 0,
@@ -658,10 +884,10 @@ impl <'a, V> VerticesIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Iterator for VerticesIterator<'a, V> where V: 'a {
-    type Item = VertexHandle<'a, V>;
+impl <'a, V, E, F> Iterator for VerticesIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    type Item = VertexHandle<'a, V, E, F>;
     
-    fn next(&mut self) -> Option<VertexHandle<'a, V>> {
+    fn next(&mut self) -> Option<VertexHandle<'a, V, E, F>> {
         if self.current < self.dcel.num_vertices() {
             let result = VertexHandle::new(self.dcel, self.current);
             self.current += 1;
@@ -672,13 +898,13 @@ impl <'a, V> Iterator for VerticesIterator<'a, V> where V: 'a {
     }
 }
 
-pub struct EdgesIterator<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct EdgesIterator<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     current: FixedEdgeHandle,
 }
 
-impl <'a, V> EdgesIterator<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>) -> Self {
+impl <'a, V, E, F> EdgesIterator<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>) -> Self {
         EdgesIterator {
             dcel: dcel,
             current: 0
@@ -686,10 +912,10 @@ impl <'a, V> EdgesIterator<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Iterator for EdgesIterator<'a, V> {
-    type Item = EdgeHandle<'a, V>;
+impl <'a, V, E, F> Iterator for EdgesIterator<'a, V, E, F> {
+    type Item = EdgeHandle<'a, V, E, F>;
 
-    fn next(&mut self) -> Option<EdgeHandle<'a, V>> {
+    fn next(&mut self) -> Option<EdgeHandle<'a, V, E, F>> {
         if let Some(edge) = self.dcel.edges.get(self.current) {
             let twin = edge.twin;
             self.current += 1;
@@ -709,8 +935,8 @@ impl <'a, V> Iterator for EdgesIterator<'a, V> {
 /// This handle has methods that can be used to
 /// retrieve the edges neighboring edges, faces
 /// and vertices.
-pub struct EdgeHandle<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct EdgeHandle<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     handle: FixedEdgeHandle,
 }
 
@@ -718,8 +944,8 @@ pub struct EdgeHandle<'a, V> where V: 'a {
 ///
 /// This handle has methods that can be used to retrieve
 /// the vertice's outgoing edges.
-pub struct VertexHandle<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct VertexHandle<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     handle: FixedVertexHandle,
 }
 
@@ -727,27 +953,27 @@ pub struct VertexHandle<'a, V> where V: 'a {
 ///
 /// This handle has methods that can be used to get all
 /// edges adjacent to this face.
-pub struct FaceHandle<'a, V> where V: 'a {
-    dcel: &'a DCEL<V>,
+pub struct FaceHandle<'a, V, E = (), F = ()> where V: 'a, E: 'a, F: 'a {
+    dcel: &'a DCEL<V, E, F>,
     handle: FixedFaceHandle,
 }
 
-impl <'a, V> ::std::fmt::Debug for VertexHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> ::std::fmt::Debug for VertexHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         write!(f, "VertexHandle({:?})", self.handle)
     }
 }
 
-impl <'a, V> PartialEq for VertexHandle<'a, V> where V: 'a {
-    fn eq(&self, other: &VertexHandle<'a, V>) -> bool {
+impl <'a, V, E, F> PartialEq for VertexHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn eq(&self, other: &VertexHandle<'a, V, E, F>) -> bool {
         self.handle == other.handle
     }
 }
 
-impl <'a, V> Copy for VertexHandle<'a, V> where V: 'a { }
+impl <'a, V, E, F> Copy for VertexHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a { }
 
-impl <'a, V> VertexHandle<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>, handle: FixedVertexHandle) -> VertexHandle<'a, V> {
+impl <'a, V, E, F> VertexHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>, handle: FixedVertexHandle) -> VertexHandle<'a, V, E, F> {
         VertexHandle {
             dcel: dcel,
             handle: handle,
@@ -757,7 +983,7 @@ impl <'a, V> VertexHandle<'a, V> where V: 'a {
     /// Returns an outgoing edge.
     ///
     /// If the vertex has multiple outgoing edges, any of them is returned.
-    pub fn out_edge(&self) -> Option<EdgeHandle<'a, V>> {
+    pub fn out_edge(&self) -> Option<EdgeHandle<'a, V, E, F>> {
         self.dcel.vertices[self.handle].out_edge.map(|e| self.dcel.edge(e))
     }
 
@@ -765,7 +991,7 @@ impl <'a, V> VertexHandle<'a, V> where V: 'a {
     ///
     /// Note that this assumes that you use a right handed coordinate system,
     /// otherwise the sense of orientation is inverted.
-    pub fn ccw_out_edges(&self) -> CCWIterator<'a, V> {
+    pub fn ccw_out_edges(&self) -> CCWIterator<'a, V, E, F> {
         CCWIterator::new(self.dcel, self.handle)
     }
 
@@ -775,13 +1001,13 @@ impl <'a, V> VertexHandle<'a, V> where V: 'a {
     }
 }
 
-impl <'a, V> Clone for VertexHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> Clone for VertexHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn clone(&self) -> Self {
         VertexHandle::new(self.dcel, self.handle)
     }
 }
 
-impl <'a, V> ::std::ops::Deref for VertexHandle<'a, V> {
+impl <'a, V, E, F> ::std::ops::Deref for VertexHandle<'a, V, E, F> {
     type Target = V;
     
     fn deref(&self) -> &V {
@@ -789,30 +1015,30 @@ impl <'a, V> ::std::ops::Deref for VertexHandle<'a, V> {
     }
 }
 
-impl <'a, V> Copy for EdgeHandle<'a, V> where V: 'a { }
+impl <'a, V, E, F> Copy for EdgeHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a { }
 
-impl <'a, V> Clone for EdgeHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> Clone for EdgeHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn clone(&self) -> Self {
         EdgeHandle::new(self.dcel, self.handle)
     }
 }
 
-impl <'a, V> PartialEq for EdgeHandle<'a, V> where V: 'a {
-    fn eq(&self, other: &EdgeHandle<'a, V>) -> bool {
+impl <'a, V, E, F> PartialEq for EdgeHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn eq(&self, other: &EdgeHandle<'a, V, E, F>) -> bool {
         self.handle == other.handle
     }
 }
 
-impl <'a, V> ::std::fmt::Debug for EdgeHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> ::std::fmt::Debug for EdgeHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         write!(f, "EdgeHandle({:?})", self.handle)
     }
 }
 
-pub fn from_neighbors<'a, V> (
-    dcel: &'a DCEL<V>,
+pub fn from_neighbors<'a, V, E, F> (
+    dcel: &'a DCEL<V, E, F>,
     from: FixedVertexHandle,
-    to: FixedVertexHandle) -> Option<EdgeHandle<'a, V>> {
+    to: FixedVertexHandle) -> Option<EdgeHandle<'a, V, E, F>> {
     let vertex = dcel.vertex(from);
     for edge in vertex.ccw_out_edges() {
         if edge.to().fix() == to {
@@ -822,9 +1048,9 @@ pub fn from_neighbors<'a, V> (
     None
 }
 
-impl <'a, V> EdgeHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> EdgeHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
 
-    fn new(dcel: &'a DCEL<V>, handle: FixedEdgeHandle) -> Self {
+    fn new(dcel: &'a DCEL<V, E, F>, handle: FixedEdgeHandle) -> Self {
         EdgeHandle {
             dcel: dcel,
             handle: handle,
@@ -837,7 +1063,7 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     }
 
     /// Returns the edge's source vertex.
-    pub fn from(&self) -> VertexHandle<'a, V> {
+    pub fn from(&self) -> VertexHandle<'a, V, E, F> {
         let edge = self.dcel.edges[self.handle];
         VertexHandle::new(self.dcel, edge.origin)
     }
@@ -849,7 +1075,7 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     /// this edge is the predecessor of the oriented next edge.
     /// "Oriented" means counterclockwise for right handed 
     /// coordinate systems.
-    pub fn o_next(&self) -> EdgeHandle<'a, V> {
+    pub fn o_next(&self) -> EdgeHandle<'a, V, E, F> {
         EdgeHandle::new(self.dcel, self.dcel.edges[self.handle].next)
     }
 
@@ -860,7 +1086,7 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     /// this edge is the successor of the oriented previous edge.
     /// "Oriented" means counterclockwise for right handed
     /// coordinate systems.
-    pub fn o_prev(&self) -> EdgeHandle<'a, V> {
+    pub fn o_prev(&self) -> EdgeHandle<'a, V, E, F> {
         EdgeHandle::new(self.dcel, self.dcel.edges[self.handle].prev)
     }
 
@@ -870,22 +1096,22 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     /// The face's edges will be traversed in oriented order.
     /// This order is counterclockwise for right handed coordinate
     /// systems or clockwise for left handed systems.
-    pub fn o_next_iterator(&self) -> ONextIterator<'a, V> {
+    pub fn o_next_iterator(&self) -> ONextIterator<'a, V, E, F> {
         ONextIterator::new(self.dcel, self.handle)
     }
 
     /// Returns the edges destination vertex.
-    pub fn to(&self) -> VertexHandle<'a, V> {
+    pub fn to(&self) -> VertexHandle<'a, V, E, F> {
         self.sym().from()
     }
     
     /// Returns the face located to the left of this edge.
-    pub fn face(&self) -> FaceHandle<'a, V> {
+    pub fn face(&self) -> FaceHandle<'a, V, E, F> {
         self.dcel.face(self.dcel.edges[self.handle].face)
     }
 
     /// Returns this edge's mirror edge.
-    pub fn sym(&self) -> EdgeHandle<'a, V> {
+    pub fn sym(&self) -> EdgeHandle<'a, V, E, F> {
         EdgeHandle {
             dcel: self.dcel,
             handle: self.dcel.edges[self.handle].twin,
@@ -896,7 +1122,7 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     ///
     /// Note that this assumes that you use a right handed coordinate system,
     /// otherwise the sense of orientation is inverted.
-    pub fn cw(&self) -> EdgeHandle<'a, V> {
+    pub fn cw(&self) -> EdgeHandle<'a, V, E, F> {
         let twin = self.sym().handle;
         EdgeHandle {
             dcel: self.dcel,
@@ -908,45 +1134,52 @@ impl <'a, V> EdgeHandle<'a, V> where V: 'a {
     ///
     /// Note that this assumes that you use a right handed coordinate system,
     /// otherwise the sense of orientation is inverted.
-    pub fn ccw(&self) -> EdgeHandle<'a, V> {
+    pub fn ccw(&self) -> EdgeHandle<'a, V, E, F> {
         EdgeHandle {
             dcel: self.dcel,
             handle: self.dcel.edges[self.handle].prev,
         }.sym()
     }
 
+    /// Returns the data associated with this edge.
+    ///
+    /// An edge and its mirror edge share the same data.
+    pub fn data(&self) -> &'a E {
+        &self.dcel.edge_data[self.handle / 2]
+    }
+
     /// Returns an iterator over all edges in counter clockwise
     /// order.
     ///
     /// Note that this assumes that you use a right handed coordinate system,
     /// otherwise the sense of orientation is inverted.
-    pub fn ccw_iter(&self) -> CCWIterator<'a, V> {
+    pub fn ccw_iter(&self) -> CCWIterator<'a, V, E, F> {
         CCWIterator::from_edge(self.dcel, self.handle)
     }
 }
 
-impl <'a, V> Copy for FaceHandle<'a, V> where V: 'a { }
+impl <'a, V, E, F> Copy for FaceHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a { }
 
-impl <'a, V> Clone for FaceHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> Clone for FaceHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn clone(&self) -> Self {
         FaceHandle::new(self.dcel, self.handle)
     }
 }
 
-impl <'a, V> PartialEq for FaceHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> PartialEq for FaceHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn eq(&self, other: &Self) -> bool {
         self.handle == other.handle
     }
 }
 
-impl <'a, V> ::std::fmt::Debug for FaceHandle<'a, V> where V: 'a {
+impl <'a, V, E, F> ::std::fmt::Debug for FaceHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         write!(f, "FaceHandle({:?})", self.handle)
     }
 }
 
-impl <'a, V> FaceHandle<'a, V> where V: 'a {
-    fn new(dcel: &'a DCEL<V>, handle: FixedFaceHandle) -> FaceHandle<'a, V> {
+impl <'a, V, E, F> FaceHandle<'a, V, E, F> where V: 'a, E: 'a, F: 'a {
+    fn new(dcel: &'a DCEL<V, E, F>, handle: FixedFaceHandle) -> FaceHandle<'a, V, E, F> {
         FaceHandle {
             dcel: dcel,
             handle: handle,
@@ -957,7 +1190,7 @@ impl <'a, V> FaceHandle<'a, V> where V: 'a {
     ///
     /// # Panic
     /// This method will panic if the face does not form a triangle.
-    pub fn as_triangle(&self) -> [VertexHandle<'a, V>; 3] {
+    pub fn as_triangle(&self) -> [VertexHandle<'a, V, E, F>; 3] {
         let adjacent = self.dcel.faces[self.handle].adjacent_edge
             .expect("Face has no adjacent edge");
         let edge = self.dcel.edge(adjacent);
@@ -967,10 +1200,15 @@ impl <'a, V> FaceHandle<'a, V> where V: 'a {
         [prev.from(), edge.from(), edge.to()]
     }
 
+    /// Returns the data associated with this face.
+    pub fn data(&self) -> &'a F {
+        &self.dcel.faces[self.handle].data
+    }
+
     /// Returns an edge that is adjacent to this face.
     ///
     /// If this face has multiple adjacent edges, any of them is returned.
-    pub fn adjacent_edge(&self) -> Option<EdgeHandle<'a, V>> {
+    pub fn adjacent_edge(&self) -> Option<EdgeHandle<'a, V, E, F>> {
         self.dcel.faces[self.handle].adjacent_edge.map(
             |e| EdgeHandle::new(self.dcel, e))
     }
@@ -980,7 +1218,7 @@ impl <'a, V> FaceHandle<'a, V> where V: 'a {
     /// The edges are traversed in oriented order.
     /// This order will be counterclockwise for right handed coordinate
     /// system or clockwise for left handed systems.
-    pub fn adjacent_edges(&self) -> ONextIterator<'a, V> {
+    pub fn adjacent_edges(&self) -> ONextIterator<'a, V, E, F> {
         if let Some(adj) = self.dcel.faces[self.handle].adjacent_edge {
             ONextIterator::new(self.dcel, adj)
         } else {
@@ -1001,7 +1239,7 @@ mod test {
 
     #[test]
     fn test_create_triangle() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let v0 = dcel.insert_vertex(());
         let v1 = dcel.insert_vertex(());
         let v2 = dcel.insert_vertex(());
@@ -1042,7 +1280,7 @@ mod test {
 
     #[test]
     fn test_flip() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let v0 = dcel.insert_vertex(());
         let v1 = dcel.insert_vertex(());
         let v2 = dcel.insert_vertex(());
@@ -1083,7 +1321,7 @@ mod test {
 
     #[test]
     fn test_split() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let v0 = dcel.insert_vertex(());
         let v1 = dcel.insert_vertex(());
         let v2 = dcel.insert_vertex(());
@@ -1141,7 +1379,7 @@ mod test {
 
     #[test]
     fn test_cw_ccw() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let v0 = dcel.insert_vertex(());
         let v1 = dcel.insert_vertex(());
         let v2 = dcel.insert_vertex(());
@@ -1160,7 +1398,7 @@ mod test {
 
     #[test]
     fn pentagon_test() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let mut v = Vec::new();
         for _ in 0 .. 5 {
             v.push(dcel.insert_vertex(()));
@@ -1182,7 +1420,7 @@ mod test {
 
     #[test]
     fn test_ccw_iterator() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let mut vs = Vec::new();
         let central = dcel.insert_vertex(());
         assert_eq!(dcel.vertex(central).ccw_out_edges().next(), None);
@@ -1212,7 +1450,7 @@ mod test {
 
     #[test]
     fn test_o_next_iterator() {
-        let mut dcel = DCEL::new();
+        let mut dcel: DCEL<_> = DCEL::new();
         let mut vs = Vec::new();
         for _ in 0 .. 5 {
             vs.push(dcel.insert_vertex(()));
@@ -7,7 +7,7 @@
 // except according to those terms.
 
 use num::{One, Float, Zero, one, zero};
-use traits::{SpatialObject, HasPosition2D, SpadeFloat, HasPosition};
+use traits::{SpatialObject, HasPosition2D, SpadeFloat, HasPosition, HasPositionMut};
 use point_traits::{PointN, PointNExtensions, TwoDimensional, ThreeDimensional};
 use kernels::{DelaunayKernel, TrivialKernel, FloatKernel};
 use primitives::{SimpleEdge, SimpleTriangle};
@@ -18,6 +18,7 @@ use smallvec::SmallVec;
 use self::dcel::*;
 use self::delaunay_locate::*;
 use delaunay::*;
+use delaunay::interpolation::weighted_sum;
 
 /// Type shorthand for a delaunay triangulation with `f64` coordinates that uses `FloatKernel`
 /// for geometric calculations.
@@ -44,6 +45,9 @@ pub enum PositionInTriangulation<V: Copy, F: Copy, E: Copy> {
     NoTriangulationPresent,
 }
 
+type LocateResult<'a, V, ED, FD> = PositionInTriangulation<
+    VertexHandle<'a, V, ED, FD>, FaceHandle<'a, V, ED, FD>, EdgeHandle<'a, V, ED, FD>>;
+
 /// A two dimensional delaunay triangulation.
 /// 
 /// A delaunay triangulation is a special triangulation of a set of points that fulfills some
@@ -110,12 +114,15 @@ pub enum PositionInTriangulation<V: Copy, F: Copy, E: Copy> {
 ///  - `nn_interpolation_c1_farin(..)`
 ///
 /// # Type parameters
-/// `DelaunayTriangulation` has three type parameters: `V`, `K` and `L`.
+/// `DelaunayTriangulation` has five type parameters: `V`, `K`, `L`, `ED` and `FD`.
 /// `V: HasPosition2D` defines the delaunay's vertex type. 
 /// `K: DelaunayKernel` defines the triangulations calculation kernel.
 /// For more information, see `spade::kernels`.
 /// `L` Defines the delaunay locate structure.
 /// For more information, see `DelaunayLocateStructure`.
+/// `ED: EdgeData` and `FD: FaceData` define optional data stored for each
+/// undirected edge and face, both default to `()`.
+/// For more information, see `EdgeData` and `FaceData`.
 ///
 /// # Performance
 /// Performance of insertion, interpolation and other queries heavily relies on
@@ -128,23 +135,26 @@ pub enum PositionInTriangulation<V: Copy, F: Copy, E: Copy> {
 /// Since version 1.1, spade uses the result of the last query as hint for the next query when
 /// using `DelaunayWalkLocate` as locate strategy. That means: Subsequent queries - like insertion, interpolation or nearest neighbor
 /// queries - will run in O(1) if the query locations are close to each other.
-pub struct DelaunayTriangulation<V, K, L = DelaunayTreeLocate<<V as HasPosition>::Point>>
+pub struct DelaunayTriangulation<V, K, L = DelaunayTreeLocate<<V as HasPosition>::Point>,
+                                 ED = (), FD = ()>
     where V: HasPosition2D,
           V::Point: TwoDimensional,
           L: DelaunayLocateStructure<V::Point>,
 {
     __kernel: PhantomData<K>,
-    s: DCEL<V>,
+    s: DCEL<V, ED, FD>,
     all_points_on_line: bool,
     lookup: L,
 }
 
-impl<V, K, L> Clone for DelaunayTriangulation<V, K, L> 
+impl<V, K, L, ED, FD> Clone for DelaunayTriangulation<V, K, L, ED, FD> 
     where V: HasPosition2D + Clone,
           V::Point: TwoDimensional,
-          L: DelaunayLocateStructure<V::Point> {
+          L: DelaunayLocateStructure<V::Point>,
+          ED: Clone,
+          FD: Clone {
 
-    fn clone(&self) -> DelaunayTriangulation<V, K, L> {
+    fn clone(&self) -> DelaunayTriangulation<V, K, L, ED, FD> {
         DelaunayTriangulation {
           __kernel: Default::default(),
             s: self.s.clone(),
@@ -179,7 +189,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     where V: HasPosition2D,
           K: DelaunayKernel<<V::Point as PointN>::Scalar>,
           V::Point: TwoDimensional,
-          L: DelaunayLocateStructure<V::Point>
+          L: DelaunayLocateStructure<V::Point>,
 {
 
     /// Creates a new Delaunay triangulation.
@@ -204,6 +214,24 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     /// Usually, the omitted types (the triangulation's vertex type) can be inferred from a call
     /// to `insert`.
     pub fn new() -> DelaunayTriangulation<V, K, L> {
+        DelaunayTriangulation::new_with_data()
+    }
+}
+
+impl <V, K, L, ED, FD> DelaunayTriangulation<V, K, L, ED, FD>
+    where V: HasPosition2D,
+          K: DelaunayKernel<<V::Point as PointN>::Scalar>,
+          V::Point: TwoDimensional,
+          L: DelaunayLocateStructure<V::Point>,
+          ED: EdgeData,
+          FD: FaceData,
+{
+
+    /// Creates a new Delaunay triangulation with custom edge and face data.
+    ///
+    /// Works like `new`, but allows to choose the edge and face data types `ED` and `FD`.
+    /// See `EdgeData` and `FaceData` for more information.
+    pub fn new_with_data() -> DelaunayTriangulation<V, K, L, ED, FD> {
         DelaunayTriangulation {
             __kernel: Default::default(),
             s: DCEL::new(),
@@ -212,10 +240,111 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
         }
     }
 
+    /// Enables stable handles for this triangulation.
+    ///
+    /// Fixed handles are invalidated by removal operations. If stable handles are
+    /// enabled, the triangulation additionally tracks a `StableHandle` for each vertex,
+    /// edge and face. Stable handles remain valid after unrelated insertions and removals,
+    /// and refer to no element anymore once their element was removed or changed.
+    /// Tracking stable handles adds a small overhead to all operations that modify
+    /// the triangulation.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate nalgebra;
+    /// # extern crate spade;
+    ///
+    /// use nalgebra::Point2;
+    /// use spade::delaunay::FloatDelaunayTriangulation;
+    ///
+    /// # fn main() {
+    /// let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+    /// delaunay.enable_stable_handles();
+    /// let v0 = delaunay.insert(Point2::new(0.0, 0.0));
+    /// let v1 = delaunay.insert(Point2::new(1.0, 0.0));
+    /// let stable0 = delaunay.stable_vertex_handle(v0);
+    /// let stable1 = delaunay.stable_vertex_handle(v1);
+    /// delaunay.remove(v0);
+    /// // The removal moved v1 to another fixed handle
+    /// let v1 = delaunay.resolve_vertex_handle(stable1).unwrap();
+    /// assert_eq!(*delaunay.vertex(v1), Point2::new(1.0, 0.0));
+    /// // Handles to removed vertices are detected
+    /// assert_eq!(delaunay.resolve_vertex_handle(stable0), None);
+    /// # }
+    /// ```
+    pub fn enable_stable_handles(&mut self) {
+        self.s.enable_stable_handles();
+    }
+
+    /// Returns `true` if stable handles have been enabled.
+    pub fn has_stable_handles(&self) -> bool {
+        self.s.has_stable_handles()
+    }
+
+    /// Returns the stable handle of a vertex.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled or if the fixed handle is invalid.
+    pub fn stable_vertex_handle(&self, handle: FixedVertexHandle) -> StableVertexHandle {
+        self.s.stable_vertex(handle)
+    }
+
+    /// Returns the stable handle of a directed edge.
+    ///
+    /// Edges whose end points change, e.g. due to an edge flip or split, are treated as
+    /// removed and receive new stable handles.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled or if the fixed handle is invalid.
+    pub fn stable_edge_handle(&self, handle: FixedEdgeHandle) -> StableEdgeHandle {
+        self.s.stable_edge(handle)
+    }
+
+    /// Returns the stable handle of a face.
+    ///
+    /// Faces that are split, merged or flipped are treated as removed and receive new
+    /// stable handles. The infinite face keeps its handle.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled or if the fixed handle is invalid.
+    pub fn stable_face_handle(&self, handle: FixedFaceHandle) -> StableFaceHandle {
+        self.s.stable_face(handle)
+    }
+
+    /// Returns the current fixed handle of a vertex given by its stable handle.
+    ///
+    /// Returns `None` if the vertex has been removed.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled.
+    pub fn resolve_vertex_handle(&self, handle: StableVertexHandle) -> Option<FixedVertexHandle> {
+        self.s.resolve_vertex(handle)
+    }
+
+    /// Returns the current fixed handle of an edge given by its stable handle.
+    ///
+    /// Returns `None` if the edge has been removed, flipped or split.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled.
+    pub fn resolve_edge_handle(&self, handle: StableEdgeHandle) -> Option<FixedEdgeHandle> {
+        self.s.resolve_edge(handle)
+    }
+
+    /// Returns the current fixed handle of a face given by its stable handle.
+    ///
+    /// Returns `None` if the face has been removed or changed.
+    ///
+    /// # Panics
+    /// Panics if stable handles are not enabled.
+    pub fn resolve_face_handle(&self, handle: StableFaceHandle) -> Option<FixedFaceHandle> {
+        self.s.resolve_face(handle)
+    }
+
     /// Creates a dynamic vertex handle from a fixed vertex handle.
     /// May panic if the handle was invalidated by a previous vertex
     /// removal.
-    pub fn vertex(&self, handle: FixedVertexHandle) -> VertexHandle<V> {
+    pub fn vertex(&self, handle: FixedVertexHandle) -> VertexHandle<V, ED, FD> {
         self.s.vertex(handle)
     }
 
@@ -225,17 +354,59 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
         self.s.vertex_mut(handle)
     }
 
+    /// Returns a mutable reference to the data of a face.
+    ///
+    /// Face data can be read with `FaceHandle::data()`. See `FaceData` for more
+    /// information on how face data is initialized and updated.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate nalgebra;
+    /// # extern crate spade;
+    ///
+    /// use nalgebra::Point2;
+    /// use spade::delaunay::{DelaunayTriangulation, DelaunayWalkLocate, FaceData};
+    /// use spade::kernels::FloatKernel;
+    ///
+    /// #[derive(Default)]
+    /// struct Visited(bool);
+    ///
+    /// impl FaceData for Visited { }
+    ///
+    /// # fn main() {
+    /// let mut delaunay: DelaunayTriangulation<_, FloatKernel, DelaunayWalkLocate, (), Visited>
+    ///     = DelaunayTriangulation::new_with_data();
+    /// delaunay.insert(Point2::new(0.0, 0.0));
+    /// delaunay.insert(Point2::new(1.0, 0.0));
+    /// delaunay.insert(Point2::new(0.0, 1.0));
+    /// let face = delaunay.triangles().next().unwrap().fix();
+    /// delaunay.face_data_mut(face).0 = true;
+    /// assert!(delaunay.face(face).data().0);
+    /// # }
+    /// ```
+    pub fn face_data_mut(&mut self, handle: FixedFaceHandle) -> &mut FD {
+        self.s.face_data_mut(handle)
+    }
+
+    /// Returns a mutable reference to the data of an undirected edge.
+    ///
+    /// Both directed edges of an undirected edge share the same data. Edge data can be
+    /// read with `EdgeHandle::data()`, see `EdgeData` for more information.
+    pub fn edge_data_mut(&mut self, handle: FixedEdgeHandle) -> &mut ED {
+        self.s.edge_data_mut(handle)
+    }
+
     /// Creates a dynamic face handle from a fixed face handle.
     /// May panic if the faces was invalidated by a previous vertex
     /// removal.
-    pub fn face(&self, handle: FixedFaceHandle) -> FaceHandle<V> {
+    pub fn face(&self, handle: FixedFaceHandle) -> FaceHandle<V, ED, FD> {
         self.s.face(handle)
     }
 
     /// Creates a dynamic edge handle from a fixed edge handle.
     /// May panic if the handle was invalidated by a previous vertex
     /// removal.
-    pub fn edge(&self, handle: FixedEdgeHandle) -> EdgeHandle<V> {
+    pub fn edge(&self, handle: FixedEdgeHandle) -> EdgeHandle<V, ED, FD> {
         self.s.edge(handle)
     }
 
@@ -263,7 +434,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     }
 
     /// Returns an iterator over all triangles.
-    pub fn triangles(&self) -> FacesIterator<V> {
+    pub fn triangles(&self) -> FacesIterator<V, ED, FD> {
         let mut result = self.s.faces();
         // Skip the outer face
         result.next();
@@ -271,17 +442,17 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     }
 
     /// Returns an iterator over all edges.
-    pub fn edges(&self) -> EdgesIterator<V> {
+    pub fn edges(&self) -> EdgesIterator<V, ED, FD> {
         self.s.edges()
     }
 
     /// Returns an iterator over all vertices.
-    pub fn vertices(&self) -> VerticesIterator<V> {
+    pub fn vertices(&self) -> VerticesIterator<V, ED, FD> {
         self.s.vertices()
     }
 
     /// Returns a handle to the infinite face.
-    pub fn infinite_face(&self) -> FaceHandle<V> {
+    pub fn infinite_face(&self) -> FaceHandle<V, ED, FD> {
         self.s.face(0)
     }
 
@@ -493,7 +664,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     /// Locates the nearest neighbor for a given point.
     ///
     /// Returns `None` if this triangulation is degenerate.
-    pub fn nearest_neighbor(&self, point: &V::Point) -> Option<VertexHandle<V>> {
+    pub fn nearest_neighbor(&self, point: &V::Point) -> Option<VertexHandle<V, ED, FD>> {
         if self.all_points_on_line {
             return None;
         } 
@@ -518,7 +689,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
 
     /// Returns information about the location of a point in a triangulation.
     pub fn locate(
-        &self, point: &V::Point) -> PositionInTriangulation<VertexHandle<V>, FaceHandle<V>, EdgeHandle<V>> {
+        &self, point: &V::Point) -> LocateResult<V, ED, FD> {
         self.locate_with_hint_option(point, None)
     }
 
@@ -526,7 +697,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     ///
     /// Returns `None` if the point could not be found _or if the triangulation is degenerate_. In future releases,
     /// this method might work for degenerate triangulations as well.
-    pub fn locate_vertex(&self, point: &V::Point) -> Option<VertexHandle<V>> {
+    pub fn locate_vertex(&self, point: &V::Point) -> Option<VertexHandle<V, ED, FD>> {
         if let Some(nn) = self.nearest_neighbor(point) {
             if &nn.position() == point {
                 return Some(nn);
@@ -539,11 +710,11 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     ///
     /// Additionally, a hint can be given to speed up computation. The hint should be a vertex close
     /// to the position that is being looked up.
-    pub fn locate_with_hint(&self, point: &V::Point, hint: FixedVertexHandle) -> PositionInTriangulation<VertexHandle<V>, FaceHandle<V>, EdgeHandle<V>> {
+    pub fn locate_with_hint(&self, point: &V::Point, hint: FixedVertexHandle) -> LocateResult<V, ED, FD> {
         self.locate_with_hint_option(point, Some(hint))
     }
 
-    fn locate_with_hint_option(&self, point: &V::Point, hint: Option<FixedVertexHandle>) -> PositionInTriangulation<VertexHandle<V>, FaceHandle<V>, EdgeHandle<V>> {
+    fn locate_with_hint_option(&self, point: &V::Point, hint: Option<FixedVertexHandle>) -> LocateResult<V, ED, FD> {
         use self::PositionInTriangulation::*;
         match self.locate_with_hint_option_fixed(point, hint) {
             NoTriangulationPresent => NoTriangulationPresent,
@@ -725,6 +896,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     ///
     /// # Handle invalidation
     /// This method will invalidate all vertex, edge and face handles.
+    /// Stable handles of other vertices remain valid, see `enable_stable_handles()`.
     pub fn remove(&mut self, vertex: FixedVertexHandle) -> V {
         let mut neighbors = Vec::new();
         let mut ch_removal = false;
@@ -786,6 +958,119 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
 
 
 
+    /// Moves a vertex to a new position.
+    ///
+    /// If the vertex is not part of the convex hull and its new position lies within
+    /// the polygon formed by its neighbors, the vertex is moved in place and the
+    /// delaunay property is restored by flipping edges, which runs in O(n) on average,
+    /// n denoting the vertex's degree. Otherwise, the vertex will be removed and
+    /// reinserted.
+    ///
+    /// Returns `false` and leaves the triangulation unchanged if another vertex
+    /// is already located at the new position.
+    ///
+    /// # Handle invalidation
+    /// All vertex handles, including `handle`, remain valid. This includes
+    /// stable vertex handles. Edge and face handles will be invalidated.
+    pub fn move_vertex(&mut self, handle: FixedVertexHandle, new_position: V::Point) -> bool
+        where V: HasPositionMut {
+        let old_position = (*self.s.vertex(handle)).position();
+        if old_position == new_position {
+            return true;
+        }
+        if self.can_move_within_star(handle, &new_position) {
+            self.s.vertex_mut(handle).set_position(new_position.clone());
+            self.lookup.remove_vertex_entry(&VertexEntry::new(old_position, handle));
+            self.lookup.insert_vertex_entry(VertexEntry::new(new_position, handle));
+            self.legalize_star(handle);
+            return true;
+        }
+        if self.all_points_on_line {
+            if self.s.vertices().any(|v| (*v).position() == new_position) {
+                return false;
+            }
+        } else if let PositionInTriangulation::OnPoint(_) =
+            self.locate_with_hint_option_fixed(&new_position, Some(handle)) {
+            return false;
+        }
+        // Removal will move the last vertex into the removed vertex's slot,
+        // reinsertion will add the moved vertex at the end. Swapping both
+        // vertices restores all handles.
+        let last = self.num_vertices() - 1;
+        let stable = if self.has_stable_handles() {
+            Some(self.s.stable_vertex(handle))
+        } else {
+            None
+        };
+        let mut data = self.remove(handle);
+        data.set_position(new_position);
+        let new_handle = self.insert(data);
+        debug_assert_eq!(new_handle, last);
+        if handle != last {
+            self.s.swap_vertices(handle, last);
+            for &v in &[handle, last] {
+                let pos = (*self.s.vertex(v)).position();
+                self.lookup.update_vertex_entry(VertexEntry::new(pos, v));
+            }
+        }
+        if let Some(stable) = stable {
+            self.s.rebind_stable_vertex(handle, stable);
+        }
+        true
+    }
+
+    fn can_move_within_star(&self, handle: FixedVertexHandle, new_position: &V::Point) -> bool {
+        if self.all_points_on_line {
+            return false;
+        }
+        let infinite = self.infinite_face();
+        for edge in self.s.vertex(handle).ccw_out_edges() {
+            if edge.face() == infinite {
+                return false;
+            }
+            let link_edge = SimpleEdge::new(
+                (*edge.to()).position(), (*edge.ccw().to()).position());
+            if !K::side_query(&link_edge, new_position).is_on_left_side() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn legalize_star(&mut self, handle: FixedVertexHandle) {
+        // All edges of triangles adjacent to the moved vertex may have become illegal
+        let mut todo = Vec::new();
+        for edge in self.s.vertex(handle).ccw_out_edges() {
+            todo.push(edge.fix());
+            todo.push(edge.o_next().fix());
+        }
+        while let Some(e) = todo.pop() {
+            if self.is_ch_edge(e) {
+                continue;
+            }
+            let (v0, v1, vl, vr, e1, e2, e3, e4);
+            {
+                let edge = self.s.edge(e);
+                v0 = (*edge.from()).position();
+                v1 = (*edge.to()).position();
+                vl = (*edge.ccw().to()).position();
+                vr = (*edge.cw().to()).position();
+                e1 = edge.o_next().fix();
+                e2 = edge.o_prev().fix();
+                e3 = edge.sym().o_next().fix();
+                e4 = edge.sym().o_prev().fix();
+            }
+            if K::contained_in_circumference(&v1, &v0, &vl, &vr) {
+                // The edge is illegal
+                self.s.flip_cw(e);
+                todo.push(e1);
+                todo.push(e2);
+                todo.push(e3);
+                todo.push(e4);
+            }
+        }
+    }
+
     fn fill_hole(&mut self, loop_edges: Vec<FixedEdgeHandle>) {
         let mut border_edges = HashSet::new();
         
@@ -925,17 +1210,25 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
         }
         for edge in self.edges() {
             assert!(edge.face() != edge.sym().face());
+            if !self.is_ch_edge(edge.fix()) {
+                // Check the delaunay property
+                assert!(!K::contained_in_circumference(
+                    &(*edge.to()).position(), &(*edge.from()).position(),
+                    &(*edge.ccw().to()).position(), &(*edge.cw().to()).position()));
+            }
         }
     }
 }
 
-impl <V, K> DelaunayTriangulation<V, K, DelaunayTreeLocate<V::Point>>
+impl <V, K, ED, FD> DelaunayTriangulation<V, K, DelaunayTreeLocate<V::Point>, ED, FD>
     where V: HasPosition2D,
           K: DelaunayKernel<<V::Point as PointN>::Scalar>,
-          V::Point: TwoDimensional {
+          V::Point: TwoDimensional,
+          ED: EdgeData,
+          FD: FaceData {
 
     /// Checks if the triangulation contains an object with a given coordinate.
-    pub fn lookup(&self, point: &V::Point) -> Option<VertexHandle<V>> {
+    pub fn lookup(&self, point: &V::Point) -> Option<VertexHandle<V, ED, FD>> {
         let handle = self.lookup.lookup(point);
         handle.map(|h| self.s.vertex(h.handle))
     }
@@ -954,14 +1247,14 @@ impl <V, K> DelaunayTriangulation<V, K, DelaunayTreeLocate<V::Point>>
     }
 
     // /// Returns all vertices contained in a rectangle.
-    // pub fn lookup_in_rect(&self, rect: &BoundingRect<V::Point>) -> Vec<VertexHandle<V>> {
+    // pub fn lookup_in_rect(&self, rect: &BoundingRect<V::Point>) -> Vec<VertexHandle<V, ED, FD>> {
     //     let fixed_handles = self.points.lookup_in_rectangle(rect);
     //     fixed_handles.iter().map(|entry| self.s.vertex(entry.handle)).collect()
     // }
 
     // /// Returns all vertices contained in a circle.
     // pub fn lookup_in_circle(&self, center: &V::Point, 
-    //                         radius2: &<V::Point as PointN>::Scalar) -> Vec<VertexHandle<V>> {
+    //                         radius2: &<V::Point as PointN>::Scalar) -> Vec<VertexHandle<V, ED, FD>> {
     //     let fixed_handles = self.points.lookup_in_circle(center, radius2);
     //     fixed_handles.iter().map(|entry| self.s.vertex(entry.handle)).collect()
     // }
@@ -969,11 +1262,13 @@ impl <V, K> DelaunayTriangulation<V, K, DelaunayTreeLocate<V::Point>>
 
 const INTPL_SMALLVEC_CAPACITY: usize = 8;
 
-impl <V, K, L> DelaunayTriangulation<V, K, L> 
+impl <V, K, L, ED, FD> DelaunayTriangulation<V, K, L, ED, FD> 
     where V: HasPosition2D, <V::Point as PointN>::Scalar: SpadeFloat,
           K: DelaunayKernel<<V::Point as PointN>::Scalar> ,
           L: DelaunayLocateStructure<V::Point>,
-          V::Point: TwoDimensional
+          V::Point: TwoDimensional,
+          ED: EdgeData,
+          FD: FaceData,
 {
     /// Performs a barycentric interpolation.
     /// Returns `None` if the triangulation has no triangles yet.
@@ -983,39 +1278,87 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     pub fn barycentric_interpolation<F> (&self, point: &V::Point, f: F) 
                                          -> Option<<V::Point as PointN>::Scalar> 
         where F: Fn(&V) -> <V::Point as PointN>::Scalar {
-        let vertices = match self.locate(point) {
-            PositionInTriangulation::NoTriangulationPresent => return None,
-            PositionInTriangulation::OnPoint(v) => vec![v],
-            PositionInTriangulation::OnEdge(e) => vec![e.from(), e.to()],
-            PositionInTriangulation::InTriangle(f) => {
-                let vs = f.as_triangle();
-                vec![vs[0], vs[1], vs[2]]
+        let weights = self.get_barycentric_weights(point);
+        let mut sum = None;
+        for &(fixed_handle, weight) in weights.iter() {
+            let new = f(&*self.s.vertex(fixed_handle)) * weight;
+            sum = match sum {
+                Some(val) => Some(val + new),
+                None => Some(new),
             }
-            PositionInTriangulation::OutsideConvexHull(e) => vec![e.from(), e.to()],
-        };
+        }
+        sum
+    }
+
+    /// Performs a barycentric interpolation of an arbitrary value type.
+    ///
+    /// Works like `barycentric_interpolation`, but `f` may return any value implementing
+    /// `LinearCombination`, e.g. points, vectors or user defined structs. This allows
+    /// to interpolate vector valued or multiple attributes while locating the
+    /// query point only once.
+    pub fn barycentric_interpolation_multi<F, R>(&self, point: &V::Point, f: F) -> Option<R>
+        where F: Fn(&V) -> R,
+              R: LinearCombination<<V::Point as PointN>::Scalar> {
+        let weights = self.get_barycentric_weights(point);
+        weighted_sum(weights.iter().map(
+            |&(fixed_handle, weight)| (f(&*self.s.vertex(fixed_handle)), weight)))
+    }
+
+    /// Precomputes a barycentric interpolation for a set of query points.
+    ///
+    /// The returned plan stores, for each query point, the contributing vertices
+    /// and their barycentric weights. Evaluating the plan with different vertex values
+    /// will yield the same results as calling `barycentric_interpolation` for each
+    /// query point, but skips locating the points again.
+    /// See `InterpolationPlan` for more information.
+    pub fn barycentric_interpolation_plan(&self, points: &[V::Point])
+                                          -> InterpolationPlan<<V::Point as PointN>::Scalar> {
+        let mut plan = InterpolationPlan::with_capacity(points.len());
+        for point in points {
+            plan.push_point(self.get_barycentric_weights(point).iter().cloned());
+        }
+        plan
+    }
+
+    fn get_barycentric_weights(&self, point: &V::Point) 
+                               -> SmallVec<[(FixedVertexHandle, <V::Point as PointN>::Scalar); 3]> {
+        let mut result = SmallVec::new();
+        let vertices: SmallVec<[FixedVertexHandle; 3]> = 
+            match self.locate_with_hint_option_fixed(point, None) {
+                PositionInTriangulation::NoTriangulationPresent => return result,
+                PositionInTriangulation::OnPoint(v) => SmallVec::from_slice(&[v]),
+                PositionInTriangulation::OnEdge(e) | PositionInTriangulation::OutsideConvexHull(e) => {
+                    let edge = self.s.edge(e);
+                    SmallVec::from_slice(&[edge.from().fix(), edge.to().fix()])
+                },
+                PositionInTriangulation::InTriangle(f) => {
+                    let vs = self.s.face(f).as_triangle();
+                    SmallVec::from_slice(&[vs[0].fix(), vs[1].fix(), vs[2].fix()])
+                },
+            };
         if vertices.len() == 1 {
-            Some(f(&*vertices[0]))
+            result.push((vertices[0], one()));
         } else if vertices.len() == 2 {
-            let p0 = vertices[0].position();
-            let p1 = vertices[1].position();
+            let p0 = (*self.s.vertex(vertices[0])).position();
+            let p1 = (*self.s.vertex(vertices[1])).position();
             let one = <<V::Point as PointN>::Scalar>::one();
             let edge = SimpleEdge::new(p0, p1);
             let w1 = ::clamp::clamp(zero(), edge.project_point(point), one);
             let w0 = one - w1;
-            Some(w1 * f(&*vertices[1]) + w0 * f(&*vertices[0]))
+            result.push((vertices[0], w0));
+            result.push((vertices[1], w1));
         } else {
             let triangle = ::primitives::SimpleTriangle::new(
-                vertices[0].position(),
-                vertices[1].position(),
-                vertices[2].position());
+                (*self.s.vertex(vertices[0])).position(),
+                (*self.s.vertex(vertices[1])).position(),
+                (*self.s.vertex(vertices[2])).position());
             let b_coords = triangle.barycentric_interpolation(point);
-            let w0 = f(&*vertices[0]);
-            let w1 = f(&*vertices[1]);
-            let w2 = f(&*vertices[2]);
-            Some(w0 * b_coords[0] + w1 * b_coords[1] + w2 * b_coords[2])
+            result.push((vertices[0], b_coords[0]));
+            result.push((vertices[1], b_coords[1]));
+            result.push((vertices[2], b_coords[2]));
         }
+        result
     }
-    
 
     /// Performs a natural neighbor interpolation for a given position.
     ///
@@ -1076,11 +1419,82 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
         sum
     }
 
+    /// Performs a natural neighbor interpolation of an arbitrary value type.
+    ///
+    /// Works like `nn_interpolation`, but `f` may return any value implementing
+    /// `LinearCombination`, e.g. points, vectors or user defined structs. Since
+    /// calculating the natural neighbors and their weights is the expensive part of
+    /// the interpolation, interpolating all attributes at once is considerably faster
+    /// than calling `nn_interpolation` once per attribute.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate nalgebra;
+    /// # extern crate spade;
+    ///
+    /// # use nalgebra::{Point2};
+    /// use spade::HasPosition;
+    /// use spade::delaunay::{FloatDelaunayTriangulation};
+    ///
+    /// struct WindSample {
+    ///   point: Point2<f64>,
+    ///   // Wind direction and speed, given as vector
+    ///   wind: [f64; 2],
+    /// }
+    ///
+    /// impl HasPosition for WindSample {
+    ///   type Point = Point2<f64>;
+    ///     fn position(&self) -> Point2<f64> {
+    ///       self.point
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///   let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+    ///   delaunay.insert(WindSample { point: Point2::new(0.0, 0.0), wind: [1.0, 0.0] });
+    ///   delaunay.insert(WindSample { point: Point2::new(1.0, 0.0), wind: [0.0, 1.0] });
+    ///   delaunay.insert(WindSample { point: Point2::new(0.0, 1.0), wind: [0.0, 1.0] });
+    ///   let wind = delaunay.nn_interpolation_multi(&Point2::new(0.2, 0.2), |s| s.wind);
+    ///   assert!(wind.is_some());
+    ///   assert_eq!(delaunay.nn_interpolation_multi(&Point2::new(0.0, 0.0), |s| s.wind),
+    ///              Some([1.0, 0.0]));
+    /// }
+    /// ```
+    pub fn nn_interpolation_multi<F, R>(&self, point: &V::Point, f: F) -> Option<R>
+        where F: Fn(&V) -> R,
+              R: LinearCombination<<V::Point as PointN>::Scalar> {
+        let nns = self.get_natural_neighbors(point);
+        let ws = self.get_weights(&nns, point);
+        weighted_sum(nns.iter().zip(ws.iter()).map(
+            |(fixed_handle, weight)| (f(&*self.s.vertex(*fixed_handle)), *weight)))
+    }
+
+    /// Precomputes a natural neighbor interpolation for a set of query points.
+    ///
+    /// The returned plan stores, for each query point, its natural neighbors and
+    /// their Sibson weights. Evaluating the plan with different vertex values
+    /// will yield the same results as calling `nn_interpolation` for each query point,
+    /// but skips the costly calculation of natural neighbors and their weights.
+    /// See `InterpolationPlan` for more information.
+    pub fn nn_interpolation_plan(&self, points: &[V::Point])
+                                 -> InterpolationPlan<<V::Point as PointN>::Scalar> {
+        let mut plan = InterpolationPlan::with_capacity(points.len());
+        for point in points {
+            let nns = self.get_natural_neighbors(point);
+            let ws = self.get_weights(&nns, point);
+            plan.push_point(nns.iter().cloned().zip(ws.iter().cloned()));
+        }
+        plan
+    }
+
     fn get_weights(&self, nns: &SmallVec<[FixedVertexHandle; INTPL_SMALLVEC_CAPACITY]>, 
                    point: &V::Point) 
                    -> SmallVec<[<V::Point as PointN>::Scalar; INTPL_SMALLVEC_CAPACITY]> {
 
         let mut result = SmallVec::new();
+        if nns.is_empty() {
+            return result;
+        }
         if nns.len() == 1 {
             result.push(one());
             return result
@@ -1375,7 +1789,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
                                              f: F, g: G) 
                                              -> Option<<V::Point as PointN>::Scalar> 
         where F: Fn(&V) -> <V::Point as PointN>::Scalar,
-              G: Fn(&Self, &VertexHandle<V>) -> V::Point {
+              G: Fn(&Self, &VertexHandle<V, ED, FD>) -> V::Point {
         
         let nns = self.get_natural_neighbors(point);
         let ws = self.get_weights(&nns, point);
@@ -1420,7 +1834,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     pub fn nn_interpolation_c1_farin<F, G>(&self, point: &V::Point, f: F, g: G) 
                                            -> Option<<V::Point as PointN>::Scalar>
         where F: Fn(&V) -> <V::Point as PointN>::Scalar,
-              G: Fn(&Self, &VertexHandle<V>) -> V::Point  {
+              G: Fn(&Self, &VertexHandle<V, ED, FD>) -> V::Point  {
         let nns = self.get_natural_neighbors(point);
         let ws = self.get_weights(&nns, point);
         if ws.is_empty() {
@@ -1505,11 +1919,13 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     }
 }
 
-impl <V, K, L> DelaunayTriangulation<V, K, L> 
+impl <V, K, L, ED, FD> DelaunayTriangulation<V, K, L, ED, FD> 
     where V: HasPosition2D, <V::Point as PointN>::Scalar: SpadeFloat,
           K: DelaunayKernel<<V::Point as PointN>::Scalar>,
           L: DelaunayLocateStructure<V::Point>,
           V::Point: TwoDimensional,
+          ED: EdgeData,
+          FD: FaceData,
 {
 
     /// Estimates a normal for each vertex in the triangulation.
@@ -1582,7 +1998,7 @@ impl <V, K, L> DelaunayTriangulation<V, K, L>
     }
 }
 
-fn to_simple_edge<'a, V>(edge: &EdgeHandle<'a, V>) -> SimpleEdge<V::Point> 
+fn to_simple_edge<'a, V, E, F>(edge: &EdgeHandle<'a, V, E, F>) -> SimpleEdge<V::Point> 
     where V: HasPosition + 'a,
 {
     let from = (*edge.from()).position();
@@ -1598,6 +2014,7 @@ mod test {
     use rand::{SeedableRng, XorShiftRng, Rng};
     use rand::distributions::{Range, IndependentSample};
     use traits::{HasPosition, SpatialObject};
+    use delaunay::{EdgeData, FaceData};
 
     #[test]
     fn test_inserting_one_point() {
@@ -1894,6 +2311,85 @@ mod test {
         assert_eq!(d.nn_interpolation(&Point2::new(3.0, 0.0), |p| p.height), Some(1.0));
     }
 
+    #[test]
+    fn test_interpolation_plan() {
+        use super::PositionInTriangulation;
+        let points = random_points_with_seed::<f64>(300, [211, 9931, 12, 7]);
+        let mut d = FloatDelaunayTriangulation::with_walk_locate();
+        for p in &points {
+            d.insert(PointWithHeight::new(p.x, p.y, p.x * p.x - p.y));
+        }
+        let mut query_points = random_points_with_seed::<f64>(100, [3, 1, 4, 1]);
+        // Add a vertex position and a point outside of the convex hull
+        query_points.push(points[10]);
+        query_points.push(Point2::new(3.0, -2.5));
+        let nn_plan = d.nn_interpolation_plan(&query_points);
+        let barycentric_plan = d.barycentric_interpolation_plan(&query_points);
+        assert_eq!(nn_plan.num_points(), query_points.len());
+        assert_eq!(barycentric_plan.num_points(), query_points.len());
+
+        let values: Vec<_> = d.vertices().map(|v| v.height).collect();
+        let nn_values = nn_plan.evaluate(&values);
+        let barycentric_values = barycentric_plan.evaluate(&values);
+        for (index, p) in query_points.iter().enumerate() {
+            let expected = d.nn_interpolation(p, |v| v.height).unwrap();
+            assert!((nn_values[index].unwrap() - expected).abs() < 1e-10);
+            if let PositionInTriangulation::OutsideConvexHull(_) = d.locate(p) {
+                // Barycentric interpolation outside of the convex hull depends on the
+                // edge found by the locate operation.
+                continue;
+            }
+            let expected = d.barycentric_interpolation(p, |v| v.height).unwrap();
+            assert!((barycentric_values[index].unwrap() - expected).abs() < 1e-10);
+        }
+        assert_eq!(nn_plan.handles(100), &[d.locate_vertex(&points[10]).unwrap().fix()]);
+        assert_eq!(nn_plan.weights(100), &[1.0]);
+    }
+
+    #[test]
+    fn test_interpolation_plan_degenerate() {
+        let mut d = FloatDelaunayTriangulation::with_walk_locate();
+        d.insert(Point2::new(0.0, 0.0));
+        d.insert(Point2::new(1.0, 0.0));
+        let plan = d.nn_interpolation_plan(&[Point2::new(0.5, 0.5)]);
+        assert!(plan.handles(0).is_empty());
+        assert_eq!(plan.evaluate(&[1.0, 2.0]), vec![None]);
+    }
+
+    #[test]
+    fn test_multi_interpolation() {
+        let points = random_points_with_seed::<f64>(200, [7, 31, 5, 1022]);
+        let mut d = FloatDelaunayTriangulation::with_walk_locate();
+        for p in &points {
+            d.insert(PointWithHeight::new(p.x, p.y, p.x + 2.0 * p.y));
+        }
+        // Keep the query points well inside of the convex hull
+        let query_points: Vec<_> = random_points_with_seed::<f64>(50, [9, 8, 1, 2])
+            .iter().map(|p| p * 0.5).collect();
+        for p in &query_points {
+            let nn = d.nn_interpolation_multi(p, |v| [v.height, -v.height]).unwrap();
+            let expected = d.nn_interpolation(p, |v| v.height).unwrap();
+            assert!((nn[0] - expected).abs() < 1e-10);
+            assert!((nn[1] + expected).abs() < 1e-10);
+
+            let barycentric = d.barycentric_interpolation_multi(
+                p, |v| vec![v.height, 1.0, 0.5 * v.height]).unwrap();
+            let expected = d.barycentric_interpolation(p, |v| v.height).unwrap();
+            assert_eq!(barycentric.len(), 3);
+            assert!((barycentric[0] - expected).abs() < 1e-10);
+            assert!((barycentric[1] - 1.0).abs() < 1e-10);
+            assert!((barycentric[2] - 0.5 * expected).abs() < 1e-10);
+        }
+        let values: Vec<_> = d.vertices().map(|v| v.point).collect();
+        let plan = d.nn_interpolation_plan(&query_points);
+        for (interpolated, p) in plan.evaluate(&values).iter().zip(&query_points) {
+            // Natural neighbor interpolation reproduces linear functions
+            let interpolated = interpolated.unwrap();
+            assert!((interpolated.x - p.x).abs() < 1e-10);
+            assert!((interpolated.y - p.y).abs() < 1e-10);
+        }
+    }
+
     #[test]
     fn test_insert_points_with_increasing_distance() {
         use cgmath::{EuclideanSpace};
@@ -2061,6 +2557,302 @@ mod test {
         d.sanity_check();
     }
 
+    #[test]
+    fn test_move_vertex_within_star() {
+        use cgmath::Vector2;
+        let points = random_points_with_seed::<f64>(500, [3, 9911, 2322, 12]);
+        let mut d = FloatDelaunayTriangulation::with_tree_locate();
+        for point in &points {
+            d.insert(*point);
+        }
+        let mut rng = XorShiftRng::from_seed([1, 3, 3, 7]);
+        let offset = Range::new(-0.02, 0.02);
+        for _ in 0 .. 1000 {
+            let handle = Range::new(0, d.num_vertices()).ind_sample(&mut rng);
+            let old_position = d.vertex(handle).position();
+            let new_position = old_position + Vector2::new(
+                offset.ind_sample(&mut rng), offset.ind_sample(&mut rng));
+            assert!(d.move_vertex(handle, new_position));
+            assert_eq!(d.vertex(handle).position(), new_position);
+            assert_eq!(d.lookup(&new_position).map(|v| v.fix()), Some(handle));
+            assert!(d.lookup(&old_position).is_none());
+        }
+        assert_eq!(d.num_vertices(), points.len());
+        d.sanity_check();
+    }
+
+    #[test]
+    fn test_move_vertex_far() {
+        use cgmath::EuclideanSpace;
+        let points = random_points_with_seed::<f64>(300, [99, 1, 2049, 7]);
+        let mut d = FloatDelaunayTriangulation::with_walk_locate();
+        for point in &points {
+            d.insert(*point);
+        }
+        let mut rng = XorShiftRng::from_seed([4, 2, 3, 17]);
+        let mut positions: Vec<_> = d.vertices().map(|v| v.position()).collect();
+        for _ in 0 .. 300 {
+            let handle = Range::new(0, d.num_vertices()).ind_sample(&mut rng);
+            let new_position = Point2::from_vec(rng.gen()) * 1.5;
+            assert!(d.move_vertex(handle, new_position));
+            positions[handle] = new_position;
+        }
+        for (handle, position) in positions.iter().enumerate() {
+            assert_eq!(d.vertex(handle).position(), *position);
+        }
+        d.sanity_check();
+    }
+
+    #[test]
+    fn test_move_vertex_onto_vertex() {
+        let mut d = FloatDelaunayTriangulation::with_tree_locate();
+        let v0 = d.insert(Point2::new(0.0, 0.0));
+        d.insert(Point2::new(1.0, 0.0));
+        let v2 = d.insert(Point2::new(0.0, 1.0));
+        d.insert(Point2::new(1.0, 1.0));
+        assert!(!d.move_vertex(v0, Point2::new(0.0, 1.0)));
+        assert_eq!(d.vertex(v0).position(), Point2::new(0.0, 0.0));
+        assert!(d.move_vertex(v0, Point2::new(-1.0, -0.5)));
+        assert!(d.move_vertex(v2, Point2::new(0.5, 0.5)));
+        assert_eq!(d.lookup(&Point2::new(0.5, 0.5)).map(|v| v.fix()), Some(v2));
+        assert_eq!(d.num_vertices(), 4);
+        d.sanity_check();
+    }
+
+    #[test]
+    fn test_move_vertex_degenerate() {
+        let mut d = FloatDelaunayTriangulation::with_tree_locate();
+        let v0 = d.insert(Point2::new(0.0, 0.0));
+        let v1 = d.insert(Point2::new(1.0, 0.0));
+        assert!(!d.move_vertex(v0, Point2::new(1.0, 0.0)));
+        assert!(d.move_vertex(v0, Point2::new(2.0, 0.0)));
+        assert!(d.is_degenerate());
+        let v2 = d.insert(Point2::new(3.0, 0.0));
+        assert!(d.move_vertex(v2, Point2::new(1.0, 1.0)));
+        assert!(!d.is_degenerate());
+        assert_eq!(d.vertex(v1).position(), Point2::new(1.0, 0.0));
+        assert_eq!(d.vertex(v2).position(), Point2::new(1.0, 1.0));
+        d.sanity_check();
+    }
+
+    #[test]
+    fn test_stable_vertex_handles() {
+        use cgmath::EuclideanSpace;
+        let points = random_points_with_seed::<f64>(300, [5, 15, 25, 35]);
+        let mut d = FloatDelaunayTriangulation::with_tree_locate();
+        for point in &points[.. 100] {
+            d.insert(*point);
+        }
+        d.enable_stable_handles();
+        let mut handles: Vec<_> = d.vertices().map(
+            |v| (d.stable_vertex_handle(v.fix()), Some(v.position()))).collect();
+        let mut rng = XorShiftRng::from_seed([3, 1, 4, 15]);
+        for point in &points[100 ..] {
+            if rng.gen() {
+                let handle = d.insert(*point);
+                handles.push((d.stable_vertex_handle(handle), Some(*point)));
+            } else {
+                let index = Range::new(0, handles.len()).ind_sample(&mut rng);
+                if let Some(handle) = d.resolve_vertex_handle(handles[index].0) {
+                    d.remove(handle);
+                    handles[index].1 = None;
+                }
+            }
+            if rng.gen_weighted_bool(5) {
+                let index = Range::new(0, handles.len()).ind_sample(&mut rng);
+                if let Some(handle) = d.resolve_vertex_handle(handles[index].0) {
+                    let new_position = Point2::from_vec(rng.gen());
+                    if d.move_vertex(handle, new_position) {
+                        handles[index].1 = Some(new_position);
+                    }
+                }
+            }
+        }
+        for &(handle, position) in &handles {
+            assert_eq!(d.resolve_vertex_handle(handle).map(|v| d.vertex(v).position()),
+                       position);
+        }
+        let num_alive = handles.iter().filter(|&&(_, p)| p.is_some()).count();
+        assert_eq!(num_alive, d.num_vertices());
+        d.sanity_check();
+    }
+
+    #[test]
+    fn test_stable_edge_and_face_handles() {
+        let points = random_points_with_seed::<f64>(200, [9, 99, 999, 9999]);
+        let mut d = FloatDelaunayTriangulation::with_walk_locate();
+        d.enable_stable_handles();
+        // Insert an outer quad to keep the removed vertex off the convex hull
+        d.insert(Point2::new(-2.0, -2.0));
+        d.insert(Point2::new(-2.0, 2.0));
+        d.insert(Point2::new(2.0, -2.0));
+        d.insert(Point2::new(2.0, 2.0));
+        for point in &points {
+            d.insert(*point);
+        }
+        let removed = d.vertex(d.locate_vertex(&points[17]).unwrap().fix()).position();
+        let edges: Vec<_> = d.edges().flat_map(|e| vec![e, e.sym()]).map(
+            |e| (d.stable_edge_handle(e.fix()), e.from().position(), e.to().position()))
+            .collect();
+        let faces: Vec<_> = d.triangles().map(|f| {
+            let triangle = f.as_triangle();
+            (d.stable_face_handle(f.fix()), [triangle[0].position(),
+                                              triangle[1].position(),
+                                              triangle[2].position()])
+        }).collect();
+        let infinite = d.stable_face_handle(d.infinite_face().fix());
+        assert!(d.locate_and_remove(&points[17]).is_some());
+
+        for &(handle, from, to) in &edges {
+            let resolved = d.resolve_edge_handle(handle);
+            if from == removed || to == removed {
+                assert_eq!(resolved, None);
+            } else {
+                let edge = d.edge(resolved.unwrap());
+                assert_eq!((edge.from().position(), edge.to().position()), (from, to));
+            }
+        }
+        for &(handle, ref positions) in &faces {
+            let resolved = d.resolve_face_handle(handle);
+            if positions.contains(&removed) {
+                assert_eq!(resolved, None);
+            } else {
+                let triangle = d.face(resolved.unwrap()).as_triangle();
+                for vertex in &triangle {
+                    assert!(positions.contains(&vertex.position()));
+                }
+            }
+        }
+        assert_eq!(d.resolve_face_handle(infinite), Some(d.infinite_face().fix()));
+        d.sanity_check();
+
+        // Insertions split and flip edges and faces, their old handles must not
+        // refer to other elements. The second point lies outside of the convex hull
+        for inserted in &[Point2::new(0.01, 0.02), Point2::new(3.0, 0.5)] {
+            let edges: Vec<_> = d.edges().flat_map(|e| vec![e, e.sym()]).map(
+                |e| (d.stable_edge_handle(e.fix()), e.from().position(), e.to().position()))
+                .collect();
+            let faces: Vec<_> = d.triangles().map(|f| {
+                let triangle = f.as_triangle();
+                (d.stable_face_handle(f.fix()), [triangle[0].position(),
+                                                  triangle[1].position(),
+                                                  triangle[2].position()])
+            }).collect();
+            d.insert(*inserted);
+            let mut invalid_edges = 0;
+            for &(handle, from, to) in &edges {
+                if let Some(resolved) = d.resolve_edge_handle(handle) {
+                    let edge = d.edge(resolved);
+                    assert_eq!((edge.from().position(), edge.to().position()), (from, to));
+                } else {
+                    invalid_edges += 1;
+                }
+            }
+            let mut invalid_faces = 0;
+            for &(handle, ref positions) in &faces {
+                if let Some(resolved) = d.resolve_face_handle(handle) {
+                    let triangle = d.face(resolved).as_triangle();
+                    for vertex in &triangle {
+                        assert!(positions.contains(&vertex.position()));
+                    }
+                } else {
+                    invalid_faces += 1;
+                }
+            }
+            assert!(invalid_faces > 0 && invalid_faces < faces.len());
+            assert!(invalid_edges < edges.len());
+            assert_eq!(d.resolve_face_handle(infinite), Some(d.infinite_face().fix()));
+        }
+        d.sanity_check();
+    }
+
+    #[derive(Default, Clone)]
+    struct EdgeEnds(Option<[Point2<f64>; 2]>);
+
+    impl EdgeData for EdgeEnds {
+        fn split(&mut self, _: &mut EdgeEnds) {
+            self.0 = None;
+        }
+
+        fn flip(&mut self) {
+            self.0 = None;
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct FaceCorners(Option<[Point2<f64>; 3]>);
+
+    impl FaceData for FaceCorners {
+        fn split(&mut self, _: &mut FaceCorners) {
+            self.0 = None;
+        }
+
+        fn merge(&mut self, _: FaceCorners) {
+            self.0 = None;
+        }
+
+        fn flip(&mut self, other: &mut FaceCorners) {
+            self.0 = None;
+            other.0 = None;
+        }
+    }
+
+    #[test]
+    fn test_edge_and_face_data() {
+        use super::DelaunayTriangulation;
+        use delaunay::DelaunayTreeLocate;
+        use kernels::FloatKernel;
+        let points = random_points_with_seed::<f64>(400, [3, 17, 4, 1]);
+        let mut d: DelaunayTriangulation<_, FloatKernel, DelaunayTreeLocate<_>,
+                                         EdgeEnds, FaceCorners> = DelaunayTriangulation::new_with_data();
+        for point in &points[.. 200] {
+            d.insert(*point);
+        }
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        for point in &points[200 ..] {
+            // Store each edge's and face's vertices as data
+            let edges: Vec<_> = d.edges().map(
+                |e| (e.fix(), [e.from().position(), e.to().position()])).collect();
+            for (edge, ends) in edges {
+                d.edge_data_mut(edge).0 = Some(ends);
+            }
+            let faces: Vec<_> = d.triangles().map(|f| {
+                let t = f.as_triangle();
+                (f.fix(), [t[0].position(), t[1].position(), t[2].position()])
+            }).collect();
+            for (face, corners) in faces {
+                d.face_data_mut(face).0 = Some(corners);
+            }
+            if rng.gen() {
+                d.insert(*point);
+            } else {
+                let handle = Range::new(0, d.num_vertices()).ind_sample(&mut rng);
+                d.remove(handle);
+            }
+            // Data that was not reset by a callback must still match its element
+            let mut num_unchanged = 0;
+            for edge in d.edges() {
+                if let Some(ends) = edge.data().0 {
+                    assert_eq!(edge.data().0, edge.sym().data().0);
+                    let from = edge.from().position();
+                    let to = edge.to().position();
+                    assert!(ends == [from, to] || ends == [to, from]);
+                    num_unchanged += 1;
+                }
+            }
+            for face in d.triangles() {
+                if let Some(corners) = face.data().0 {
+                    for vertex in &face.as_triangle() {
+                        assert!(corners.contains(&vertex.position()));
+                    }
+                    num_unchanged += 1;
+                }
+            }
+            assert!(num_unchanged > 0);
+        }
+        d.sanity_check();
+    }
+
     #[test]
     fn test_nearest_neighbor() {
         const SIZE: usize = 100;
@@ -0,0 +1,218 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use traits::SpadeFloat;
+use point_traits::{PointN, PointNExtensions};
+use delaunay::FixedVertexHandle;
+
+/// Values that can be interpolated.
+///
+/// All interpolation methods calculate a weighted sum of vertex values. This trait
+/// defines the two operations required for this sum, allowing to interpolate vector
+/// valued or multiple attributes in a single pass, see
+/// `DelaunayTriangulation::nn_interpolation_multi(..)` for an example.
+/// The trait is implemented for `f32`, `f64`, all types implementing `PointN` and `Vec`.
+///
+/// # Example
+/// ```
+/// use spade::delaunay::LinearCombination;
+///
+/// #[derive(Clone)]
+/// struct Sample {
+///   temperature: f64,
+///   humidity: f64,
+/// }
+///
+/// impl LinearCombination<f64> for Sample {
+///   fn scale(self, factor: f64) -> Sample {
+///     Sample {
+///       temperature: self.temperature * factor,
+///       humidity: self.humidity * factor,
+///     }
+///   }
+///
+///   fn add_scaled(self, other: Sample, factor: f64) -> Sample {
+///     Sample {
+///       temperature: self.temperature + other.temperature * factor,
+///       humidity: self.humidity + other.humidity * factor,
+///     }
+///   }
+/// }
+/// ```
+pub trait LinearCombination<S>: Sized {
+    /// Multiplies this value with a scalar factor.
+    fn scale(self, factor: S) -> Self;
+
+    /// Adds another value, multiplied with a scalar factor, to this value.
+    fn add_scaled(self, other: Self, factor: S) -> Self;
+}
+
+impl LinearCombination<f32> for f32 {
+    fn scale(self, factor: f32) -> f32 {
+        self * factor
+    }
+
+    fn add_scaled(self, other: f32, factor: f32) -> f32 {
+        self + other * factor
+    }
+}
+
+impl LinearCombination<f64> for f64 {
+    fn scale(self, factor: f64) -> f64 {
+        self * factor
+    }
+
+    fn add_scaled(self, other: f64, factor: f64) -> f64 {
+        self + other * factor
+    }
+}
+
+impl <P> LinearCombination<P::Scalar> for P where P: PointN {
+    fn scale(self, factor: P::Scalar) -> P {
+        self.mul(factor)
+    }
+
+    fn add_scaled(self, other: P, factor: P::Scalar) -> P {
+        self.add(&other.mul(factor))
+    }
+}
+
+impl <S> LinearCombination<S> for Vec<S> where S: SpadeFloat {
+    fn scale(self, factor: S) -> Vec<S> {
+        self.into_iter().map(|value| value * factor).collect()
+    }
+
+    fn add_scaled(self, other: Vec<S>, factor: S) -> Vec<S> {
+        assert_eq!(self.len(), other.len(), "Cannot combine vectors of different lengths");
+        self.into_iter().zip(other).map(|(value, other)| value + other * factor).collect()
+    }
+}
+
+/// Calculates the weighted sum of the values yielded by an iterator.
+///
+/// Returns `None` if the iterator is empty.
+pub(crate) fn weighted_sum<S, R, I>(values: I) -> Option<R>
+    where I: IntoIterator<Item=(R, S)>,
+          R: LinearCombination<S> {
+    let mut sum = None;
+    for (value, weight) in values {
+        sum = Some(match sum {
+            Some(val) => R::add_scaled(val, value, weight),
+            None => value.scale(weight),
+        });
+    }
+    sum
+}
+
+/// Precomputed interpolation weights for a fixed set of query points.
+///
+/// Interpolating many values over the same vertex set and the same query points
+/// repeats the expensive part of the interpolation - locating each query point and
+/// calculating its weights - over and over again. An interpolation plan stores the
+/// contributing vertices and weights of each query point once, any per vertex value
+/// set can then be interpolated by a simple sparse matrix-vector product.
+///
+/// Plans are created by `DelaunayTriangulation::barycentric_interpolation_plan(..)` and
+/// `DelaunayTriangulation::nn_interpolation_plan(..)`.
+///
+/// # Handle invalidation
+/// A plan refers to vertices by their fixed handles. Inserting vertices can change the
+/// weights of a query point, removing vertices will invalidate the stored handles.
+/// A plan must be recreated if the triangulation is modified.
+///
+/// # Example
+/// ```
+/// # extern crate nalgebra;
+/// # extern crate spade;
+///
+/// use nalgebra::Point2;
+/// use spade::delaunay::FloatDelaunayTriangulation;
+///
+/// # fn main() {
+/// let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+/// delaunay.insert(Point2::new(0.0, 0.0));
+/// delaunay.insert(Point2::new(1.0, 0.0));
+/// delaunay.insert(Point2::new(0.0, 1.0));
+///
+/// let plan = delaunay.nn_interpolation_plan(&[Point2::new(0.2, 0.2), Point2::new(0.0, 0.0)]);
+/// // Values are given per vertex, indexed by the vertices' fixed handles
+/// for values in &[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]] {
+///     let interpolated = plan.evaluate(values);
+///     assert_eq!(interpolated.len(), 2);
+///     assert_eq!(interpolated[1], Some(values[0]));
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct InterpolationPlan<S> {
+    offsets: Vec<usize>,
+    handles: Vec<FixedVertexHandle>,
+    weights: Vec<S>,
+}
+
+impl <S> InterpolationPlan<S> where S: SpadeFloat {
+    pub(crate) fn with_capacity(num_points: usize) -> InterpolationPlan<S> {
+        let mut offsets = Vec::with_capacity(num_points + 1);
+        offsets.push(0);
+        InterpolationPlan {
+            offsets,
+            handles: Vec::with_capacity(num_points * 3),
+            weights: Vec::with_capacity(num_points * 3),
+        }
+    }
+
+    pub(crate) fn push_point<I>(&mut self, contributions: I)
+        where I: IntoIterator<Item=(FixedVertexHandle, S)> {
+        for (handle, weight) in contributions {
+            self.handles.push(handle);
+            self.weights.push(weight);
+        }
+        self.offsets.push(self.handles.len());
+    }
+
+    /// Returns the number of query points of this plan.
+    pub fn num_points(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns the vertices contributing to the query point at position `index`.
+    ///
+    /// The returned slice is empty if the point could not be interpolated, e.g.
+    /// if the triangulation was degenerate when creating the plan.
+    pub fn handles(&self, index: usize) -> &[FixedVertexHandle] {
+        &self.handles[self.offsets[index] .. self.offsets[index + 1]]
+    }
+
+    /// Returns the weights of the vertices contributing to the query point at
+    /// position `index`.
+    ///
+    /// The weights are ordered like the handles returned by `handles(index)`.
+    pub fn weights(&self, index: usize) -> &[S] {
+        &self.weights[self.offsets[index] .. self.offsets[index + 1]]
+    }
+
+    /// Interpolates a set of vertex values for all query points.
+    ///
+    /// `values` must contain one value per vertex, indexed by the vertices' fixed handles.
+    /// Any value implementing `LinearCombination` can be interpolated.
+    /// Returns one value per query point, in the order the points were given when creating
+    /// the plan. A value is `None` if the query point could not be interpolated.
+    ///
+    /// # Panics
+    /// Panics if `values` does not contain an entry for a contributing vertex.
+    pub fn evaluate<R>(&self, values: &[R]) -> Vec<Option<R>>
+        where R: LinearCombination<S> + Clone {
+        let mut result = Vec::with_capacity(self.num_points());
+        for range in self.offsets.windows(2) {
+            let contributions = (range[0] .. range[1]).map(
+                |index| (values[self.handles[index]].clone(), self.weights[index]));
+            result.push(weighted_sum(contributions));
+        }
+        result
+    }
+}
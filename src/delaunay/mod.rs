@@ -11,12 +11,19 @@
 mod dcel;
 mod delaunay2d;
 mod delaunay_locate;
+mod interpolation;
+mod stable_handles;
+#[cfg(feature = "rayon")]
+mod parallel;
 
 pub use self::delaunay2d::*;
 pub use self::dcel::{FixedVertexHandle, FixedEdgeHandle, FixedFaceHandle,
                      VertexHandle, EdgeHandle, FaceHandle,
-                     CCWIterator, ONextIterator};
+                     CCWIterator, ONextIterator, FaceData, EdgeData};
 pub use self::delaunay_locate::{DelaunayTreeLocate, DelaunayWalkLocate,
                                 DelaunayLocateStructure};
+pub use self::interpolation::{InterpolationPlan, LinearCombination};
+pub use self::stable_handles::{StableHandle, StableVertexHandle, StableEdgeHandle,
+                               StableFaceHandle};
 #[allow(deprecated)]
 pub use self::delaunay_locate::{TriangulationWalkLocate, RTreeDelaunayLocate};
@@ -0,0 +1,64 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parallel batch queries, requires the `rayon` feature.
+
+use rayon::prelude::*;
+use traits::{HasPosition2D, SpadeFloat};
+use point_traits::{PointN, TwoDimensional};
+use kernels::DelaunayKernel;
+use delaunay::{DelaunayTriangulation, DelaunayLocateStructure, EdgeData, FaceData};
+
+impl <V, K, L, ED, FD> DelaunayTriangulation<V, K, L, ED, FD>
+    where V: HasPosition2D, <V::Point as PointN>::Scalar: SpadeFloat,
+          K: DelaunayKernel<<V::Point as PointN>::Scalar>,
+          L: DelaunayLocateStructure<V::Point>,
+          V::Point: TwoDimensional + Sync,
+          ED: EdgeData,
+          FD: FaceData,
+{
+    /// Performs a natural neighbor interpolation for each query point.
+    ///
+    /// The queries are distributed among rayon's thread pool, the result is in
+    /// the same order as `points`. See `nn_interpolation` for more information.
+    /// The walk locate strategy stores its query hint in an atomic, concurrent
+    /// queries will share and overwrite this hint.
+    /// Requires the `rayon` feature.
+    pub fn par_nn_interpolation<F>(&self, points: &[V::Point], f: F)
+                                   -> Vec<Option<<V::Point as PointN>::Scalar>>
+        where F: Fn(&V) -> <V::Point as PointN>::Scalar + Sync,
+              <V::Point as PointN>::Scalar: Send,
+              Self: Sync {
+        points.par_iter().map(|point| self.nn_interpolation(point, &f)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Point2;
+    use delaunay::{DelaunayTriangulation, DelaunayWalkLocate};
+    use kernels::FloatKernel;
+    use testutils::*;
+
+    #[test]
+    fn test_par_nn_interpolation() {
+        let points = random_points_with_seed::<f64>(300, [1, 9, 8, 4]);
+        let mut d: DelaunayTriangulation<_, FloatKernel, DelaunayWalkLocate> =
+            DelaunayTriangulation::with_walk_locate();
+        for point in &points {
+            d.insert(*point);
+        }
+        let queries: Vec<_> = random_points_with_seed::<f64>(200, [4, 8, 9, 1])
+            .iter().map(|p| Point2::new(p.x * 0.5, p.y * 0.5)).collect();
+        let interpolated = d.par_nn_interpolation(&queries, |v| v.x + v.y);
+        assert_eq!(interpolated.len(), queries.len());
+        for (query, value) in queries.iter().zip(interpolated) {
+            assert_eq!(value, d.nn_interpolation(query, |v| v.x + v.y));
+        }
+    }
+}
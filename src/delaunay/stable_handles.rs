@@ -0,0 +1,166 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// Handle that remains valid after unrelated removals.
+///
+/// Fixed handles are indices into the triangulation's internal storage. Since
+/// removing an element moves another element into its place, fixed handles are
+/// invalidated by removal operations. Stable handles add a level of indirection:
+/// They refer to a slot that always tracks the same element, and store a generation
+/// counter that detects if the element was removed in the meantime.
+///
+/// Stable handles must be enabled with
+/// `DelaunayTriangulation::enable_stable_handles()` and are converted from and to
+/// fixed handles with methods like `DelaunayTriangulation::stable_vertex_handle(..)`
+/// and `DelaunayTriangulation::resolve_vertex_handle(..)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StableHandle {
+    slot: usize,
+    generation: u32,
+}
+
+/// Stable handle to a vertex.
+pub type StableVertexHandle = StableHandle;
+/// Stable handle to a directed edge.
+pub type StableEdgeHandle = StableHandle;
+/// Stable handle to a face.
+pub type StableFaceHandle = StableHandle;
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    index: Option<usize>,
+    generation: u32,
+}
+
+/// Generational slot map that mirrors the operations on one of the
+/// DCEL's entry vectors.
+#[derive(Clone, Debug, Default)]
+pub struct HandleSlots {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    slot_of: Vec<usize>,
+}
+
+impl HandleSlots {
+    pub fn with_len(len: usize) -> HandleSlots {
+        let mut result = HandleSlots::default();
+        for _ in 0 .. len {
+            result.push();
+        }
+        result
+    }
+
+    /// Assigns a slot to an entry that was pushed to the mirrored vector.
+    pub fn push(&mut self) {
+        let index = self.slot_of.len();
+        let slot = if let Some(slot) = self.free.pop() {
+            self.slots[slot].index = Some(index);
+            slot
+        } else {
+            self.slots.push(Slot { index: Some(index), generation: 0 });
+            self.slots.len() - 1
+        };
+        self.slot_of.push(slot);
+    }
+
+    /// Mirrors a `swap_remove` of the entry at `index`.
+    pub fn swap_remove(&mut self, index: usize) {
+        let slot = self.slot_of.swap_remove(index);
+        self.release(slot);
+        if index < self.slot_of.len() {
+            self.slots[self.slot_of[index]].index = Some(index);
+        }
+    }
+
+    /// Mirrors a swap of the entries at `i0` and `i1`.
+    pub fn swap(&mut self, i0: usize, i1: usize) {
+        self.slot_of.swap(i0, i1);
+        self.slots[self.slot_of[i0]].index = Some(i0);
+        self.slots[self.slot_of[i1]].index = Some(i1);
+    }
+
+    /// Mirrors clearing the vector.
+    pub fn clear(&mut self) {
+        while let Some(slot) = self.slot_of.pop() {
+            self.release(slot);
+        }
+    }
+
+    /// Assigns a new slot to the entry at `index`, invalidating its old handle.
+    pub fn renew(&mut self, index: usize) {
+        let slot = self.slot_of[index];
+        // The released slot can be reused right away, its generation has changed
+        self.release(slot);
+        self.free.pop();
+        self.slots[slot].index = Some(index);
+    }
+
+    /// Binds a handle to the entry at `index`, releasing the entry's current slot.
+    ///
+    /// The handle's slot must not be in use.
+    pub fn rebind(&mut self, index: usize, handle: StableHandle) {
+        let current = self.slot_of[index];
+        if current != handle.slot {
+            let position = self.free.iter().position(|s| *s == handle.slot)
+                .expect("Cannot rebind a slot that is in use");
+            self.free.swap_remove(position);
+            self.release(current);
+        }
+        self.slots[handle.slot] = Slot { index: Some(index), generation: handle.generation };
+        self.slot_of[index] = handle.slot;
+    }
+
+    pub fn stable(&self, index: usize) -> StableHandle {
+        let slot = self.slot_of[index];
+        StableHandle {
+            slot,
+            generation: self.slots[slot].generation,
+        }
+    }
+
+    pub fn resolve(&self, handle: StableHandle) -> Option<usize> {
+        match self.slots.get(handle.slot) {
+            Some(slot) if slot.generation == handle.generation => slot.index,
+            _ => None,
+        }
+    }
+
+    fn release(&mut self, slot: usize) {
+        let entry = &mut self.slots[slot];
+        entry.index = None;
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(slot);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HandleSlots;
+
+    #[test]
+    fn test_handle_slots() {
+        let mut slots = HandleSlots::with_len(3);
+        let h0 = slots.stable(0);
+        let h2 = slots.stable(2);
+        slots.swap_remove(0);
+        assert_eq!(slots.resolve(h0), None);
+        assert_eq!(slots.resolve(h2), Some(0));
+        // The released slot is reused with a new generation
+        slots.push();
+        let new = slots.stable(2);
+        assert!(new != h0);
+        assert_eq!(slots.resolve(h0), None);
+        assert_eq!(slots.resolve(new), Some(2));
+        slots.swap(0, 2);
+        assert_eq!(slots.resolve(h2), Some(2));
+        assert_eq!(slots.resolve(new), Some(0));
+        slots.clear();
+        assert_eq!(slots.resolve(h2), None);
+        assert_eq!(slots.resolve(new), None);
+    }
+}
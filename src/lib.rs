@@ -24,6 +24,7 @@
 //!   * Uses exact predicates to avoid floating point rounding issues, see `spade::kernels::FloatKernel`
 //!   * Natural neighbor interpolation
 //!   * Can be backed up by an r*-tree to improve performance when inserting randomly distributed points
+//! * Density based clustering with DBSCAN and OPTICS: `spade::clustering`
 
 #![warn(missing_docs)]
 
@@ -32,6 +33,8 @@ extern crate cgmath;
 extern crate nalgebra;
 extern crate clamp;
 extern crate smallvec;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 
 #[cfg(test)]
 extern crate approx;
@@ -52,6 +55,7 @@ pub mod delaunay;
 pub mod kernels;
 pub mod primitives;
 pub mod rtree;
+pub mod clustering;
 
 pub use traits::*;
 pub use boundingvolume::*;
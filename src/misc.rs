@@ -6,6 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cmp::Ordering;
 use traits::{SpadeNum};
 
 // A call to l.min(r) does not seem to be inlined, thus we define it ourselves
@@ -27,3 +28,30 @@ pub fn max_inline<S: SpadeNum>(a: S, b: S) -> S {
         b
     }
 }
+
+// An entry of a binary heap that pops the entry with the smallest distance first
+pub struct MinHeapEntry<S, T> {
+    pub distance2: S,
+    pub entry: T,
+}
+
+impl <S: PartialOrd, T> PartialEq for MinHeapEntry<S, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance2 == other.distance2
+    }
+}
+
+impl <S: PartialOrd, T> Eq for MinHeapEntry<S, T> { }
+
+impl <S: PartialOrd, T> PartialOrd for MinHeapEntry<S, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl <S: PartialOrd, T> Ord for MinHeapEntry<S, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed to turn the binary max heap into a min heap
+        other.distance2.partial_cmp(&self.distance2).unwrap_or(Ordering::Equal)
+    }
+}
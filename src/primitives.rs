@@ -14,11 +14,12 @@
 
 
 use cgmath::{Point3, Zero, One};
-use traits::{SpadeFloat, SpadeNum, SpatialObject};
+use traits::{SpadeFloat, SpadeNum, SpatialObject, RayIntersect, MovingObject};
 use point_traits::{PointN, PointNExtensions, TwoDimensional};
-use num::{Float, one, zero, Signed};
+use num::{Float, FromPrimitive, one, zero, Signed};
 use boundingvolume::BoundingRect;
-use kernels::{TrivialKernel, DelaunayKernel};
+use misc::min_inline;
+use kernels::{TrivialKernel, FloatKernel, DelaunayKernel};
 
 /// An edge defined by it's two end points.
 #[derive(Clone, Debug, PartialEq)]
@@ -183,6 +184,44 @@ impl <V: PointN> SpatialObject for SimpleEdge<V> where V::Scalar: SpadeFloat {
     }
 }
 
+fn cross2<V: TwoDimensional>(a: &V, b: &V) -> V::Scalar {
+    a.nth(0).clone() * b.nth(1).clone() - a.nth(1).clone() * b.nth(0).clone()
+}
+
+impl <V> RayIntersect for SimpleEdge<V> where V: TwoDimensional, V::Scalar: SpadeFloat {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        let edge_dir = self.to.sub(&self.from);
+        let to_from = self.from.sub(origin);
+        let denominator = cross2(direction, &edge_dir);
+        if denominator == zero() {
+            if cross2(&to_from, direction) != zero() {
+                // Parallel, but not collinear
+                return None;
+            }
+            // Collinear, the ray hits the edge at the end point closest to its origin
+            let length2 = direction.length2();
+            if length2 == zero() {
+                return if self.distance2(origin) == zero() { Some(zero()) } else { None };
+            }
+            let t_from = to_from.dot(direction) / length2;
+            let t_to = self.to.sub(origin).dot(direction) / length2;
+            let (near, far) = if t_from < t_to { (t_from, t_to) } else { (t_to, t_from) };
+            return if far < zero() {
+                None
+            } else {
+                Some(near.max(zero()))
+            };
+        }
+        let t = cross2(&to_from, &edge_dir) / denominator;
+        let s = cross2(&to_from, direction) / denominator;
+        if t >= zero() && s >= zero() && s <= one() {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
 /// A triangle, defined by it's three points.
 #[derive(Clone)]
 pub struct SimpleTriangle<V: PointN> {
@@ -311,6 +350,23 @@ impl <V> SpatialObject for SimpleTriangle<V> where V: TwoDimensional, V::Scalar:
     }
 }
 
+impl <V> RayIntersect for SimpleTriangle<V> where V: TwoDimensional, V::Scalar: SpadeFloat {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        if self.contains(origin) {
+            return Some(zero());
+        }
+        let mut result: Option<V::Scalar> = None;
+        for i in 0 .. 3 {
+            let edge = SimpleEdge::new(self.vertices()[i].clone(),
+                                       self.vertices()[(i + 1) % 3].clone());
+            if let Some(t) = edge.ray_intersection(origin, direction) {
+                result = Some(result.map(|r| r.min(t)).unwrap_or(t));
+            }
+        }
+        result
+    }
+}
+
 /// An n-dimensional circle, defined by its origin and radius.
 pub struct SimpleCircle<V: PointN> {
     /// The circle's center.
@@ -353,10 +409,352 @@ impl <V> SpatialObject for SimpleCircle<V> where V: PointN, V::Scalar: SpadeFloa
     }
 }
 
+impl <V> RayIntersect for SimpleCircle<V> where V: PointN, V::Scalar: SpadeFloat {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        let to_origin = origin.sub(&self.center);
+        let c = to_origin.length2() - self.radius * self.radius;
+        if c <= zero() {
+            // The origin lies within the circle
+            return Some(zero());
+        }
+        let a = direction.length2();
+        let b = to_origin.dot(direction);
+        let discriminant = b * b - a * c;
+        if a == zero() || discriminant < zero() {
+            return None;
+        }
+        let t = (-b - discriminant.sqrt()) / a;
+        if t >= zero() { Some(t) } else { None }
+    }
+}
+
+/// A point that moves with a constant velocity.
+///
+/// Can be inserted into a `rtree::TprTree`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimpleMovingPoint<V: PointN> {
+    /// The point's position at `time`.
+    pub position: V,
+    /// The distance traveled per time unit.
+    pub velocity: V,
+    /// The time at which the point is located at `position`.
+    pub time: V::Scalar,
+}
+
+impl <V> SimpleMovingPoint<V> where V: PointN {
+    /// Creates a point that is located at `position` at time `time`.
+    pub fn new(position: V, velocity: V, time: V::Scalar) -> SimpleMovingPoint<V> {
+        SimpleMovingPoint { position, velocity, time }
+    }
+}
+
+impl <V> MovingObject for SimpleMovingPoint<V> where V: PointN {
+    type Point = V;
+
+    fn position(&self) -> V {
+        self.position.clone()
+    }
+
+    fn velocity(&self) -> V {
+        self.velocity.clone()
+    }
+
+    fn reference_time(&self) -> V::Scalar {
+        self.time.clone()
+    }
+}
+
+/// An axis aligned rectangle, defined by its lower and upper corner.
+///
+/// Points on the rectangle's boundary are contained in the rectangle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimpleRectangle<V: PointN> {
+    rect: BoundingRect<V>,
+}
+
+impl <V> SimpleRectangle<V> where V: PointN {
+    /// Creates a rectangle from two of its opposite corners.
+    pub fn new(corner1: V, corner2: V) -> SimpleRectangle<V> {
+        SimpleRectangle::from_rect(BoundingRect::from_corners(&corner1, &corner2))
+    }
+
+    /// Creates a rectangle that covers a bounding rectangle.
+    pub fn from_rect(rect: BoundingRect<V>) -> SimpleRectangle<V> {
+        SimpleRectangle { rect }
+    }
+
+    /// Returns the rectangle's lower corner.
+    pub fn lower(&self) -> V {
+        self.rect.lower()
+    }
+
+    /// Returns the rectangle's upper corner.
+    pub fn upper(&self) -> V {
+        self.rect.upper()
+    }
+}
+
+impl <V> SpatialObject for SimpleRectangle<V> where V: PointN {
+    type Point = V;
+
+    fn mbr(&self) -> BoundingRect<V> {
+        self.rect.clone()
+    }
+
+    fn distance2(&self, point: &V) -> V::Scalar {
+        if !self.rect.contains_point(point) {
+            return self.rect.min_dist2(point);
+        }
+        // Return the negated squared distance to the closest side
+        let (lower, upper) = (self.rect.lower(), self.rect.upper());
+        let mut min_dist = point.nth(0).clone() - lower.nth(0).clone();
+        for i in 0 .. V::dimensions() {
+            let to_lower = point.nth(i).clone() - lower.nth(i).clone();
+            let to_upper = upper.nth(i).clone() - point.nth(i).clone();
+            min_dist = min_inline(min_dist, min_inline(to_lower, to_upper));
+        }
+        -(min_dist.clone() * min_dist)
+    }
+
+    fn contains(&self, point: &V) -> bool {
+        self.rect.contains_point(point)
+    }
+}
+
+impl <V> RayIntersect for SimpleRectangle<V> where V: PointN, V::Scalar: SpadeFloat {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        self.rect.ray_intersection(origin, direction)
+    }
+}
+
+fn points_mbr<V: PointN>(points: &[V]) -> BoundingRect<V> {
+    let mut result = BoundingRect::from_point(points[0].clone());
+    for point in &points[1 ..] {
+        result.add_point(point.clone());
+    }
+    result
+}
+
+/// A chain of connected edges, defined by its vertices.
+///
+/// A point is contained in a polyline if its distance is zero.
+#[derive(Clone, Debug)]
+pub struct SimplePolyline<V: PointN> {
+    vertices: Vec<V>,
+    mbr: BoundingRect<V>,
+}
+
+impl <V> SimplePolyline<V> where V: PointN {
+    /// Creates a polyline that connects the given vertices in order.
+    ///
+    /// Panics if `vertices` is empty.
+    pub fn new(vertices: Vec<V>) -> SimplePolyline<V> {
+        assert!(!vertices.is_empty(), "A polyline needs at least one vertex");
+        let mbr = points_mbr(&vertices);
+        SimplePolyline { vertices, mbr }
+    }
+
+    /// Returns the polyline's vertices.
+    pub fn vertices(&self) -> &[V] {
+        &self.vertices
+    }
+
+    // A polyline with a single vertex consists of one degenerate edge
+    fn num_edges(&self) -> usize {
+        ::std::cmp::max(self.vertices.len() - 1, 1)
+    }
+
+    fn edge(&self, index: usize) -> SimpleEdge<V> {
+        let to = ::std::cmp::min(index + 1, self.vertices.len() - 1);
+        SimpleEdge::new(self.vertices[index].clone(), self.vertices[to].clone())
+    }
+}
+
+impl <V> SpatialObject for SimplePolyline<V> where V: PointN, V::Scalar: SpadeFloat {
+    type Point = V;
+
+    fn mbr(&self) -> BoundingRect<V> {
+        self.mbr.clone()
+    }
+
+    fn distance2(&self, point: &V) -> V::Scalar {
+        if self.vertices.len() == 1 {
+            return self.vertices[0].sub(point).length2();
+        }
+        let mut result = self.edge(0).distance2(point);
+        for i in 1 .. self.num_edges() {
+            result = result.min(self.edge(i).distance2(point));
+        }
+        result
+    }
+}
+
+impl <V> RayIntersect for SimplePolyline<V> where V: TwoDimensional, V::Scalar: SpadeFloat {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        let mut result: Option<V::Scalar> = None;
+        for i in 0 .. self.num_edges() {
+            if let Some(t) = self.edge(i).ray_intersection(origin, direction) {
+                result = Some(result.map(|r| r.min(t)).unwrap_or(t));
+            }
+        }
+        result
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RingPosition {
+    Inside,
+    Outside,
+    OnBoundary,
+}
+
+// Determines the position of a point relative to a closed ring with the
+// winding number algorithm, all side queries are evaluated exactly.
+fn ring_position<V>(ring: &[V], point: &V) -> RingPosition
+    where V: TwoDimensional, V::Scalar: SpadeFloat + FromPrimitive {
+    let y = *point.nth(1);
+    let mut winding = 0i32;
+    for i in 0 .. ring.len() {
+        let edge = SimpleEdge::new(ring[i].clone(), ring[(i + 1) % ring.len()].clone());
+        let side = edge.side_query::<FloatKernel>(point);
+        if side.is_on_line() && edge.mbr().contains_point(point) {
+            return RingPosition::OnBoundary;
+        }
+        let (from_y, to_y) = (*edge.from.nth(1), *edge.to.nth(1));
+        if from_y <= y {
+            if to_y > y && side.is_on_left_side() {
+                winding += 1;
+            }
+        } else if to_y <= y && side.is_on_right_side() {
+            winding -= 1;
+        }
+    }
+    if winding == 0 {
+        RingPosition::Outside
+    } else {
+        RingPosition::Inside
+    }
+}
+
+/// A polygon with holes.
+///
+/// The polygon's exterior and each of its holes is a ring of vertices that is
+/// closed implicitly by connecting its last and first vertex. Rings must not
+/// intersect themselves or each other and all holes must lie within the exterior.
+/// Points on the polygon's boundary, including the boundaries of its holes, are
+/// contained in the polygon. Point containment is checked with exact predicates,
+/// see `FloatKernel`.
+#[derive(Clone, Debug)]
+pub struct SimplePolygon<V: PointN> {
+    exterior: Vec<V>,
+    holes: Vec<Vec<V>>,
+    mbr: BoundingRect<V>,
+}
+
+impl <V> SimplePolygon<V> where V: TwoDimensional {
+    /// Creates a polygon without holes.
+    ///
+    /// Panics if the polygon has less than three vertices.
+    pub fn new(exterior: Vec<V>) -> SimplePolygon<V> {
+        SimplePolygon::with_holes(exterior, Vec::new())
+    }
+
+    /// Creates a polygon with holes.
+    ///
+    /// Panics if any ring has less than three vertices.
+    pub fn with_holes(exterior: Vec<V>, holes: Vec<Vec<V>>) -> SimplePolygon<V> {
+        assert!(exterior.len() >= 3 && holes.iter().all(|hole| hole.len() >= 3),
+                "Polygon rings need at least three vertices");
+        let mbr = points_mbr(&exterior);
+        SimplePolygon { exterior, holes, mbr }
+    }
+
+    /// Returns the vertices of the polygon's exterior.
+    pub fn exterior(&self) -> &[V] {
+        &self.exterior
+    }
+
+    /// Returns the vertices of each of the polygon's holes.
+    pub fn holes(&self) -> &[Vec<V>] {
+        &self.holes
+    }
+}
+
+impl <V> SimplePolygon<V> where V: TwoDimensional, V::Scalar: SpadeFloat + FromPrimitive {
+    fn position(&self, point: &V) -> RingPosition {
+        let position = ring_position(&self.exterior, point);
+        if position != RingPosition::Inside {
+            return position;
+        }
+        for hole in &self.holes {
+            match ring_position(hole, point) {
+                RingPosition::Inside => return RingPosition::Outside,
+                RingPosition::OnBoundary => return RingPosition::OnBoundary,
+                RingPosition::Outside => {},
+            }
+        }
+        RingPosition::Inside
+    }
+
+    // Calls f for each edge of the exterior and all holes
+    fn for_each_edge<F: FnMut(SimpleEdge<V>)>(&self, mut f: F) {
+        for ring in Some(&self.exterior).into_iter().chain(self.holes.iter()) {
+            for i in 0 .. ring.len() {
+                f(SimpleEdge::new(ring[i].clone(), ring[(i + 1) % ring.len()].clone()));
+            }
+        }
+    }
+}
+
+impl <V> SpatialObject for SimplePolygon<V>
+    where V: TwoDimensional, V::Scalar: SpadeFloat + FromPrimitive {
+    type Point = V;
+
+    fn mbr(&self) -> BoundingRect<V> {
+        self.mbr.clone()
+    }
+
+    /// Returns the squared distance to the polygon's boundary, negated if the
+    /// point lies within the polygon.
+    fn distance2(&self, point: &V) -> V::Scalar {
+        let position = self.position(point);
+        if position == RingPosition::OnBoundary {
+            return zero();
+        }
+        let mut result = Float::infinity();
+        self.for_each_edge(|edge| result = edge.distance2(point).min(result));
+        if position == RingPosition::Inside {
+            -result
+        } else {
+            result
+        }
+    }
+
+    fn contains(&self, point: &V) -> bool {
+        self.position(point) != RingPosition::Outside
+    }
+}
+
+impl <V> RayIntersect for SimplePolygon<V>
+    where V: TwoDimensional, V::Scalar: SpadeFloat + FromPrimitive {
+    fn ray_intersection(&self, origin: &V, direction: &V) -> Option<V::Scalar> {
+        if self.contains(origin) {
+            return Some(zero());
+        }
+        let mut result: Option<V::Scalar> = None;
+        self.for_each_edge(|edge| if let Some(t) = edge.ray_intersection(origin, direction) {
+            result = Some(result.map(|r| r.min(t)).unwrap_or(t));
+        });
+        result
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{SimpleEdge, SimpleTriangle, SimpleCircle};
-    use traits::SpatialObject;
+    use super::{SimpleEdge, SimpleTriangle, SimpleCircle, SimpleRectangle, SimplePolyline,
+                SimplePolygon};
+    use rtree::RTree;
+    use traits::{SpatialObject, RayIntersect};
     use kernels::TrivialKernel;
     use cgmath::{Point2, Point3};
     use approx::ApproxEq;
@@ -418,5 +816,113 @@ mod test {
         assert_eq!(c.contains(&p1), false);
         assert_eq!(c.contains(&p2), false);
         assert_eq!(c.contains(&p3), false);
-}
+    }
+
+    #[test]
+    fn test_ray_intersection() {
+        let origin = Point2::new(0f64, 0.);
+        let e = SimpleEdge::new(Point2::new(2., -1.), Point2::new(2., 1.));
+        assert_eq!(e.ray_intersection(&origin, &Point2::new(1., 0.)), Some(2.));
+        assert_eq!(e.ray_intersection(&origin, &Point2::new(-1., 0.)), None);
+        assert_eq!(e.ray_intersection(&origin, &Point2::new(1., 1.)), None);
+        let collinear = SimpleEdge::new(Point2::new(3., 0.), Point2::new(2., 0.));
+        assert_eq!(collinear.ray_intersection(&origin, &Point2::new(2., 0.)), Some(1.));
+        assert_eq!(collinear.ray_intersection(&Point2::new(2.5, 0.), &Point2::new(1., 0.)),
+                   Some(0.));
+
+        let t = SimpleTriangle::new(Point2::new(1., -1.), Point2::new(3., -1.), Point2::new(2., 1.));
+        assert_eq!(t.ray_intersection(&origin, &Point2::new(1., 0.)), Some(1.5));
+        assert_eq!(t.ray_intersection(&Point2::new(2., 0.), &Point2::new(1., 0.)), Some(0.));
+        assert_eq!(t.ray_intersection(&origin, &Point2::new(0., 1.)), None);
+
+        let c = SimpleCircle::new(Point3::new(0f64, 0., 5.), 1.0);
+        let origin = Point3::new(0., 0., 0.);
+        assert_eq!(c.ray_intersection(&origin, &Point3::new(0., 0., 2.)), Some(2.));
+        assert_eq!(c.ray_intersection(&origin, &Point3::new(0., 0., -1.)), None);
+        assert_eq!(c.ray_intersection(&origin, &Point3::new(1., 0., 1.)), None);
+        assert_eq!(c.ray_intersection(&Point3::new(0., 0.5, 5.), &Point3::new(1., 0., 0.)),
+                   Some(0.));
+    }
+
+    #[test]
+    fn test_rectangle_distance() {
+        let r = SimpleRectangle::new(Point2::new(2, 1), Point2::new(-2, -1));
+        assert_eq!(r.lower(), Point2::new(-2, -1));
+        assert_eq!(r.distance2(&Point2::new(4, 0)), 4);
+        assert_eq!(r.distance2(&Point2::new(3, 3)), 5);
+        assert_eq!(r.distance2(&Point2::new(2, 0)), 0);
+        assert_eq!(r.distance2(&Point2::new(0, 0)), -1);
+        assert_eq!(r.distance2(&Point2::new(-1, 0)), -1);
+        assert!(r.contains(&Point2::new(2, 1)));
+        assert!(!r.contains(&Point2::new(3, 1)));
+    }
+
+    #[test]
+    fn test_polyline_distance() {
+        let l = SimplePolyline::new(vec![Point2::new(0f64, 0.), Point2::new(2., 0.),
+                                         Point2::new(2., 2.)]);
+        assert_eq!(l.mbr().upper(), Point2::new(2., 2.));
+        assert_eq!(l.distance2(&Point2::new(1., 1.)), 1.);
+        assert_eq!(l.distance2(&Point2::new(3., 3.)), 2.);
+        assert_eq!(l.distance2(&Point2::new(0., 2.)), 4.);
+        assert!(l.contains(&Point2::new(2., 1.)));
+        assert!(!l.contains(&Point2::new(1., 1.)));
+        let single = SimplePolyline::new(vec![Point2::new(1f64, 1.)]);
+        assert_eq!(single.distance2(&Point2::new(2., 2.)), 2.);
+    }
+
+    fn square_with_hole() -> SimplePolygon<Point2<f64>> {
+        SimplePolygon::with_holes(
+            vec![Point2::new(0., 0.), Point2::new(4., 0.), Point2::new(4., 4.), Point2::new(0., 4.)],
+            vec![vec![Point2::new(1., 1.), Point2::new(1., 3.), Point2::new(3., 3.), Point2::new(3., 1.)]])
+    }
+
+    #[test]
+    fn test_polygon_contains() {
+        let p = square_with_hole();
+        assert!(p.contains(&Point2::new(0.5, 0.5)));
+        assert!(p.contains(&Point2::new(3.5, 2.0)));
+        assert!(!p.contains(&Point2::new(2.0, 2.0)));
+        assert!(!p.contains(&Point2::new(5.0, 2.0)));
+        assert!(!p.contains(&Point2::new(-1.0, 0.0)));
+        // Boundary points
+        assert!(p.contains(&Point2::new(0.0, 0.0)));
+        assert!(p.contains(&Point2::new(2.0, 4.0)));
+        assert!(p.contains(&Point2::new(1.0, 2.0)));
+        assert!(p.contains(&Point2::new(3.0, 3.0)));
+        // A concave polygon with a horizontal edge at the query point's height
+        let c = SimplePolygon::new(vec![Point2::new(0., 0.), Point2::new(4., 0.), Point2::new(4., 2.),
+                                        Point2::new(2., 1.), Point2::new(1., 1.), Point2::new(0., 2.)]);
+        assert!(c.contains(&Point2::new(0.5, 1.0)));
+        assert!(c.contains(&Point2::new(1.5, 1.0)));
+        assert!(!c.contains(&Point2::new(1.5, 1.5)));
+        assert!(!c.contains(&Point2::new(-0.5, 1.0)));
+        assert!(c.contains(&Point2::new(3.0, 1.0)));
+    }
+
+    #[test]
+    fn test_polygon_distance() {
+        let p = square_with_hole();
+        assert_eq!(p.mbr().lower(), Point2::new(0., 0.));
+        assert_eq!(p.distance2(&Point2::new(0.5, 2.0)), -0.25);
+        assert_eq!(p.distance2(&Point2::new(2.0, 2.5)), 0.25);
+        assert_eq!(p.distance2(&Point2::new(6.0, 2.0)), 4.0);
+        assert_eq!(p.distance2(&Point2::new(4.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn test_polygons_in_rtree() {
+        let mut tree = RTree::new();
+        tree.insert(square_with_hole());
+        tree.insert(SimplePolygon::new(vec![Point2::new(1.5, 1.5), Point2::new(2.5, 1.5),
+                                            Point2::new(2., 2.5)]));
+        assert_eq!(tree.lookup(&Point2::new(2.0, 2.0)).unwrap().exterior().len(), 3);
+        assert_eq!(tree.lookup(&Point2::new(0.5, 3.0)).unwrap().holes().len(), 1);
+        assert!(tree.lookup(&Point2::new(1.2, 2.0)).is_none());
+        assert_eq!(tree.nearest_neighbor(&Point2::new(2.0, 1.6)).unwrap().holes().len(), 0);
+        let hit = tree.ray_cast(&Point2::new(2.0, -1.0), &Point2::new(0.0, 1.0)).unwrap();
+        assert_eq!(hit.1, 1.0);
+        let hits = tree.ray_intersections(&Point2::new(2.0, 5.0), &Point2::new(0.0, -1.0));
+        assert_eq!(hits.iter().map(|h| h.1).collect::<Vec<_>>(), vec![1.0, 2.5]);
+    }
 }
@@ -0,0 +1,269 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Aggregate augmented r*-trees.
+
+use std::marker::PhantomData;
+use traits::SpatialObject;
+use boundingvolume::BoundingRect;
+use super::{RTree, RTreeNode, DirectoryNodeData};
+
+/// An aggregate that is cached by every directory node of an r-tree.
+///
+/// Aggregates form a monoid: `combine` must be associative and `empty` must be
+/// its identity element. Typical aggregates are counts, sums, minima or maxima
+/// of some property of the contained objects.
+/// An r-tree with aggregate `A` has type `RTree<T, A>`, the default
+/// aggregate `()` does not cache anything. Aggregate values must be comparable
+/// to allow `RTree::validate` to detect stale aggregates.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::{RTree, Aggregate};
+/// use spade::{HasPosition, BoundingRect};
+/// use cgmath::Point2;
+///
+/// struct Sensor {
+///   position: Point2<f64>,
+///   load: f64,
+/// }
+///
+/// impl HasPosition for Sensor {
+///   type Point = Point2<f64>;
+///   fn position(&self) -> Point2<f64> {
+///     self.position
+///   }
+/// }
+///
+/// // Sums up the load of all sensors
+/// struct TotalLoad;
+///
+/// impl Aggregate<Sensor> for TotalLoad {
+///   type Value = f64;
+///
+///   fn empty() -> f64 { 0.0 }
+///   fn leaf(sensor: &Sensor) -> f64 { sensor.load }
+///   fn combine(first: &f64, second: &f64) -> f64 { first + second }
+/// }
+///
+/// # fn main() {
+/// let mut tree: RTree<Sensor, TotalLoad> = RTree::new_aggregated();
+/// tree.insert(Sensor { position: Point2::new(0.0, 0.0), load: 2.0 });
+/// tree.insert(Sensor { position: Point2::new(1.0, 1.0), load: 3.0 });
+/// tree.insert(Sensor { position: Point2::new(5.0, 5.0), load: 7.0 });
+/// let rect = BoundingRect::from_corners(&Point2::new(-1.0, -1.0), &Point2::new(2.0, 2.0));
+/// assert_eq!(tree.aggregate_in_rectangle(&rect), 5.0);
+/// assert_eq!(tree.aggregate(), 12.0);
+/// # }
+/// ```
+pub trait Aggregate<T> {
+    /// The type of the aggregated values.
+    type Value: Clone + PartialEq;
+
+    /// Returns the aggregate of an empty set of objects.
+    fn empty() -> Self::Value;
+
+    /// Returns the aggregate of a single object.
+    fn leaf(object: &T) -> Self::Value;
+
+    /// Combines two aggregates.
+    fn combine(first: &Self::Value, second: &Self::Value) -> Self::Value;
+}
+
+impl <T> Aggregate<T> for () {
+    type Value = ();
+
+    fn empty() { }
+
+    fn leaf(_: &T) { }
+
+    fn combine(_: &(), _: &()) { }
+}
+
+/// Counts the objects of an r-tree, see `Aggregate`.
+pub struct Count<T> {
+    object_type: PhantomData<T>,
+}
+
+impl <T> Aggregate<T> for Count<T> {
+    type Value = usize;
+
+    fn empty() -> usize {
+        0
+    }
+
+    fn leaf(_: &T) -> usize {
+        1
+    }
+
+    fn combine(first: &usize, second: &usize) -> usize {
+        first + second
+    }
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn aggregate_in_rectangle(&self, query_rect: &BoundingRect<T::Point>) -> A::Value {
+        let mut result = A::empty();
+        for child in self.children.iter() {
+            let mbr = child.mbr();
+            if !mbr.intersects(query_rect) {
+                continue;
+            }
+            let aggregate = match *child {
+                RTreeNode::DirectoryNode(ref data) => {
+                    if query_rect.contains_rect(&mbr) {
+                        // All objects below this node are contained
+                        data.aggregate.clone()
+                    } else {
+                        data.aggregate_in_rectangle(query_rect)
+                    }
+                },
+                RTreeNode::Leaf(ref t) => A::leaf(t),
+            };
+            result = A::combine(&result, &aggregate);
+        }
+        result
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Creates an empty r*-tree that caches aggregates of type `A`.
+    ///
+    /// Each directory node caches the aggregate of all objects below it. The
+    /// aggregates are updated whenever objects are inserted, removed or updated.
+    /// Mutable queries like `lookup_mut` are only available for trees without
+    /// aggregates, use `update` or `update_all` to modify objects instead.
+    pub fn new_aggregated() -> RTree<T, A> {
+        RTree::new_with_options(Default::default())
+    }
+
+    /// Returns the aggregate of all contained objects.
+    pub fn aggregate(&self) -> A::Value {
+        self.root.aggregate.clone()
+    }
+
+    /// Returns the aggregate of all objects (partially) contained in a rectangle.
+    ///
+    /// Aggregates the same objects that `lookup_in_rectangle` returns. Cached
+    /// aggregates of directory nodes that lie completely within the rectangle
+    /// are used without visiting their children.
+    pub fn aggregate_in_rectangle(&self, query_rect: &BoundingRect<T::Point>) -> A::Value {
+        if self.size > 0 && self.root.mbr().intersects(query_rect) {
+            self.root.aggregate_in_rectangle(query_rect)
+        } else {
+            A::empty()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Aggregate, Count};
+    use super::super::RTree;
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    // Sums the x coordinates of all points
+    struct SumX;
+
+    impl Aggregate<Point2<i32>> for SumX {
+        type Value = i32;
+
+        fn empty() -> i32 {
+            0
+        }
+
+        fn leaf(point: &Point2<i32>) -> i32 {
+            point.x
+        }
+
+        fn combine(first: &i32, second: &i32) -> i32 {
+            first + second
+        }
+    }
+
+    #[test]
+    fn test_count_in_rectangle() {
+        let points = random_points_with_seed::<f64>(2000, [1, 2, 4, 8]);
+        let mut tree: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            tree.insert(*point);
+        }
+        assert_eq!(tree.aggregate(), points.len());
+        let rects = [
+            BoundingRect::from_corners(&Point2::new(-0.5, -0.5), &Point2::new(0.5, 0.5)),
+            BoundingRect::from_corners(&Point2::new(-2.0, -2.0), &Point2::new(2.0, 2.0)),
+            BoundingRect::from_corners(&Point2::new(0.9, -1.0), &Point2::new(3.0, 0.0)),
+            BoundingRect::from_corners(&Point2::new(5.0, 5.0), &Point2::new(6.0, 6.0)),
+        ];
+        for rect in &rects {
+            assert_eq!(tree.aggregate_in_rectangle(rect), tree.lookup_in_rectangle(rect).len());
+        }
+        for point in &points[.. 1500] {
+            assert!(tree.remove(point));
+        }
+        assert_eq!(tree.aggregate(), 500);
+        for rect in &rects {
+            assert_eq!(tree.aggregate_in_rectangle(rect), tree.lookup_in_rectangle(rect).len());
+        }
+    }
+
+    #[test]
+    fn test_sum_with_updates() {
+        let mut tree: RTree<Point2<i32>, SumX> = RTree::new_aggregated();
+        for x in 0 .. 30 {
+            for y in 0 .. 30 {
+                tree.insert(Point2::new(x, y));
+            }
+        }
+        assert_eq!(tree.aggregate(), 30 * (29 * 30 / 2));
+        let rect = BoundingRect::from_corners(&Point2::new(10, 0), &Point2::new(12, 9));
+        assert_eq!(tree.aggregate_in_rectangle(&rect), (10 + 11 + 12) * 10);
+        assert!(tree.update(&Point2::new(11, 5), |p| p.x = 100));
+        assert_eq!(tree.aggregate_in_rectangle(&rect), (10 + 12) * 10 + 11 * 9);
+        assert_eq!(tree.aggregate(), 30 * (29 * 30 / 2) + 89);
+        assert!(tree.validate().is_ok());
+    }
+
+    #[test]
+    fn test_aggregates_with_update_all() {
+        let mut tree: RTree<Point2<i32>, SumX> = RTree::new_aggregated();
+        for x in 0 .. 30 {
+            for y in 0 .. 30 {
+                tree.insert(Point2::new(x, y));
+            }
+        }
+        tree.update_all(|p| p.x *= 2);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.aggregate(), 2 * 30 * (29 * 30 / 2));
+        let rect = BoundingRect::from_corners(&Point2::new(20, 0), &Point2::new(24, 29));
+        assert_eq!(tree.aggregate_in_rectangle(&rect), (20 + 22 + 24) * 30);
+
+        let points = random_points_with_seed::<f64>(1000, [3, 1, 4, 1]);
+        let mut tree: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let rect = BoundingRect::from_corners(&Point2::new(-0.5, -0.5), &Point2::new(0.5, 0.5));
+        let inside = tree.lookup_in_rectangle(&rect).len();
+        tree.update_in_rectangle(&rect, |p| p.x += 3.0);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.aggregate(), points.len());
+        assert_eq!(tree.aggregate_in_rectangle(&rect), 0);
+        let moved = BoundingRect::from_corners(&Point2::new(2.5, -0.5), &Point2::new(3.5, 0.5));
+        assert_eq!(tree.aggregate_in_rectangle(&moved), inside);
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bulk loading of r*-trees.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+use traits::SpatialObject;
+use point_traits::PointN;
+use super::{Aggregate, RTree, RTreeNode, RTreeOptions, DirectoryNodeData};
+
+impl <T> RTree<T>
+    where T: SpatialObject {
+
+    /// Creates an r*-tree containing all given elements.
+    ///
+    /// The elements are sorted into tiles of nearly equal size, one axis after
+    /// the other, and each tile is loaded into its own subtree (also known as
+    /// *overlap minimizing top-down* bulk loading). This is considerably faster
+    /// than inserting the elements one by one and yields a tree with less
+    /// overlap between its nodes.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let points: Vec<_> = (0 .. 100).map(|x| Point2::new(x, x * x)).collect();
+    /// let tree = RTree::bulk_load(points);
+    /// assert_eq!(tree.size(), 100);
+    /// assert_eq!(tree.nearest_neighbor(&Point2::new(4, 20)), Some(&Point2::new(4, 16)));
+    /// # }
+    /// ```
+    pub fn bulk_load(elements: Vec<T>) -> RTree<T> {
+        RTree::bulk_load_with_options(elements, Default::default())
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Creates an r*-tree that caches aggregates of type `A` and contains all
+    /// given elements.
+    ///
+    /// The tree is built like in `RTree::bulk_load`, each node's aggregate is
+    /// computed once its children have been loaded.
+    pub fn bulk_load_aggregated(elements: Vec<T>) -> RTree<T, A> {
+        RTree::bulk_load_with_options(elements, Default::default())
+    }
+
+    #[doc(hidden)]
+    pub fn bulk_load_with_options(elements: Vec<T>, options: RTreeOptions<T::Point>)
+                                  -> RTree<T, A> {
+        if elements.is_empty() {
+            return RTree::new_with_options(options);
+        }
+        let size = elements.len();
+        let options = Arc::new(options);
+        let depth = root_depth(size, options.max_size);
+        RTree {
+            root: bulk_load_node(elements, depth, &options),
+            size,
+        }
+    }
+}
+
+// Returns the smallest depth of a tree that can hold `size` elements
+pub(crate) fn root_depth(size: usize, max_size: usize) -> usize {
+    let mut depth = 1;
+    let mut capacity = max_size;
+    while capacity < size {
+        capacity = capacity.saturating_mul(max_size);
+        depth += 1;
+    }
+    depth
+}
+
+// Splits the elements of a directory node at the given depth into
+// groups, one for each child node.
+pub(crate) fn partition_children<T, S>(elements: Vec<T>, depth: usize, max_size: usize,
+                                       sort: &S) -> Vec<Vec<T>>
+    where T: SpatialObject,
+          S: Fn(&mut [T], usize) {
+    let mut child_capacity = 1usize;
+    for _ in 1 .. depth {
+        child_capacity = child_capacity.saturating_mul(max_size);
+    }
+    let parts = (elements.len() - 1) / child_capacity + 1;
+    let mut result = Vec::with_capacity(parts);
+    partition(elements, parts, 0, sort, &mut result);
+    result
+}
+
+// Sorts the elements along an axis and cuts them into slabs. Each slab is
+// partitioned further along the next axis until `parts` groups of nearly
+// equal size are found.
+fn partition<T, S>(mut elements: Vec<T>, parts: usize, axis: usize, sort: &S,
+                   result: &mut Vec<Vec<T>>)
+    where T: SpatialObject,
+          S: Fn(&mut [T], usize) {
+    if parts <= 1 {
+        result.push(elements);
+        return;
+    }
+    sort(&mut elements, axis);
+    let remaining_axes = T::Point::dimensions() - axis;
+    let mut slab_count = 1usize;
+    while slab_count.pow(remaining_axes as u32) < parts {
+        slab_count += 1;
+    }
+    let len = elements.len();
+    let mut slabs = Vec::with_capacity(slab_count);
+    let mut parts_end = parts;
+    for slab in (0 .. slab_count).rev() {
+        let slab_parts = parts / slab_count + if slab < parts % slab_count { 1 } else { 0 };
+        let parts_start = parts_end - slab_parts;
+        slabs.push((elements.split_off(len * parts_start / parts), slab_parts));
+        parts_end = parts_start;
+    }
+    for (slab, slab_parts) in slabs.into_iter().rev() {
+        partition(slab, slab_parts, axis + 1, sort, result);
+    }
+}
+
+// Sorts elements by the center of their bounding rectangles
+pub(crate) fn sort_along_axis<T>(elements: &mut [T], axis: usize)
+    where T: SpatialObject {
+    elements.sort_by(|l, r| compare_along_axis(l, r, axis));
+}
+
+pub(crate) fn compare_along_axis<T>(l: &T, r: &T, axis: usize) -> Ordering
+    where T: SpatialObject {
+    let (l, r) = (l.mbr().center(), r.mbr().center());
+    l.nth(axis).partial_cmp(r.nth(axis)).unwrap_or(Ordering::Equal)
+}
+
+pub(crate) fn bulk_load_node<T, A>(elements: Vec<T>, depth: usize,
+                                   options: &Arc<RTreeOptions<T::Point>>) -> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    let children = if depth <= 1 {
+        elements.into_iter().map(RTreeNode::Leaf).collect()
+    } else {
+        partition_children(elements, depth, options.max_size, &sort_along_axis)
+            .into_iter()
+            .map(|group| RTreeNode::DirectoryNode(bulk_load_node(group, depth - 1, options)))
+            .collect()
+    };
+    // Computes the node's bounding rectangle and aggregate
+    DirectoryNodeData::new_parent(Box::new(children), depth, options.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::check_tree;
+    use super::super::{RTree, RTreeOptions, Count};
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_bulk_load() {
+        for &size in &[0, 1, 6, 7, 36, 37, 100, 1000, 4000] {
+            let points = random_points_with_seed::<f64>(size, [3, 1, 4, 1]);
+            let tree = RTree::bulk_load(points.clone());
+            assert_eq!(tree.size(), size);
+            check_tree(&tree);
+            for point in &points {
+                assert!(tree.contains(point));
+            }
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_with_options() {
+        let points = random_points_with_seed::<f64>(3000, [5, 9, 2, 6]);
+        let options = RTreeOptions::new().set_max_size(10).set_min_size(5);
+        let tree: RTree<_> = RTree::bulk_load_with_options(points.clone(), options);
+        check_tree(&tree);
+        assert_eq!(tree.size(), points.len());
+    }
+
+    #[test]
+    fn test_bulk_load_aggregated() {
+        for &size in &[0, 1, 7, 100, 3000] {
+            let points = random_points_with_seed::<f64>(size, [7, 3, 1, 5]);
+            let mut tree: RTree<_, Count<_>> = RTree::bulk_load_aggregated(points.clone());
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.aggregate(), size);
+            let rect = BoundingRect::from_corners(&Point2::new(-0.5, -0.5), &Point2::new(0.5, 0.5));
+            assert_eq!(tree.aggregate_in_rectangle(&rect), tree.lookup_in_rectangle(&rect).len());
+            tree.insert(Point2::new(0.0, 0.0));
+            assert_eq!(tree.aggregate(), size + 1);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_bulk_loaded_tree_queries() {
+        let points = random_points_with_seed::<f64>(1000, [2, 7, 1, 8]);
+        let mut tree = RTree::bulk_load(points.clone());
+        let query_points = random_points_with_seed::<f64>(100, [1, 4, 1, 4]);
+        for query in &query_points {
+            let nn = tree.nearest_neighbor(query).unwrap();
+            let expected = points.iter().map(|p| (p.x - query.x).powi(2) + (p.y - query.y).powi(2))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!((nn.x - query.x).powi(2) + (nn.y - query.y).powi(2), expected);
+        }
+        // Bulk loaded trees must support insertion and removal
+        for point in &points[.. 500] {
+            assert!(tree.remove(point));
+        }
+        tree.insert(Point2::new(0.0, 0.0));
+        assert_eq!(tree.size(), 501);
+        check_tree(&tree);
+    }
+}
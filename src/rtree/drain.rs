@@ -0,0 +1,208 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bulk removal of r*-tree elements.
+
+use std::vec::IntoIter;
+use traits::SpatialObject;
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData, SelectionFunction};
+use super::selection::SelectInRectangle;
+
+/// Iterates over the elements removed by a drain operation.
+///
+/// All elements are removed from the tree before the iterator is returned,
+/// dropping the iterator will not put them back.
+pub struct DrainIterator<T> {
+    removed: IntoIter<T>,
+}
+
+impl <T> Iterator for DrainIterator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.removed.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.removed.size_hint()
+    }
+}
+
+impl <T> ExactSizeIterator for DrainIterator<T> { }
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    // Removes all selected leaves below this node. Directory nodes that become
+    // underfull are dissolved, their children are added to `orphans`.
+    // Returns true if this node has changed.
+    fn drain_selected<P, L>(&mut self, unpack_parent: &P, select_leaf: &mut L,
+                            removed: &mut Vec<T>, orphans: &mut Vec<RTreeNode<T, A>>) -> bool
+        where P: Fn(&BoundingRect<T::Point>) -> bool,
+              L: FnMut(&T) -> bool {
+        let mut changed = false;
+        let mut children = ::std::mem::replace(&mut self.children,
+                                               Box::new(Vec::new()));
+        for child in children.drain(..) {
+            match child {
+                RTreeNode::DirectoryNode(mut data) => {
+                    if unpack_parent(&data.mbr()) &&
+                        data.drain_selected(unpack_parent, select_leaf, removed, orphans) {
+                        changed = true;
+                        if data.children.len() < self.options.min_size {
+                            // Condense the tree, the remaining children are reinserted later
+                            orphans.extend(data.children.drain(..));
+                            continue;
+                        }
+                    }
+                    self.children.push(RTreeNode::DirectoryNode(data));
+                },
+                RTreeNode::Leaf(t) => {
+                    if select_leaf(&t) {
+                        removed.push(t);
+                        changed = true;
+                    } else {
+                        self.children.push(RTreeNode::Leaf(t));
+                    }
+                },
+            }
+        }
+        if changed {
+            self.update_mbr();
+        }
+        changed
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Removes all objects (partially) contained in a rectangle.
+    ///
+    /// Returns an iterator over the removed objects.
+    pub fn drain_in_rectangle(&mut self, query_rect: &BoundingRect<T::Point>)
+                              -> DrainIterator<T> {
+        self.drain_with_selection(&SelectInRectangle { query_rect })
+    }
+
+    /// Removes all objects selected by a selection function.
+    ///
+    /// Returns an iterator over the removed objects.
+    pub fn drain_with_selection<S>(&mut self, selection: &S) -> DrainIterator<T>
+        where S: SelectionFunction<T> {
+        self.drain_selected(&|mbr| selection.should_unpack_parent(mbr),
+                            &mut |t| selection.should_unpack_leaf(t))
+    }
+
+    /// Retains only the objects for which a predicate returns `true`.
+    ///
+    /// Returns an iterator over the removed objects.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// for x in 0 .. 10 {
+    ///   tree.insert(Point2::new(x, 0));
+    /// }
+    /// let removed: Vec<_> = tree.retain(|p| p.x % 2 == 0).collect();
+    /// assert_eq!(removed.len(), 5);
+    /// assert_eq!(tree.size(), 5);
+    /// # }
+    /// ```
+    pub fn retain<F>(&mut self, mut predicate: F) -> DrainIterator<T>
+        where F: FnMut(&T) -> bool {
+        self.drain_selected(&|_| true, &mut |t| !predicate(t))
+    }
+
+    // Removes all selected elements in a single pass. Underfull nodes are
+    // dissolved and their remaining entries are reinserted at their level.
+    fn drain_selected<P, L>(&mut self, unpack_parent: &P, select_leaf: &mut L)
+                            -> DrainIterator<T>
+        where P: Fn(&BoundingRect<T::Point>) -> bool,
+              L: FnMut(&T) -> bool {
+        let mut removed = Vec::new();
+        let mut orphans = Vec::new();
+        if self.size > 0 && unpack_parent(&self.root.mbr()) {
+            self.root.drain_selected(unpack_parent, select_leaf, &mut removed, &mut orphans);
+        }
+        self.size -= removed.len();
+        self.condense(orphans);
+        DrainIterator { removed: removed.into_iter() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::check_tree;
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_drain_in_rectangle() {
+        let (mut tree, points) = create_random_tree::<f64>(1000, [1, 3, 3, 7]);
+        let rect = BoundingRect::from_corners(&Point2::new(-0.5, -0.8), &Point2::new(0.7, 0.1));
+        let expected = points.iter().filter(|p| rect.contains_point(p)).count();
+        let removed: Vec<_> = tree.drain_in_rectangle(&rect).collect();
+        assert_eq!(removed.len(), expected);
+        assert!(removed.iter().all(|p| rect.contains_point(p)));
+        assert!(tree.lookup_in_rectangle(&rect).is_empty());
+        assert_eq!(tree.size(), points.len() - expected);
+        check_tree(&tree);
+        for point in &points {
+            assert_eq!(tree.contains(point), !rect.contains_point(point));
+        }
+        // The tree must remain usable
+        for point in &removed {
+            tree.insert(*point);
+        }
+        assert_eq!(tree.size(), points.len());
+        check_tree(&tree);
+    }
+
+    #[test]
+    fn test_retain() {
+        let (mut tree, points) = create_random_tree::<f64>(1000, [2, 0, 1, 7]);
+        let removed = tree.retain(|p| p.x + p.y > 0.5).count();
+        let expected = points.iter().filter(|p| p.x + p.y > 0.5).count();
+        assert_eq!(tree.size(), expected);
+        assert_eq!(removed, points.len() - expected);
+        check_tree(&tree);
+        assert!(tree.iter().all(|p| p.x + p.y > 0.5));
+
+        tree.retain(|_| false);
+        assert_eq!(tree.size(), 0);
+        check_tree(&tree);
+        tree.insert(Point2::new(0.0, 0.0));
+        assert_eq!(tree.size(), 1);
+        check_tree(&tree);
+    }
+
+    #[test]
+    fn test_retain_most() {
+        // Only few elements remain, the tree's height shrinks
+        let (mut tree, points) = create_random_tree::<f64>(2000, [9, 8, 7, 6]);
+        let old_depth = tree.root.depth;
+        tree.retain(|p| p.x > 0.95);
+        let expected: Vec<_> = points.iter().filter(|p| p.x > 0.95).collect();
+        assert_eq!(tree.size(), expected.len());
+        assert!(tree.root.depth < old_depth);
+        check_tree(&tree);
+        for point in expected {
+            assert!(tree.contains(point));
+        }
+    }
+}
@@ -0,0 +1,546 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A read-only r*-tree with a flat memory layout.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::slice::Iter;
+use std::sync::Arc;
+use traits::SpatialObject;
+use point_traits::{PointN, PointNExtensions};
+use boundingvolume::BoundingRect;
+use misc::MinHeapEntry;
+use super::{Aggregate, RTree, RTreeNode, RTreeOptions, DirectoryNodeData, SelectionFunction};
+
+/// An immutable r*-tree with a flat, cache friendly memory layout.
+///
+/// All directory nodes are stored in breadth-first order in one contiguous array,
+/// the children of a node are stored next to each other. Bounding boxes are
+/// stored as separate arrays of lower and upper corners. All elements are stored
+/// in a single contiguous array as well.
+/// A frozen tree supports the same queries as `RTree` but cannot be modified.
+/// It is created with `RTree::freeze()` and can be turned back into a dynamic
+/// tree with `FrozenRTree::thaw()`. Frozen aggregate augmented trees keep the
+/// cached aggregate of each node.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::RTree;
+/// use cgmath::Point2;
+///
+/// # fn main() {
+/// let mut tree = RTree::new();
+/// tree.insert(Point2::new(0.0, 1.0));
+/// tree.insert(Point2::new(2.0, 0.5));
+/// let frozen = tree.freeze();
+/// assert_eq!(frozen.nearest_neighbor(&Point2::new(1.5, 0.0)), Some(&Point2::new(2.0, 0.5)));
+/// let mut tree = frozen.thaw();
+/// tree.insert(Point2::new(1.5, 0.0));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FrozenRTree<T, A = ()>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    lower: Vec<T::Point>,
+    upper: Vec<T::Point>,
+    aggregates: Vec<A::Value>,
+    first_child: Vec<usize>,
+    child_count: Vec<usize>,
+    // All nodes starting at this index contain leaves, all other nodes
+    // contain directory nodes
+    first_leaf_parent: usize,
+    leaves: Vec<T>,
+    options: Arc<RTreeOptions<T::Point>>,
+}
+
+#[derive(Clone, Copy)]
+enum FrozenEntry {
+    Node(usize),
+    Leaf(usize),
+}
+
+type Scalar<T> = <<T as SpatialObject>::Point as PointN>::Scalar;
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    /// Converts this tree into an immutable `FrozenRTree`.
+    ///
+    /// The frozen tree keeps this tree's structure and cached aggregates.
+    pub fn freeze(self) -> FrozenRTree<T, A> {
+        let mut result = FrozenRTree {
+            lower: Vec::new(),
+            upper: Vec::new(),
+            aggregates: Vec::new(),
+            first_child: Vec::new(),
+            child_count: Vec::new(),
+            first_leaf_parent: 0,
+            leaves: Vec::with_capacity(self.size),
+            options: self.root.options.clone(),
+        };
+        if self.size == 0 {
+            return result;
+        }
+        let mut queue = VecDeque::new();
+        queue.push_back(self.root);
+        let mut num_nodes = 1;
+        while let Some(mut node) = queue.pop_front() {
+            let mbr = node.mbr();
+            result.lower.push(mbr.lower());
+            result.upper.push(mbr.upper());
+            result.aggregates.push(node.aggregate);
+            result.child_count.push(node.children.len());
+            if node.depth == 1 {
+                if result.leaves.is_empty() {
+                    result.first_leaf_parent = result.first_child.len();
+                }
+                result.first_child.push(result.leaves.len());
+            } else {
+                result.first_child.push(num_nodes);
+                num_nodes += node.children.len();
+            }
+            for child in node.children.drain(..) {
+                match child {
+                    RTreeNode::Leaf(t) => result.leaves.push(t),
+                    RTreeNode::DirectoryNode(data) => queue.push_back(data),
+                }
+            }
+        }
+        result
+    }
+}
+
+impl <T, A> FrozenRTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Converts this frozen tree back into a dynamic `RTree`.
+    pub fn thaw(self) -> RTree<T, A> {
+        let size = self.leaves.len();
+        let options = self.options.clone();
+        if size == 0 {
+            return RTree::new_with_options((*options).clone());
+        }
+        let depth = self.depth();
+        // Breadth first order visits the leaves in the same order as a depth
+        // first traversal, thus the leaves can be taken one after another
+        let FrozenRTree { first_child, child_count, first_leaf_parent, leaves, .. } = self;
+        let mut leaves = leaves.into_iter();
+        let root = thaw_node(0, depth, &first_child, &child_count, first_leaf_parent,
+                             &mut leaves, &options);
+        RTree {
+            root,
+            size,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        let mut depth = 1;
+        let mut node = 0;
+        while node < self.first_leaf_parent {
+            node = self.first_child[node];
+            depth += 1;
+        }
+        depth
+    }
+
+    /// Returns the number of elements contained in this tree.
+    pub fn size(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns the tree's minimal bounding box.
+    pub fn mbr(&self) -> Option<BoundingRect<T::Point>> {
+        if self.leaves.is_empty() {
+            None
+        } else {
+            Some(self.node_mbr(0))
+        }
+    }
+
+    /// Returns an iterator over all contained elements.
+    pub fn iter<'a>(&'a self) -> Iter<'a, T> {
+        self.leaves.iter()
+    }
+
+    /// Returns the aggregate of all contained objects.
+    pub fn aggregate(&self) -> A::Value {
+        self.aggregates.first().cloned().unwrap_or_else(A::empty)
+    }
+
+    /// Returns the aggregate of all objects (partially) contained in a rectangle.
+    ///
+    /// Cached aggregates of nodes that lie completely within the rectangle are
+    /// used without visiting their children, see `RTree::aggregate_in_rectangle`.
+    pub fn aggregate_in_rectangle(&self, query_rect: &BoundingRect<T::Point>) -> A::Value {
+        if self.leaves.is_empty() {
+            A::empty()
+        } else {
+            self.node_aggregate_in_rectangle(0, query_rect)
+        }
+    }
+
+    fn node_aggregate_in_rectangle(&self, node: usize, query_rect: &BoundingRect<T::Point>)
+                                   -> A::Value {
+        let mbr = self.node_mbr(node);
+        if !mbr.intersects(query_rect) {
+            return A::empty();
+        }
+        if query_rect.contains_rect(&mbr) {
+            // All objects below this node are contained
+            return self.aggregates[node].clone();
+        }
+        let mut result = A::empty();
+        for child in self.children(node) {
+            let aggregate = if node >= self.first_leaf_parent {
+                let leaf = &self.leaves[child];
+                if !leaf.mbr().intersects(query_rect) {
+                    continue;
+                }
+                A::leaf(leaf)
+            } else {
+                self.node_aggregate_in_rectangle(child, query_rect)
+            };
+            result = A::combine(&result, &aggregate);
+        }
+        result
+    }
+
+    fn node_mbr(&self, node: usize) -> BoundingRect<T::Point> {
+        BoundingRect::from_corners(&self.lower[node], &self.upper[node])
+    }
+
+    fn node_contains_point(&self, node: usize, point: &T::Point) -> bool {
+        self.lower[node].all_comp_wise(point, |l, r| l <= r) &&
+            self.upper[node].all_comp_wise(point, |u, r| u >= r)
+    }
+
+    fn children(&self, node: usize) -> ::std::ops::Range<usize> {
+        let first = self.first_child[node];
+        first .. first + self.child_count[node]
+    }
+
+    // Calls `f` for all leaves below nodes accepted by `unpack_parent` until `f`
+    // returns false.
+    fn visit_leaves<'a, P, F>(&'a self, unpack_parent: P, mut f: F)
+        where P: Fn(usize) -> bool,
+              F: FnMut(&'a T) -> bool {
+        if self.leaves.is_empty() || !unpack_parent(0) {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            if node >= self.first_leaf_parent {
+                for leaf in &self.leaves[self.children(node)] {
+                    if !f(leaf) {
+                        return;
+                    }
+                }
+            } else {
+                stack.extend(self.children(node).filter(|child| unpack_parent(*child)));
+            }
+        }
+    }
+
+    /// Searches for an element at a given position.
+    ///
+    /// If `query_point` is contained by one object in the tree, this object will be returned.
+    /// If multiple objects contain the point, only one of them will be returned.
+    pub fn lookup(&self, query_point: &T::Point) -> Option<&T> {
+        let mut result = None;
+        self.visit_leaves(|node| self.node_contains_point(node, query_point), |t| {
+            if t.contains(query_point) {
+                result = Some(t);
+                false
+            } else {
+                true
+            }
+        });
+        result
+    }
+
+    /// Returns all objects (partially) contained in a rectangle
+    pub fn lookup_in_rectangle(&self, query_rect: &BoundingRect<T::Point>) -> Vec<&T> {
+        let mut result = Vec::new();
+        self.visit_leaves(|node| self.node_mbr(node).intersects(query_rect), |t| {
+            if t.mbr().intersects(query_rect) {
+                result.push(t);
+            }
+            true
+        });
+        result
+    }
+
+    /// Returns all objects (partially) contained in a circle.
+    ///
+    /// Note that `radius2` is the circle's squared radius, not the actual radius.
+    /// An object is contained if a part of it lies within the circle.
+    pub fn lookup_in_circle(&self, circle_origin: &T::Point,
+                            radius2: &Scalar<T>) -> Vec<&T> {
+        let mut result = Vec::new();
+        self.visit_leaves(
+            |node| self.node_mbr(node).min_dist2(circle_origin) <= *radius2, |t| {
+                if t.distance2(circle_origin) < *radius2 {
+                    result.push(t);
+                }
+                true
+            });
+        result
+    }
+
+    /// Returns all elements selected by a selection function.
+    pub fn locate_with_selection_function<S>(&self, selection: &S) -> Vec<&T>
+        where S: SelectionFunction<T> {
+        let mut result = Vec::new();
+        self.visit_leaves(|node| selection.should_unpack_parent(&self.node_mbr(node)), |t| {
+            if selection.should_unpack_leaf(t) {
+                result.push(t);
+            }
+            true
+        });
+        result
+    }
+
+    /// Returns an object close to a given point. This operation is faster than
+    /// `nearest_neighbor` but will not neccessarily yield the real nearest neighbor.
+    pub fn close_neighbor(&self, point: &T::Point) -> Option<&T> {
+        if self.leaves.is_empty() {
+            return None;
+        }
+        let mut node = 0;
+        loop {
+            let closest = self.children(node).min_by(|l, r| {
+                let (l, r) = if node >= self.first_leaf_parent {
+                    (self.leaves[*l].distance2(point), self.leaves[*r].distance2(point))
+                } else {
+                    (self.node_mbr(*l).min_dist2(point), self.node_mbr(*r).min_dist2(point))
+                };
+                l.partial_cmp(&r).unwrap_or(Ordering::Equal)
+            });
+            match closest {
+                Some(closest) if node >= self.first_leaf_parent =>
+                    return Some(&self.leaves[closest]),
+                Some(closest) => node = closest,
+                None => return None,
+            }
+        }
+    }
+
+    // Returns elements in order of increasing distance until `take` returns false.
+    fn nearest_elements<'a, F>(&'a self, point: &T::Point, mut take: F)
+        where F: FnMut(&'a T, &Scalar<T>) -> bool {
+        if self.leaves.is_empty() {
+            return;
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push(MinHeapEntry {
+            distance2: self.node_mbr(0).min_dist2(point),
+            entry: FrozenEntry::Node(0),
+        });
+        while let Some(MinHeapEntry { distance2, entry }) = heap.pop() {
+            match entry {
+                FrozenEntry::Leaf(leaf) => {
+                    if !take(&self.leaves[leaf], &distance2) {
+                        return;
+                    }
+                },
+                FrozenEntry::Node(node) => {
+                    for child in self.children(node) {
+                        let entry = if node >= self.first_leaf_parent {
+                            MinHeapEntry {
+                                distance2: self.leaves[child].distance2(point),
+                                entry: FrozenEntry::Leaf(child),
+                            }
+                        } else {
+                            MinHeapEntry {
+                                distance2: self.node_mbr(child).min_dist2(point),
+                                entry: FrozenEntry::Node(child),
+                            }
+                        };
+                        heap.push(entry);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Returns the nearest neighbor.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn nearest_neighbor(&self, query_point: &T::Point) -> Option<&T> {
+        let mut result = None;
+        self.nearest_elements(query_point, |t, _| {
+            result = Some(t);
+            false
+        });
+        result
+    }
+
+    /// Returns the nearest neighbors of a given point.
+    ///
+    /// All returned values will have the exact same distance from the given query point.
+    /// Returns an empty `Vec` if the tree is empty.
+    pub fn nearest_neighbors(&self, query_point: &T::Point) -> Vec<&T> {
+        let mut result = Vec::new();
+        let mut nearest_distance = None;
+        self.nearest_elements(query_point, |t, distance2| {
+            match nearest_distance {
+                Some(ref nearest) if distance2 > nearest => return false,
+                Some(_) => {},
+                None => nearest_distance = Some(distance2.clone()),
+            }
+            result.push(t);
+            true
+        });
+        result
+    }
+
+    /// Returns the nearest n neighbors.
+    pub fn nearest_n_neighbors(&self, query_point: &T::Point, n: usize) -> Vec<&T> {
+        let mut result = Vec::with_capacity(n);
+        if n > 0 {
+            self.nearest_elements(query_point, |t, _| {
+                result.push(t);
+                result.len() < n
+            });
+        }
+        result
+    }
+}
+
+impl <T, A> FrozenRTree<T, A>
+    where T: SpatialObject + PartialEq,
+          A: Aggregate<T> {
+    /// Returns `true` if a given object is contained in this tree.
+    pub fn contains(&self, obj: &T) -> bool {
+        let mbr = obj.mbr();
+        let mut result = false;
+        self.visit_leaves(|node| self.node_mbr(node).contains_rect(&mbr), |t| {
+            result = t == obj;
+            !result
+        });
+        result
+    }
+}
+
+fn thaw_node<T, A, I>(node: usize, depth: usize, first_child: &[usize], child_count: &[usize],
+                      first_leaf_parent: usize, leaves: &mut I,
+                      options: &Arc<RTreeOptions<T::Point>>) -> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T>,
+          I: Iterator<Item=T> {
+    let mut children = Vec::with_capacity(child_count[node]);
+    if node >= first_leaf_parent {
+        for _ in 0 .. child_count[node] {
+            children.push(RTreeNode::Leaf(leaves.next().unwrap()));
+        }
+    } else {
+        let first = first_child[node];
+        for child in first .. first + child_count[node] {
+            children.push(RTreeNode::DirectoryNode(thaw_node(
+                child, depth - 1, first_child, child_count, first_leaf_parent,
+                leaves, options)));
+        }
+    }
+    DirectoryNodeData::new_parent(Box::new(children), depth, options.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{RTree, Count};
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_frozen_queries() {
+        let (tree, points) = create_random_tree::<f64>(1000, [4, 8, 15, 16]);
+        let frozen = tree.clone().freeze();
+        assert_eq!(frozen.size(), tree.size());
+        assert_eq!(frozen.mbr(), tree.mbr());
+        assert_eq!(frozen.iter().count(), points.len());
+        for point in &points {
+            assert_eq!(frozen.lookup(point), Some(point));
+            assert!(frozen.contains(point));
+        }
+        let queries = random_points_with_seed::<f64>(100, [23, 42, 1, 2]);
+        for query in &queries {
+            assert_eq!(frozen.nearest_neighbor(query), tree.nearest_neighbor(query));
+            assert_eq!(frozen.nearest_neighbors(query), tree.nearest_neighbors(query));
+            assert_eq!(frozen.nearest_n_neighbors(query, 7), tree.nearest_n_neighbors(query, 7));
+            assert!(frozen.close_neighbor(query).is_some());
+            assert!(!frozen.contains(query));
+            assert_eq!(frozen.lookup(query), None);
+
+            let in_circle = frozen.lookup_in_circle(query, &0.05);
+            assert_eq!(in_circle.len(), tree.lookup_in_circle(query, &0.05).len());
+            assert!(in_circle.iter().all(|p| tree.lookup_in_circle(query, &0.05).contains(p)));
+
+            let rect = BoundingRect::from_corners(query, &Point2::new(0.0, 0.0));
+            assert_eq!(frozen.lookup_in_rectangle(&rect).len(),
+                       tree.lookup_in_rectangle(&rect).len());
+        }
+    }
+
+    #[test]
+    fn test_freeze_and_thaw() {
+        let (tree, points) = create_random_tree::<f64>(500, [1, 1, 2, 3]);
+        let mut thawed = tree.freeze().thaw();
+        assert_eq!(thawed.size(), points.len());
+        for point in &points {
+            assert_eq!(thawed.nearest_neighbor(point), Some(point));
+        }
+        thawed.insert(Point2::new(2.0, 2.0));
+        assert!(thawed.remove(&points[0]));
+        assert_eq!(thawed.size(), points.len());
+        assert_eq!(thawed.iter().count(), points.len());
+
+        let empty: RTree<Point2<f64>> = RTree::new();
+        let frozen = empty.freeze();
+        assert_eq!(frozen.size(), 0);
+        assert_eq!(frozen.nearest_neighbor(&Point2::new(0.0, 0.0)), None);
+        assert!(frozen.nearest_n_neighbors(&Point2::new(0.0, 0.0), 3).is_empty());
+        assert_eq!(frozen.close_neighbor(&Point2::new(0.0, 0.0)), None);
+        let mut thawed = frozen.thaw();
+        thawed.insert(Point2::new(0.0, 0.0));
+        assert_eq!(thawed.size(), 1);
+    }
+
+    #[test]
+    fn test_freeze_aggregated_tree() {
+        let points = random_points_with_seed::<f64>(1000, [9, 4, 7, 3]);
+        let mut tree: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let rects: Vec<_> = random_points_with_seed::<f64>(50, [2, 2, 7, 7]).iter()
+            .map(|query| BoundingRect::from_corners(query, &Point2::new(0.1, -0.2)))
+            .collect();
+        let expected: Vec<_> = rects.iter().map(|rect| tree.aggregate_in_rectangle(rect)).collect();
+        let frozen = tree.freeze();
+        assert_eq!(frozen.aggregate(), points.len());
+        for (rect, expected) in rects.iter().zip(expected) {
+            assert_eq!(frozen.aggregate_in_rectangle(rect), expected);
+        }
+        let mut thawed = frozen.thaw();
+        assert_eq!(thawed.validate(), Ok(()));
+        assert_eq!(thawed.aggregate(), points.len());
+        thawed.insert(Point2::new(2.0, 2.0));
+        assert_eq!(thawed.aggregate(), points.len() + 1);
+
+        let empty: RTree<Point2<f64>, Count<_>> = RTree::new_aggregated();
+        let frozen = empty.freeze();
+        assert_eq!(frozen.aggregate(), 0);
+        let rect = BoundingRect::from_point(Point2::new(0.0, 0.0));
+        assert_eq!(frozen.aggregate_in_rectangle(&rect), 0);
+    }
+}
@@ -0,0 +1,633 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Spatial joins between two r*-trees.
+
+use std::collections::BinaryHeap;
+use num::zero;
+use traits::{SpatialObject, HasPosition};
+use point_traits::PointN;
+use boundingvolume::BoundingRect;
+use misc::MinHeapEntry;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+type Scalar<T> = <<T as SpatialObject>::Point as PointN>::Scalar;
+
+type PairHeap<'a, T, A, U, B> = BinaryHeap<MinHeapEntry<Scalar<T>, NodePair<'a, T, A, U, B>>>;
+
+impl<T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Returns all pairs of objects from this and another tree whose
+    /// bounding rectangles intersect.
+    ///
+    /// Both trees are traversed simultaneously, only descending into pairs of
+    /// nodes whose bounding rectangles intersect. This is considerably faster than
+    /// querying `other` once for every element of this tree.
+    /// The result contains only candidates: Two objects with intersecting
+    /// bounding rectangles do not necessarily intersect themselves. Use
+    /// `intersecting_pairs_with_other_tree` to refine the result with an exact
+    /// intersection test.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use spade::primitives::{SimpleCircle, SimpleEdge};
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut circles = RTree::new();
+    /// circles.insert(SimpleCircle::new(Point2::new(0.0, 0.0), 1.0));
+    /// circles.insert(SimpleCircle::new(Point2::new(5.0, 5.0), 1.0));
+    /// let mut edges = RTree::new();
+    /// edges.insert(SimpleEdge::new(Point2::new(-2.0, 0.5), Point2::new(2.0, 0.5)));
+    ///
+    /// let candidates = circles.intersection_candidates_with_other_tree(&edges);
+    /// assert_eq!(candidates.len(), 1);
+    /// assert_eq!(candidates[0].0.center, Point2::new(0.0, 0.0));
+    /// # }
+    /// ```
+    pub fn intersection_candidates_with_other_tree<'a, U, B>(&'a self, other: &'a RTree<U, B>)
+                                                             -> Vec<(&'a T, &'a U)>
+        where U: SpatialObject<Point=T::Point>,
+              B: Aggregate<U> {
+        self.intersecting_pairs_with_other_tree(other, |_, _| true)
+    }
+
+    /// Returns all pairs of objects from this and another tree that pass
+    /// an exact intersection test.
+    ///
+    /// Works like `intersection_candidates_with_other_tree`, but every candidate
+    /// pair is passed to `refine`. Only pairs for which `refine` returns `true`
+    /// are contained in the result.
+    pub fn intersecting_pairs_with_other_tree<'a, U, B, F>(&'a self, other: &'a RTree<U, B>,
+                                                            mut refine: F)
+                                                            -> Vec<(&'a T, &'a U)>
+        where U: SpatialObject<Point=T::Point>,
+              B: Aggregate<U>,
+              F: FnMut(&T, &U) -> bool {
+        let mut result = Vec::new();
+        join_nodes(&self.root.children, &other.root.children,
+                   &|l, r| l.intersects(r), &mut refine, &mut result);
+        result
+    }
+
+    /// Returns all pairs of objects from this and another tree that are within
+    /// a given distance.
+    ///
+    /// `max_distance2` is the squared distance. The distance of a pair is the
+    /// distance of the position of the object from `other` to the object from
+    /// this tree.
+    /// Pairs of nodes whose bounding rectangles are farther apart than the given
+    /// distance are skipped during the simultaneous traversal of both trees.
+    pub fn pairs_within_distance_with_other_tree<'a, U, B>(&'a self, other: &'a RTree<U, B>,
+                                                            max_distance2: Scalar<T>)
+                                                            -> Vec<(&'a T, &'a U)>
+        where U: HasPosition<Point=T::Point>,
+              B: Aggregate<U> {
+        self.pairs_within_distance_with_other_tree_by(
+            other, max_distance2, |t, u| t.distance2(&u.position()))
+    }
+
+    /// Returns all pairs of objects from this and another tree that are within
+    /// a given distance, measured by a custom distance function.
+    ///
+    /// `distance2` returns the squared distance of two objects. It must never be
+    /// smaller than the squared distance of the objects' bounding rectangles.
+    pub fn pairs_within_distance_with_other_tree_by<'a, U, B, F>(&'a self,
+                                                                  other: &'a RTree<U, B>,
+                                                                  max_distance2: Scalar<T>,
+                                                                  mut distance2: F)
+                                                                  -> Vec<(&'a T, &'a U)>
+        where U: SpatialObject<Point=T::Point>,
+              B: Aggregate<U>,
+              F: FnMut(&T, &U) -> Scalar<T> {
+        let mut result = Vec::new();
+        let max = max_distance2;
+        join_nodes(&self.root.children, &other.root.children,
+                   &|l, r| l.min_dist2_rect(r) <= max,
+                   &mut |t, u| distance2(t, u) <= max, &mut result);
+        result
+    }
+
+    /// Returns the `k` closest pairs of objects from this and another tree.
+    ///
+    /// The distance of a pair is the distance of the position of the object from
+    /// `other` to the object from this tree. The pairs are sorted by
+    /// their distance, starting with the closest pair. Less than `k` pairs are
+    /// returned if there are not enough pairs.
+    ///
+    /// The pairs are found with a best-first traversal of both trees that uses the
+    /// distance between two bounding rectangles as a lower bound for the distance
+    /// of all pairs below them.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use spade::primitives::SimpleEdge;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut roads = RTree::new();
+    /// roads.insert(SimpleEdge::new(Point2::new(0.0, 0.0), Point2::new(10.0, 0.0)));
+    /// roads.insert(SimpleEdge::new(Point2::new(0.0, 5.0), Point2::new(10.0, 5.0)));
+    /// let mut fixes = RTree::new();
+    /// fixes.insert(Point2::new(3.0, 0.5));
+    /// fixes.insert(Point2::new(6.0, 2.0));
+    ///
+    /// let closest = roads.k_closest_pairs_with_other_tree(&fixes, 1);
+    /// assert_eq!(*closest[0].1, Point2::new(3.0, 0.5));
+    /// # }
+    /// ```
+    pub fn k_closest_pairs_with_other_tree<'a, U, B>(&'a self, other: &'a RTree<U, B>, k: usize)
+                                                     -> Vec<(&'a T, &'a U)>
+        where U: HasPosition<Point=T::Point>,
+              B: Aggregate<U> {
+        self.k_closest_pairs_with_other_tree_by(other, k, |t, u| t.distance2(&u.position()))
+    }
+
+    /// Returns the `k` closest pairs of objects from this and another tree,
+    /// measured by a custom distance function.
+    ///
+    /// `distance2` returns the squared distance of two objects. It must never be
+    /// smaller than the squared distance of the objects' bounding rectangles.
+    pub fn k_closest_pairs_with_other_tree_by<'a, U, B, F>(&'a self, other: &'a RTree<U, B>,
+                                                            k: usize, mut distance2: F)
+                                                            -> Vec<(&'a T, &'a U)>
+        where U: SpatialObject<Point=T::Point>,
+              B: Aggregate<U>,
+              F: FnMut(&T, &U) -> Scalar<T> {
+        if self.size == 0 || other.size == 0 {
+            return Vec::new();
+        }
+        let initial = MinHeapEntry {
+            distance2: self.root.mbr().min_dist2_rect(&other.root.mbr()),
+            entry: NodePair {
+                left: JoinNode::Directory(&self.root),
+                right: JoinNode::Directory(&other.root),
+                same_node: false,
+            },
+        };
+        // Nodes of distinct trees are never the same
+        k_closest_pairs(initial, k, &mut distance2, &mut |_, _, _| {})
+    }
+
+    /// Returns all pairs of distinct objects of this tree that are within
+    /// a given distance.
+    ///
+    /// Every pair is contained only once. This self join can be used to find
+    /// near duplicates within a single tree.
+    pub fn pairs_within_distance(&self, max_distance2: Scalar<T>) -> Vec<(&T, &T)>
+        where T: HasPosition<Point=<T as SpatialObject>::Point> {
+        self.pairs_within_distance_by(max_distance2, |t, u| t.distance2(&u.position()))
+    }
+
+    /// Returns all pairs of distinct objects of this tree that are within
+    /// a given distance, measured by a custom distance function.
+    ///
+    /// `distance2` must be symmetric and must never be smaller than the squared
+    /// distance of the objects' bounding rectangles.
+    pub fn pairs_within_distance_by<F>(&self, max_distance2: Scalar<T>, mut distance2: F)
+                                       -> Vec<(&T, &T)>
+        where F: FnMut(&T, &T) -> Scalar<T> {
+        let mut result = Vec::new();
+        let max = max_distance2;
+        self_join_nodes(&self.root.children, &|l, r| l.min_dist2_rect(r) <= max,
+                        &mut |t, u| distance2(t, u) <= max, &mut result);
+        result
+    }
+
+    /// Returns the `k` closest pairs of distinct objects of this tree.
+    ///
+    /// Every pair is contained only once, the pairs are sorted by their distance.
+    pub fn k_closest_pairs(&self, k: usize) -> Vec<(&T, &T)>
+        where T: HasPosition<Point=<T as SpatialObject>::Point> {
+        self.k_closest_pairs_by(k, |t, u| t.distance2(&u.position()))
+    }
+
+    /// Returns the `k` closest pairs of distinct objects of this tree, measured
+    /// by a custom distance function.
+    ///
+    /// `distance2` must be symmetric and must never be smaller than the squared
+    /// distance of the objects' bounding rectangles.
+    pub fn k_closest_pairs_by<F>(&self, k: usize, mut distance2: F) -> Vec<(&T, &T)>
+        where F: FnMut(&T, &T) -> Scalar<T> {
+        if self.size == 0 {
+            return Vec::new();
+        }
+        let initial = MinHeapEntry {
+            distance2: zero(),
+            entry: NodePair {
+                left: JoinNode::Directory(&self.root),
+                right: JoinNode::Directory(&self.root),
+                same_node: true,
+            },
+        };
+        k_closest_pairs(initial, k, &mut distance2, &mut |data, heap, distance2| {
+            // Pairs each child with itself and with all following children,
+            // every unordered pair of children is pushed once
+            for (index, child) in data.children.iter().enumerate() {
+                if let RTreeNode::DirectoryNode(ref child_data) = *child {
+                    heap.push(MinHeapEntry {
+                        distance2: zero(),
+                        entry: NodePair {
+                            left: JoinNode::Directory(child_data),
+                            right: JoinNode::Directory(child_data),
+                            same_node: true,
+                        },
+                    });
+                }
+                for other in &data.children[index + 1 ..] {
+                    push_pair(heap, JoinNode::from_node(child), JoinNode::from_node(other),
+                              distance2);
+                }
+            }
+        })
+    }
+}
+
+// Joins the nodes of a single tree with themselves. Pairs of distinct
+// subtrees are joined with `join_nodes`, thus every unordered pair of distinct
+// elements is found once.
+fn self_join_nodes<'a, T, A, M, F>(nodes: &'a [RTreeNode<T, A>], mbr_filter: &M, refine: &mut F,
+                                   result: &mut Vec<(&'a T, &'a T)>)
+    where T: SpatialObject,
+          A: Aggregate<T>,
+          M: Fn(&BoundingRect<T::Point>, &BoundingRect<T::Point>) -> bool,
+          F: FnMut(&T, &T) -> bool {
+    for (index, node) in nodes.iter().enumerate() {
+        if let RTreeNode::DirectoryNode(ref data) = *node {
+            self_join_nodes(&data.children, mbr_filter, refine, result);
+        }
+        join_nodes(&nodes[index .. index + 1], &nodes[index + 1 ..], mbr_filter, refine, result);
+    }
+}
+
+fn join_nodes<'a, T, A, U, B, M, F>(left: &'a [RTreeNode<T, A>], right: &'a [RTreeNode<U, B>],
+                                    mbr_filter: &M, refine: &mut F,
+                                    result: &mut Vec<(&'a T, &'a U)>)
+    where T: SpatialObject,
+          A: Aggregate<T>,
+          U: SpatialObject<Point=T::Point>,
+          B: Aggregate<U>,
+          M: Fn(&BoundingRect<T::Point>, &BoundingRect<T::Point>) -> bool,
+          F: FnMut(&T, &U) -> bool {
+    let right_mbrs: Vec<BoundingRect<T::Point>> = right.iter().map(|n| n.mbr()).collect();
+    for (l_index, l) in left.iter().enumerate() {
+        let l_mbr = l.mbr();
+        for (r_index, (r, r_mbr)) in right.iter().zip(right_mbrs.iter()).enumerate() {
+            if !mbr_filter(&l_mbr, r_mbr) {
+                continue;
+            }
+            // Descend only on the directory side if the other node is a leaf,
+            // the leaf is kept as a single element slice
+            match *l {
+                RTreeNode::Leaf(ref t) => match *r {
+                    RTreeNode::Leaf(ref u) => {
+                        if refine(t, u) {
+                            result.push((t, u));
+                        }
+                    },
+                    RTreeNode::DirectoryNode(ref r_data) =>
+                        join_nodes(&left[l_index .. l_index + 1], &r_data.children,
+                                   mbr_filter, refine, result),
+                },
+                RTreeNode::DirectoryNode(ref l_data) => match *r {
+                    RTreeNode::Leaf(_) =>
+                        join_nodes(&l_data.children, &right[r_index .. r_index + 1],
+                                   mbr_filter, refine, result),
+                    RTreeNode::DirectoryNode(ref r_data) =>
+                        join_nodes(&l_data.children, &r_data.children,
+                                   mbr_filter, refine, result),
+                },
+            }
+        }
+    }
+}
+
+enum JoinNode<'a, T, A>
+    where T: SpatialObject + 'a,
+          A: Aggregate<T> + 'a {
+    Leaf(&'a T),
+    Directory(&'a DirectoryNodeData<T, A>),
+}
+
+// Derive would require T: Clone
+impl <'a, T, A> Clone for JoinNode<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl <'a, T, A> Copy for JoinNode<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> { }
+
+impl <'a, T, A> JoinNode<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn from_node(node: &'a RTreeNode<T, A>) -> JoinNode<'a, T, A> {
+        match *node {
+            RTreeNode::Leaf(ref t) => JoinNode::Leaf(t),
+            RTreeNode::DirectoryNode(ref data) => JoinNode::Directory(data),
+        }
+    }
+
+    fn mbr(&self) -> BoundingRect<T::Point> {
+        match *self {
+            JoinNode::Leaf(t) => t.mbr(),
+            JoinNode::Directory(data) => data.mbr(),
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match *self {
+            JoinNode::Leaf(_) => 0,
+            JoinNode::Directory(data) => data.depth,
+        }
+    }
+}
+
+struct NodePair<'a, T, A, U, B>
+    where T: SpatialObject + 'a,
+          A: Aggregate<T> + 'a,
+          U: SpatialObject<Point=T::Point> + 'a,
+          B: Aggregate<U> + 'a {
+    left: JoinNode<'a, T, A>,
+    right: JoinNode<'a, U, B>,
+    // True if both sides refer to the same directory node of a self join
+    same_node: bool,
+}
+
+fn k_closest_pairs<'a, T, A, U, B, F, S>(initial: MinHeapEntry<Scalar<T>, NodePair<'a, T, A, U, B>>,
+                                         k: usize, distance2: &mut F, expand_same_node: &mut S)
+                                         -> Vec<(&'a T, &'a U)>
+    where T: SpatialObject,
+          A: Aggregate<T>,
+          U: SpatialObject<Point=T::Point>,
+          B: Aggregate<U>,
+          F: FnMut(&T, &U) -> Scalar<T>,
+          S: FnMut(&'a DirectoryNodeData<T, A>, &mut PairHeap<'a, T, A, U, B>, &mut F) {
+    let mut result = Vec::new();
+    let mut heap = BinaryHeap::new();
+    heap.push(initial);
+    while result.len() < k {
+        let entry = match heap.pop() {
+            Some(MinHeapEntry { entry, .. }) => entry,
+            None => break,
+        };
+        // Leaf pairs are pushed with their exact distance. Once such a pair is
+        // popped, no other pair can be closer.
+        if let (&JoinNode::Leaf(t), &JoinNode::Leaf(u)) = (&entry.left, &entry.right) {
+            result.push((t, u));
+            continue;
+        }
+        if entry.same_node {
+            if let JoinNode::Directory(data) = entry.left {
+                expand_same_node(data, &mut heap, distance2);
+            }
+            continue;
+        }
+        // Expand the deeper node, the other node is kept
+        if entry.left.depth() >= entry.right.depth() {
+            if let JoinNode::Directory(data) = entry.left {
+                for child in data.children.iter() {
+                    push_pair(&mut heap, JoinNode::from_node(child),
+                              entry.right, distance2);
+                }
+            }
+        } else if let JoinNode::Directory(data) = entry.right {
+            for child in data.children.iter() {
+                push_pair(&mut heap, entry.left,
+                          JoinNode::from_node(child), distance2);
+            }
+        }
+    }
+    result
+}
+
+fn push_pair<'a, T, A, U, B, F>(heap: &mut PairHeap<'a, T, A, U, B>,
+                                left: JoinNode<'a, T, A>, right: JoinNode<'a, U, B>,
+                                distance2: &mut F)
+    where T: SpatialObject,
+          A: Aggregate<T>,
+          U: SpatialObject<Point=T::Point>,
+          B: Aggregate<U>,
+          F: FnMut(&T, &U) -> Scalar<T> {
+    let distance2 = if let (&JoinNode::Leaf(t), &JoinNode::Leaf(u)) = (&left, &right) {
+        distance2(t, u)
+    } else {
+        left.mbr().min_dist2_rect(&right.mbr())
+    };
+    heap.push(MinHeapEntry {
+        distance2,
+        entry: NodePair {
+            left,
+            right,
+            same_node: false,
+        },
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{RTree, Count};
+    use primitives::{SimpleCircle, SimpleEdge};
+    use traits::SpatialObject;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_intersection_candidates_with_other_tree() {
+        let centers = random_points_with_seed::<f64>(300, [3, 1, 4, 1]);
+        let points = random_points_with_seed::<f64>(500, [5, 9, 2, 6]);
+        let mut circles = RTree::new();
+        for center in &centers {
+            circles.insert(SimpleCircle::new(*center, 0.05));
+        }
+        let mut point_tree = RTree::new();
+        for point in &points {
+            point_tree.insert(*point);
+        }
+        let candidates = circles.intersection_candidates_with_other_tree(&point_tree);
+        let mut expected = 0;
+        for center in &centers {
+            let circle = SimpleCircle::new(*center, 0.05);
+            expected += points.iter().filter(|p| circle.mbr().contains_point(p)).count();
+        }
+        assert_eq!(candidates.len(), expected);
+        for &(circle, point) in &candidates {
+            assert!(circle.mbr().contains_point(point));
+        }
+
+        let refined = circles.intersecting_pairs_with_other_tree(
+            &point_tree, |c, p| c.contains(p));
+        let mut expected = 0;
+        for center in &centers {
+            let circle = SimpleCircle::new(*center, 0.05);
+            expected += points.iter().filter(|p| circle.contains(p)).count();
+        }
+        assert_eq!(refined.len(), expected);
+        assert!(refined.len() < candidates.len());
+    }
+
+    #[test]
+    fn test_join_with_empty_tree() {
+        let (tree, _) = create_random_tree::<f64>(100, [1, 2, 3, 4]);
+        let empty: RTree<Point2<f64>> = RTree::new();
+        assert!(tree.intersection_candidates_with_other_tree(&empty).is_empty());
+        assert!(empty.intersection_candidates_with_other_tree(&tree).is_empty());
+    }
+
+    fn sorted(mut distances: Vec<f64>) -> Vec<f64> {
+        distances.sort_by(|l, r| l.partial_cmp(r).unwrap());
+        distances
+    }
+
+    #[test]
+    fn test_pairs_within_distance_with_other_tree() {
+        let (left, left_points) = create_random_tree::<f64>(300, [2, 7, 1, 8]);
+        let (right, right_points) = create_random_tree::<f64>(200, [2, 8, 1, 8]);
+        let max_distance2 = 0.01;
+        let pairs = left.pairs_within_distance_with_other_tree(&right, max_distance2);
+        let mut expected = Vec::new();
+        for l in &left_points {
+            for r in &right_points {
+                if l.distance2(r) <= max_distance2 {
+                    expected.push(l.distance2(r));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        let found = pairs.iter().map(|&(l, r)| l.distance2(r)).collect();
+        assert_eq!(sorted(found), sorted(expected));
+    }
+
+    #[test]
+    fn test_k_closest_pairs_with_other_tree() {
+        let points = random_points_with_seed::<f64>(400, [1, 4, 1, 4]);
+        let ends = random_points_with_seed::<f64>(200, [2, 1, 3, 5]);
+        let mut point_tree = RTree::new();
+        for point in &points {
+            point_tree.insert(*point);
+        }
+        let mut edges = Vec::new();
+        let mut edge_tree = RTree::new();
+        for pair in ends.chunks(2) {
+            let edge = SimpleEdge::new(pair[0], pair[0] + (pair[1] - pair[0]) * 0.1);
+            edges.push(edge.clone());
+            edge_tree.insert(edge);
+        }
+        let k = 50;
+        let pairs = edge_tree.k_closest_pairs_with_other_tree(&point_tree, k);
+        let found: Vec<_> = pairs.iter().map(|&(e, p)| e.distance2(p)).collect();
+        assert_eq!(found.len(), k);
+        assert_eq!(found.clone(), sorted(found.clone()));
+        let mut expected = Vec::new();
+        for edge in &edges {
+            for point in &points {
+                expected.push(edge.distance2(point));
+            }
+        }
+        let expected = sorted(expected);
+        assert_eq!(found, &expected[.. k]);
+        // Requesting more pairs than available returns all pairs
+        let all = edge_tree.k_closest_pairs_with_other_tree(
+            &point_tree, edges.len() * points.len() + 1);
+        assert_eq!(all.len(), expected.len());
+    }
+
+    #[test]
+    fn test_self_join() {
+        let (tree, points) = create_random_tree::<f64>(300, [3, 5, 8, 9]);
+        let mut expected = Vec::new();
+        for (index, l) in points.iter().enumerate() {
+            for r in &points[index + 1 ..] {
+                expected.push(l.distance2(r));
+            }
+        }
+        let expected = sorted(expected);
+
+        let max_distance2 = 0.005;
+        let pairs = tree.pairs_within_distance(max_distance2);
+        let found = pairs.iter().map(|&(l, r)| l.distance2(r)).collect();
+        let within: Vec<_> = expected.iter().cloned()
+            .filter(|d| *d <= max_distance2).collect();
+        assert!(!within.is_empty());
+        assert_eq!(sorted(found), within);
+
+        let k = 40;
+        let closest = tree.k_closest_pairs(k);
+        let found: Vec<_> = closest.iter().map(|&(l, r)| l.distance2(r)).collect();
+        assert_eq!(found, &expected[.. k]);
+        assert!(closest.iter().all(|&(l, r)| !::std::ptr::eq(l, r)));
+
+        // The result must not depend on the elements' memory addresses
+        let copy = tree.clone();
+        let values = |pairs: Vec<(&Point2<f64>, &Point2<f64>)>| -> Vec<_> {
+            pairs.into_iter().map(|(l, r)| (*l, *r)).collect()
+        };
+        assert_eq!(values(copy.pairs_within_distance(max_distance2)), values(pairs));
+        assert_eq!(values(copy.k_closest_pairs(k)), values(closest));
+    }
+
+    #[test]
+    fn test_self_join_with_duplicates() {
+        let mut tree = RTree::new();
+        for _ in 0 .. 10 {
+            tree.insert(Point2::new(1.0, 2.0));
+        }
+        tree.insert(Point2::new(5.0, 5.0));
+        assert_eq!(tree.pairs_within_distance(0.0).len(), 45);
+        let closest = tree.k_closest_pairs(60);
+        assert_eq!(closest.len(), 55);
+        assert!(closest[.. 45].iter().all(|&(l, r)| l == r));
+        assert!(closest.iter().all(|&(l, r)| !::std::ptr::eq(l, r)));
+    }
+
+    #[test]
+    fn test_joins_with_aggregated_trees() {
+        let (tree, points) = create_random_tree::<f64>(200, [6, 1, 2, 7]);
+        let mut counted: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            counted.insert(*point);
+        }
+        let max_distance2 = 0.01;
+        let distances = |pairs: Vec<(&Point2<f64>, &Point2<f64>)>| -> Vec<_> {
+            sorted(pairs.into_iter().map(|(l, r)| l.distance2(r)).collect())
+        };
+        let expected = distances(tree.pairs_within_distance_with_other_tree(&tree, max_distance2));
+        assert_eq!(distances(counted.pairs_within_distance_with_other_tree(&tree, max_distance2)),
+                   expected);
+        assert_eq!(distances(tree.pairs_within_distance_with_other_tree(&counted, max_distance2)),
+                   expected);
+        assert_eq!(counted.intersection_candidates_with_other_tree(&counted).len(),
+                   tree.intersection_candidates_with_other_tree(&tree).len());
+        assert_eq!(distances(counted.k_closest_pairs_with_other_tree(&tree, 30)),
+                   distances(tree.k_closest_pairs_with_other_tree(&tree, 30)));
+        assert_eq!(distances(counted.pairs_within_distance(max_distance2)),
+                   distances(tree.pairs_within_distance(max_distance2)));
+        assert_eq!(distances(counted.k_closest_pairs(30)), distances(tree.k_closest_pairs(30)));
+    }
+
+    #[test]
+    fn test_k_closest_pairs_with_empty_tree() {
+        let (tree, _) = create_random_tree::<f64>(10, [1, 2, 3, 4]);
+        let empty: RTree<Point2<f64>> = RTree::new();
+        assert!(tree.k_closest_pairs_with_other_tree(&empty, 5).is_empty());
+        assert!(empty.k_closest_pairs(5).is_empty());
+        assert!(tree.k_closest_pairs(0).is_empty());
+    }
+}
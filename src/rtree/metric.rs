@@ -0,0 +1,582 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Nearest neighbor queries with custom distance metrics and filters.
+
+use num::{cast, zero, Signed};
+use smallvec::SmallVec;
+use misc::{max_inline, min_inline};
+use traits::{SpatialObject, HasPosition, SpadeFloat};
+use point_traits::{PointN, PointNExtensions, TwoDimensional};
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+type Scalar<T> = <<T as SpatialObject>::Point as PointN>::Scalar;
+type SortedChildren<'a, T, A> = SmallVec<[(&'a RTreeNode<T, A>, Scalar<T>); 8]>;
+
+// The n nearest neighbors found so far, sorted by their distance
+struct NearestN<'a, T>
+    where T: SpatialObject + 'a {
+    n: usize,
+    neighbors: Vec<(&'a T, Scalar<T>)>,
+}
+
+/// A distance metric used by nearest neighbor queries.
+///
+/// `distance` measures the distance between an object and a query point,
+/// `min_dist` must return a lower bound of the distance between the query point
+/// and any object contained in a rectangle. The tighter this bound is, the fewer
+/// nodes need to be visited.
+/// Metrics are used by `RTree::nearest_neighbor_with_metric`,
+/// `RTree::nearest_neighbors_with_metric` and `RTree::nearest_n_neighbors_with_metric`.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::{RTree, Manhattan};
+/// use cgmath::Point2;
+///
+/// # fn main() {
+/// let mut tree = RTree::new();
+/// tree.insert(Point2::new(3, 0));
+/// tree.insert(Point2::new(2, 2));
+/// // (2, 2) is closer in euclidean space, (3, 0) has the smaller manhattan distance
+/// assert_eq!(tree.nearest_neighbor(&Point2::new(0, 0)), Some(&Point2::new(2, 2)));
+/// assert_eq!(tree.nearest_neighbor_with_metric(&Point2::new(0, 0), &Manhattan),
+///            Some(&Point2::new(3, 0)));
+/// # }
+/// ```
+pub trait Metric<T>
+    where T: SpatialObject {
+    /// Returns the distance between an object and a point.
+    fn distance(&self, object: &T, point: &T::Point) -> <T::Point as PointN>::Scalar;
+
+    /// Returns a lower bound of the distance between a point and all objects
+    /// contained in a rectangle.
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point)
+                -> <T::Point as PointN>::Scalar;
+}
+
+/// The squared euclidean distance used by `RTree::nearest_neighbor`.
+///
+/// Distances are calculated by `SpatialObject::distance2`.
+#[derive(Clone, Copy, Debug)]
+pub struct SquaredEuclidean;
+
+/// The manhattan (L1) distance, the sum of all absolute coordinate differences.
+#[derive(Clone, Copy, Debug)]
+pub struct Manhattan;
+
+/// The chebyshev (L∞) distance, the maximum of all absolute coordinate differences.
+#[derive(Clone, Copy, Debug)]
+pub struct Chebyshev;
+
+/// A squared euclidean distance with a weight for each axis.
+///
+/// The distance between two points is the sum of each squared coordinate
+/// difference multiplied by its axis' weight. All weights must be non negative.
+#[derive(Clone, Debug)]
+pub struct WeightedSquaredEuclidean<V>
+    where V: PointN {
+    /// The weight of each axis.
+    pub weights: V,
+}
+
+/// The great circle distance between points on a sphere.
+///
+/// Points are given in degrees, their first coordinate is the longitude and
+/// their second coordinate is the latitude. Distances are calculated with the
+/// haversine formula and are measured in units of the sphere's radius.
+#[derive(Clone, Copy, Debug)]
+pub struct GreatCircle<S>
+    where S: SpadeFloat {
+    /// The sphere's radius.
+    pub radius: S,
+}
+
+impl <V> WeightedSquaredEuclidean<V>
+    where V: PointN {
+    /// Creates a new metric with the given axis weights.
+    pub fn new(weights: V) -> WeightedSquaredEuclidean<V> {
+        WeightedSquaredEuclidean { weights }
+    }
+}
+
+impl <S> GreatCircle<S>
+    where S: SpadeFloat {
+    /// Creates a great circle metric on a sphere with the given radius.
+    pub fn new(radius: S) -> GreatCircle<S> {
+        GreatCircle { radius }
+    }
+
+    /// Creates a great circle metric on a sphere with the earth's mean radius.
+    ///
+    /// Distances are measured in meters.
+    pub fn earth() -> GreatCircle<S> {
+        GreatCircle::new(cast(6_371_008.8).unwrap())
+    }
+
+    fn central_angle(lon1: S, lat1: S, lon2: S, lat2: S) -> S {
+        let sin_lat = ((lat2 - lat1).to_radians() / cast(2.0).unwrap()).sin();
+        let sin_lon = ((lon2 - lon1).to_radians() / cast(2.0).unwrap()).sin();
+        let haversine = sin_lat * sin_lat
+            + lat1.to_radians().cos() * lat2.to_radians().cos() * sin_lon * sin_lon;
+        let two: S = cast(2.0).unwrap();
+        two * min_inline(haversine, S::one()).sqrt().asin()
+    }
+
+    // Returns the difference between two longitudes in degrees, ranging from 0 to 180
+    fn longitude_difference(lon1: S, lon2: S) -> S {
+        let full: S = cast(360.0).unwrap();
+        let difference = ((lon1 - lon2) % full + full) % full;
+        if difference > cast(180.0).unwrap() {
+            full - difference
+        } else {
+            difference
+        }
+    }
+
+    fn point_distance(&self, lon1: S, lat1: S, lon2: S, lat2: S) -> S {
+        self.radius * GreatCircle::central_angle(lon1, lat1, lon2, lat2)
+    }
+
+    fn rect_distance(&self, lower: (S, S), upper: (S, S), lon: S, lat: S) -> S {
+        let full: S = cast(360.0).unwrap();
+        let width = upper.0 - lower.0;
+        if width >= full || ((lon - lower.0) % full + full) % full <= width {
+            // The closest point lies on the query point's meridian
+            return if lat < lower.1 {
+                self.radius * (lower.1 - lat).to_radians()
+            } else if lat > upper.1 {
+                self.radius * (lat - upper.1).to_radians()
+            } else {
+                zero()
+            };
+        }
+        let to_lower = GreatCircle::longitude_difference(lon, lower.0);
+        let to_upper = GreatCircle::longitude_difference(lon, upper.0);
+        let (edge, difference) = if to_lower < to_upper {
+            (lower.0, to_lower)
+        } else {
+            (upper.0, to_upper)
+        };
+        // The closest point on the edge meridian's great circle
+        let difference = difference.to_radians();
+        let lat_radians = lat.to_radians();
+        let closest = lat_radians.sin().atan2(lat_radians.cos() * difference.cos()).to_degrees();
+        if lower.1 <= closest && closest <= upper.1 {
+            self.radius * (lat_radians.cos() * difference.sin()).asin()
+        } else {
+            // The distance is monotonous between the closest point and both corners
+            min_inline(self.point_distance(lon, lat, edge, lower.1),
+                       self.point_distance(lon, lat, edge, upper.1))
+        }
+    }
+}
+
+fn manhattan_length<V: PointN>(delta: &V) -> V::Scalar {
+    let mut result = zero();
+    for i in 0 .. V::dimensions() {
+        result = result + delta.nth(i).abs();
+    }
+    result
+}
+
+fn chebyshev_length<V: PointN>(delta: &V) -> V::Scalar {
+    let mut result = zero();
+    for i in 0 .. V::dimensions() {
+        result = max_inline(result, delta.nth(i).abs());
+    }
+    result
+}
+
+fn weighted_length2<V: PointN>(delta: &V, weights: &V) -> V::Scalar {
+    let mut result = zero();
+    for i in 0 .. V::dimensions() {
+        let d = delta.nth(i).clone();
+        result = result + weights.nth(i).clone() * d.clone() * d;
+    }
+    result
+}
+
+impl <T> Metric<T> for SquaredEuclidean
+    where T: SpatialObject {
+    fn distance(&self, object: &T, point: &T::Point) -> Scalar<T> {
+        object.distance2(point)
+    }
+
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point) -> Scalar<T> {
+        rect.min_dist2(point)
+    }
+}
+
+impl <T> Metric<T> for Manhattan
+    where T: HasPosition {
+    fn distance(&self, object: &T, point: &T::Point) -> Scalar<T> {
+        manhattan_length(&object.position().sub(point))
+    }
+
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point) -> Scalar<T> {
+        manhattan_length(&rect.min_point(point).sub(point))
+    }
+}
+
+impl <T> Metric<T> for Chebyshev
+    where T: HasPosition {
+    fn distance(&self, object: &T, point: &T::Point) -> Scalar<T> {
+        chebyshev_length(&object.position().sub(point))
+    }
+
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point) -> Scalar<T> {
+        chebyshev_length(&rect.min_point(point).sub(point))
+    }
+}
+
+impl <T> Metric<T> for WeightedSquaredEuclidean<T::Point>
+    where T: HasPosition {
+    fn distance(&self, object: &T, point: &T::Point) -> Scalar<T> {
+        weighted_length2(&object.position().sub(point), &self.weights)
+    }
+
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point) -> Scalar<T> {
+        weighted_length2(&rect.min_point(point).sub(point), &self.weights)
+    }
+}
+
+impl <T, S> Metric<T> for GreatCircle<S>
+    where T: HasPosition,
+          T::Point: PointN<Scalar=S> + TwoDimensional,
+          S: SpadeFloat {
+    fn distance(&self, object: &T, point: &T::Point) -> S {
+        let position = object.position();
+        self.point_distance(*position.nth(0), *position.nth(1), *point.nth(0), *point.nth(1))
+    }
+
+    fn min_dist(&self, rect: &BoundingRect<T::Point>, point: &T::Point) -> S {
+        let (lower, upper) = (rect.lower(), rect.upper());
+        self.rect_distance((*lower.nth(0), *lower.nth(1)), (*upper.nth(0), *upper.nth(1)),
+                           *point.nth(0), *point.nth(1))
+    }
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn sorted_by_metric<'b, M>(&'b self, point: &T::Point, metric: &M) -> SortedChildren<'b, T, A>
+        where M: Metric<T> {
+        let mut sorted: SmallVec<[_; 8]> = self.children.iter().map(|child| {
+            let min_dist = match *child {
+                RTreeNode::DirectoryNode(ref data) => metric.min_dist(&data.mbr(), point),
+                RTreeNode::Leaf(ref t) => metric.distance(t, point),
+            };
+            (child, min_dist)
+        }).collect();
+        sorted.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+        sorted
+    }
+
+    // Only objects within max_distance that pass the filter are considered
+    fn nearest_neighbor_with_metric<'b, M, F>(&'b self, point: &T::Point, metric: &M,
+                                              max_distance: Option<&Scalar<T>>, filter: &mut F,
+                                              nearest: &mut Option<(&'b T, Scalar<T>)>)
+        where M: Metric<T>,
+              F: FnMut(&T) -> bool {
+        for (child, min_dist) in self.sorted_by_metric(point, metric) {
+            if nearest.as_ref().map(|n| min_dist >= n.1).unwrap_or(false)
+                || max_distance.map(|d| min_dist > *d).unwrap_or(false) {
+                // All remaining children are farther away
+                break;
+            }
+            match *child {
+                RTreeNode::DirectoryNode(ref data) => data.nearest_neighbor_with_metric(
+                    point, metric, max_distance, filter, nearest),
+                RTreeNode::Leaf(ref t) => {
+                    if filter(t) {
+                        *nearest = Some((t, min_dist));
+                    }
+                },
+            }
+        }
+    }
+
+    fn nearest_neighbors_with_metric<'b, M>(&'b self, point: &T::Point, metric: &M,
+                                            nearest_distance: &mut Option<Scalar<T>>,
+                                            result: &mut Vec<&'b T>)
+        where M: Metric<T> {
+        for (child, min_dist) in self.sorted_by_metric(point, metric) {
+            if nearest_distance.as_ref().map(|d| min_dist > *d).unwrap_or(false) {
+                break;
+            }
+            match *child {
+                RTreeNode::DirectoryNode(ref data) =>
+                    data.nearest_neighbors_with_metric(point, metric, nearest_distance, result),
+                RTreeNode::Leaf(ref t) => {
+                    if nearest_distance.as_ref().map(|d| min_dist < *d).unwrap_or(true) {
+                        // We've found a new minimum element, remove all other neighbors found so far
+                        result.clear();
+                        *nearest_distance = Some(min_dist);
+                    }
+                    result.push(t);
+                },
+            }
+        }
+    }
+
+    fn nearest_n_neighbors_with_metric<'b, M, F>(&'b self, point: &T::Point, metric: &M,
+                                                 max_distance: Option<&Scalar<T>>, filter: &mut F,
+                                                 result: &mut NearestN<'b, T>)
+        where M: Metric<T>,
+              F: FnMut(&T) -> bool {
+        for (child, min_dist) in self.sorted_by_metric(point, metric) {
+            if (result.neighbors.len() == result.n
+                && min_dist >= result.neighbors.last().unwrap().1)
+                || max_distance.map(|d| min_dist > *d).unwrap_or(false) {
+                break;
+            }
+            match *child {
+                RTreeNode::DirectoryNode(ref data) => data.nearest_n_neighbors_with_metric(
+                    point, metric, max_distance, filter, result),
+                RTreeNode::Leaf(ref t) => {
+                    if !filter(t) {
+                        continue;
+                    }
+                    if result.neighbors.len() == result.n {
+                        result.neighbors.pop();
+                    }
+                    let index = match result.neighbors.binary_search_by(
+                        |e| e.1.partial_cmp(&min_dist).unwrap()) {
+                        Ok(index) => index,
+                        Err(index) => index,
+                    };
+                    result.neighbors.insert(index, (t, min_dist));
+                },
+            }
+        }
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Returns the nearest neighbor according to a custom metric.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn nearest_neighbor_with_metric<M>(&self, query_point: &T::Point, metric: &M)
+                                           -> Option<&T>
+        where M: Metric<T> {
+        let mut nearest = None;
+        if self.size > 0 {
+            self.root.nearest_neighbor_with_metric(query_point, metric, None, &mut |_| true,
+                                                   &mut nearest);
+        }
+        nearest.map(|n| n.0)
+    }
+
+    /// Returns the nearest neighbors of a given point according to a custom metric.
+    ///
+    /// All returned values will have the exact same distance from the given query point.
+    /// Returns an empty `Vec` if the tree is empty.
+    pub fn nearest_neighbors_with_metric<M>(&self, query_point: &T::Point, metric: &M)
+                                            -> Vec<&T>
+        where M: Metric<T> {
+        let mut result = Vec::new();
+        if self.size > 0 {
+            self.root.nearest_neighbors_with_metric(query_point, metric, &mut None, &mut result);
+        }
+        result
+    }
+
+    /// Returns the nearest n neighbors according to a custom metric.
+    ///
+    /// The neighbors are sorted by their distance from the query point.
+    pub fn nearest_n_neighbors_with_metric<M>(&self, query_point: &T::Point, n: usize,
+                                              metric: &M) -> Vec<&T>
+        where M: Metric<T> {
+        self.filtered_nearest_n_neighbors(query_point, n, metric, None, |_| true)
+    }
+
+    /// Returns the nearest neighbor that lies within a maximum distance and
+    /// satisfies a predicate.
+    ///
+    /// Only objects whose squared distance to the query point is at most
+    /// `max_distance2` and for which `predicate` returns `true` are considered.
+    /// Nodes beyond `max_distance2` are pruned and rejected objects are skipped
+    /// during a single traversal.
+    /// Returns `None` if no object qualifies.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// tree.insert(Point2::new(1, 0));
+    /// tree.insert(Point2::new(0, 2));
+    /// tree.insert(Point2::new(4, 4));
+    /// // Looks for points with an even x coordinate
+    /// let query = Point2::new(0, 0);
+    /// assert_eq!(tree.nearest_neighbor_filtered(&query, &16, |p| p.x % 2 == 0),
+    ///            Some(&Point2::new(0, 2)));
+    /// assert_eq!(tree.nearest_neighbor_filtered(&query, &3, |p| p.x % 2 == 0), None);
+    /// # }
+    /// ```
+    pub fn nearest_neighbor_filtered<F>(&self, query_point: &T::Point,
+                                        max_distance2: &Scalar<T>, mut predicate: F)
+                                        -> Option<&T>
+        where F: FnMut(&T) -> bool {
+        let mut nearest = None;
+        if self.size > 0 {
+            self.root.nearest_neighbor_with_metric(query_point, &SquaredEuclidean,
+                                                   Some(max_distance2), &mut predicate,
+                                                   &mut nearest);
+        }
+        nearest.map(|n| n.0)
+    }
+
+    /// Returns the nearest n neighbors that lie within a maximum distance and
+    /// satisfy a predicate.
+    ///
+    /// The neighbors are sorted by their distance from the query point, less than
+    /// `n` neighbors are returned if not enough objects qualify. See
+    /// `RTree::nearest_neighbor_filtered`.
+    pub fn nearest_n_neighbors_filtered<F>(&self, query_point: &T::Point, n: usize,
+                                           max_distance2: &Scalar<T>, predicate: F)
+                                           -> Vec<&T>
+        where F: FnMut(&T) -> bool {
+        self.filtered_nearest_n_neighbors(query_point, n, &SquaredEuclidean,
+                                          Some(max_distance2), predicate)
+    }
+
+    fn filtered_nearest_n_neighbors<M, F>(&self, query_point: &T::Point, n: usize, metric: &M,
+                                          max_distance: Option<&Scalar<T>>, mut filter: F)
+                                          -> Vec<&T>
+        where M: Metric<T>,
+              F: FnMut(&T) -> bool {
+        let mut result = NearestN { n, neighbors: Vec::with_capacity(n) };
+        if self.size > 0 && n > 0 {
+            self.root.nearest_n_neighbors_with_metric(query_point, metric, max_distance,
+                                                      &mut filter, &mut result);
+        }
+        result.neighbors.into_iter().map(|n| n.0).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Metric, SquaredEuclidean, Manhattan, Chebyshev, WeightedSquaredEuclidean,
+                GreatCircle};
+    use super::super::RTree;
+    use boundingvolume::BoundingRect;
+    use traits::SpatialObject;
+    use cgmath::Point2;
+    use testutils::*;
+
+    fn check_metric<M>(tree: &RTree<Point2<f64>>, points: &[Point2<f64>],
+                       queries: &[Point2<f64>], metric: &M)
+        where M: Metric<Point2<f64>> {
+        for query in queries {
+            let mut expected: Vec<_> = points.iter()
+                .map(|p| (metric.distance(p, query), *p)).collect();
+            expected.sort_by(|l, r| l.0.partial_cmp(&r.0).unwrap());
+            let nearest = tree.nearest_neighbor_with_metric(query, metric).unwrap();
+            assert_eq!(metric.distance(nearest, query), expected[0].0);
+            assert_eq!(tree.nearest_neighbors_with_metric(query, metric), vec![nearest]);
+            let nearest_n = tree.nearest_n_neighbors_with_metric(query, 10, metric);
+            let expected_n: Vec<_> = expected[.. 10].iter().map(|e| &e.1).collect();
+            assert_eq!(nearest_n, expected_n);
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let (tree, points) = create_random_tree::<f64>(1000, [2, 1, 5, 2]);
+        let queries = random_points_with_seed::<f64>(50, [8, 1, 9, 4]);
+        check_metric(&tree, &points, &queries, &SquaredEuclidean);
+        check_metric(&tree, &points, &queries, &Manhattan);
+        check_metric(&tree, &points, &queries, &Chebyshev);
+        check_metric(&tree, &points, &queries,
+                     &WeightedSquaredEuclidean::new(Point2::new(1.0, 10.0)));
+        for query in &queries {
+            assert_eq!(tree.nearest_neighbor_with_metric(query, &SquaredEuclidean),
+                       tree.nearest_neighbor(query));
+        }
+    }
+
+    #[test]
+    fn test_great_circle() {
+        // Longitudes range from -180 to 180, latitudes from -90 to 90
+        let points: Vec<_> = random_points_in_range(180.0, 1000, [3, 1, 4, 1]).into_iter()
+            .map(|p| Point2::new(p.x, p.y / 2.0)).collect();
+        let mut tree = RTree::new();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let queries: Vec<_> = random_points_in_range(200.0, 50, [2, 7, 1, 8]).into_iter()
+            .map(|p| Point2::new(p.x, p.y * 0.45)).collect();
+        check_metric(&tree, &points, &queries, &GreatCircle::new(1.0));
+
+        let metric = GreatCircle::earth();
+        let berlin = Point2::new(13.405, 52.52);
+        let paris = Point2::new(2.3522, 48.8566);
+        let distance: f64 = Metric::<Point2<f64>>::distance(&metric, &berlin, &paris);
+        assert!((distance - 877_500.0).abs() < 1000.0);
+    }
+
+    #[test]
+    fn test_great_circle_lower_bound() {
+        let metric = GreatCircle::new(1.0);
+        let corners = random_points_in_range(180.0, 200, [1, 2, 3, 4]);
+        let queries = random_points_in_range(180.0, 200, [5, 6, 7, 8]);
+        for (pair, query) in corners.chunks(2).zip(queries.iter()) {
+            let query = Point2::new(query.x, query.y / 2.0);
+            let rect = BoundingRect::from_corners(&Point2::new(pair[0].x, pair[0].y / 2.0),
+                                                  &Point2::new(pair[1].x, pair[1].y / 2.0));
+            let min_dist = Metric::<Point2<f64>>::min_dist(&metric, &rect, &query);
+            let (lower, upper) = (rect.lower(), rect.upper());
+            for i in 0 .. 20 {
+                for j in 0 .. 20 {
+                    let lon = lower.x + (upper.x - lower.x) * i as f64 / 19.0;
+                    let lat = lower.y + (upper.y - lower.y) * j as f64 / 19.0;
+                    let distance = metric.distance(&Point2::new(lon, lat), &query);
+                    assert!(min_dist <= distance + 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest_neighbor_filtered() {
+        let (tree, points) = create_random_tree::<f64>(1000, [6, 2, 8, 3]);
+        let queries = random_points_with_seed::<f64>(50, [4, 4, 3, 3]);
+        let predicate = |p: &Point2<f64>| p.x * p.y > 0.0;
+        for query in &queries {
+            for &max_distance2 in &[0.001, 0.05, 1.0] {
+                let mut expected: Vec<_> = points.iter()
+                    .filter(|p| predicate(p) && p.distance2(query) <= max_distance2)
+                    .collect();
+                expected.sort_by(|l, r| l.distance2(query).partial_cmp(&r.distance2(query)).unwrap());
+                assert_eq!(tree.nearest_neighbor_filtered(query, &max_distance2, predicate),
+                           expected.first().cloned());
+                expected.truncate(5);
+                assert_eq!(tree.nearest_n_neighbors_filtered(query, 5, &max_distance2, predicate),
+                           expected);
+            }
+        }
+        let mut visited = 0;
+        tree.nearest_neighbor_filtered(&Point2::new(0.0, 0.0), &0.01, |_| {
+            visited += 1;
+            false
+        });
+        assert!(visited < 100);
+    }
+}
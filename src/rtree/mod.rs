@@ -17,72 +17,125 @@ use boundingvolume::BoundingRect;
 use std::iter::Once;
 use smallvec::SmallVec;
 
+mod join;
+mod selection;
+mod drain;
+mod frozen;
+mod packed;
+mod bulk_load;
+mod statistics;
+mod validate;
+mod split;
+mod aggregate;
+mod metric;
+mod ray;
+mod rknn;
+mod tpr;
+#[cfg(feature = "rayon")]
+mod parallel;
+
+pub use self::selection::SelectionFunction;
+pub use self::drain::DrainIterator;
+pub use self::frozen::FrozenRTree;
+pub use self::statistics::{LevelStatistics, RTreeStatistics};
+pub use self::validate::RTreeValidationError;
+pub use self::split::{SplitStrategy, SplitNode, RStar, Quadratic, Linear, Hilbert};
+pub use self::aggregate::{Aggregate, Count};
+pub use self::metric::{Metric, SquaredEuclidean, Manhattan, Chebyshev, WeightedSquaredEuclidean,
+                        GreatCircle};
+pub use self::packed::{PackedRTree, PackedRTreeError, PackedRecord, PackedScalar};
+pub use self::tpr::TprTree;
+use self::selection::SelectInRectangle;
+
 #[doc(hidden)]
-#[derive(Eq, PartialEq, Clone, Debug)]
-pub struct RTreeOptions {
+#[derive(Clone, Debug)]
+pub struct RTreeOptions<V: PointN> {
     max_size: usize,
     min_size: usize,
     reinsertion_count: usize,
+    split_strategy: Arc<dyn SplitStrategy<V>>,
+}
+
+// Split strategies are compared by their debug output
+impl <V: PointN> PartialEq for RTreeOptions<V> {
+    fn eq(&self, other: &RTreeOptions<V>) -> bool {
+        self.max_size == other.max_size &&
+            self.min_size == other.min_size &&
+            self.reinsertion_count == other.reinsertion_count &&
+            format!("{:?}", self.split_strategy) == format!("{:?}", other.split_strategy)
+    }
 }
 
-impl Default for RTreeOptions {
-    fn default() -> RTreeOptions {
+impl <V: PointN> Eq for RTreeOptions<V> { }
+
+impl <V: PointN> Default for RTreeOptions<V> {
+    fn default() -> RTreeOptions<V> {
         RTreeOptions::new()
     }
 }
 
 #[doc(hidden)]
-impl RTreeOptions {
-    pub fn new() -> RTreeOptions {
+impl <V: PointN> RTreeOptions<V> {
+    pub fn new() -> RTreeOptions<V> {
         RTreeOptions {
             max_size: 6,
             min_size: 3,
             reinsertion_count: 2,
+            split_strategy: Arc::new(RStar),
         }
     }
 
-    pub fn set_max_size(mut self, max_size: usize) -> RTreeOptions {
+    pub fn set_max_size(mut self, max_size: usize) -> RTreeOptions<V> {
         assert!(max_size > self.min_size);
         self.max_size = max_size;
         self
     }
 
-    pub fn set_min_size(mut self, min_size: usize) -> RTreeOptions {
+    pub fn set_min_size(mut self, min_size: usize) -> RTreeOptions<V> {
         assert!(self.max_size > min_size);
         self.min_size = min_size;
         self
     }
 
-    pub fn set_reinsertion_count(mut self, reinsertion_count: usize) -> RTreeOptions {
+    pub fn set_reinsertion_count(mut self, reinsertion_count: usize) -> RTreeOptions<V> {
         assert!(0 < reinsertion_count && self.max_size > reinsertion_count);
         self.reinsertion_count = reinsertion_count;
         self
     }
 
-    pub fn build<T: SpatialObject>(self) -> RTree<T> {
+    pub fn set_split_strategy<S>(mut self, split_strategy: S) -> RTreeOptions<V>
+        where S: SplitStrategy<V> + 'static {
+        self.split_strategy = Arc::new(split_strategy);
+        self
+    }
+
+    pub fn build<T: SpatialObject<Point=V>>(self) -> RTree<T> {
         RTree::new_with_options(self)
     }
 }
 
 /// Iterates over all entries in an r-tree.
 /// Returned by `RTree::iter()`
-pub struct RTreeIterator<'a, T> 
-    where T: SpatialObject + 'a {
-    data: &'a DirectoryNodeData<T>,
+pub struct RTreeIterator<'a, T, A = ()> 
+    where T: SpatialObject + 'a,
+          A: Aggregate<T> {
+    data: &'a DirectoryNodeData<T, A>,
     cur_index: usize, 
-    cur_iterator: Option<Box<RTreeNodeIterator<'a, T>>>,
+    cur_iterator: Option<Box<RTreeNodeIterator<'a, T, A>>>,
 }
 
 #[allow(missing_docs)]
-pub enum RTreeNodeIterator<'a, T> 
-    where T: SpatialObject + 'a {
+pub enum RTreeNodeIterator<'a, T, A = ()> 
+    where T: SpatialObject + 'a,
+          A: Aggregate<T> {
     LeafIterator(Once<&'a T>),
-    DirectoryNodeIterator(RTreeIterator<'a, T>),
+    DirectoryNodeIterator(RTreeIterator<'a, T, A>),
 }
 
-impl <'a, T> RTreeIterator<'a, T> 
-    where T: SpatialObject {
-    fn new(data: &'a DirectoryNodeData<T>) -> RTreeIterator<'a, T> {
+impl <'a, T, A> RTreeIterator<'a, T, A> 
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn new(data: &'a DirectoryNodeData<T, A>) -> RTreeIterator<'a, T, A> {
         RTreeIterator {
             data: data,
             cur_index: 0,
@@ -92,8 +145,9 @@ impl <'a, T> RTreeIterator<'a, T>
     }
 }
 
-impl <'a, T> Iterator for RTreeIterator<'a, T>
-    where T: SpatialObject {
+impl <'a, T, A> Iterator for RTreeIterator<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
@@ -125,10 +179,11 @@ impl <'a, T> Iterator for RTreeIterator<'a, T>
     }
 }
 
-impl <'a, T> RTreeNodeIterator<'a, T>
-    where T: SpatialObject {
+impl <'a, T, A> RTreeNodeIterator<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
 
-    fn new(node: &'a RTreeNode<T>) -> RTreeNodeIterator<'a, T> {
+    fn new(node: &'a RTreeNode<T, A>) -> RTreeNodeIterator<'a, T, A> {
         use self::RTreeNodeIterator::{LeafIterator, DirectoryNodeIterator};
         match node {
             &RTreeNode::Leaf(ref b) => LeafIterator(::std::iter::once(b)),
@@ -138,8 +193,9 @@ impl <'a, T> RTreeNodeIterator<'a, T>
     }
 }
 
-impl <'a, T> Iterator for RTreeNodeIterator<'a, T>
-    where T: SpatialObject {
+impl <'a, T, A> Iterator for RTreeNodeIterator<'a, T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
@@ -152,9 +208,10 @@ impl <'a, T> Iterator for RTreeNodeIterator<'a, T>
 }
 
 #[doc(hidden)]
-impl <T> DirectoryNodeData<T>
-    where T: SpatialObject {
-    pub fn children(&self) -> &Vec<RTreeNode<T>> {
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    pub fn children(&self) -> &Vec<RTreeNode<T, A>> {
         &self.children
     }
 
@@ -166,24 +223,26 @@ impl <T> DirectoryNodeData<T>
         self.bounding_box.clone().unwrap()
     }
 
-    fn new(depth: usize, options: Arc<RTreeOptions>) -> DirectoryNodeData<T> {
+    fn new(depth: usize, options: Arc<RTreeOptions<T::Point>>) -> DirectoryNodeData<T, A> {
         DirectoryNodeData {
             bounding_box: None,
             children: Box::new(Vec::with_capacity(options.max_size + 1)),
             options: options,
             depth: depth,
+            aggregate: A::empty(),
         }
     }
 
-    fn new_parent(mut children: Box<Vec<RTreeNode<T>>>, depth: usize, options: Arc<RTreeOptions>
-                  ) -> DirectoryNodeData<T> {
+    fn new_parent(mut children: Box<Vec<RTreeNode<T, A>>>, depth: usize, options: Arc<RTreeOptions<T::Point>>
+                  ) -> DirectoryNodeData<T, A> {
         let missing = options.max_size + 1 - children.len();
         children.reserve_exact(missing);
         let mut result = DirectoryNodeData {
             bounding_box: None,
             children: children,
             depth: depth,
-            options: options
+            options: options,
+            aggregate: A::empty(),
         };
         result.update_mbr();
         result
@@ -200,6 +259,15 @@ impl <T> DirectoryNodeData<T>
         } else {
             self.bounding_box = None;
         }
+        self.update_aggregate();
+    }
+
+    #[inline]
+    fn update_aggregate(&mut self) {
+        self.aggregate = A::empty();
+        for child in self.children.iter() {
+            self.aggregate = A::combine(&self.aggregate, &child.aggregate());
+        }
     }
 
     #[inline]
@@ -211,36 +279,41 @@ impl <T> DirectoryNodeData<T>
         }
     }
 
-    fn insert(&mut self, t: RTreeNode<T>, state: &mut InsertionState) -> InsertionResult<T> {
+    fn insert(&mut self, t: RTreeNode<T, A>, state: &mut InsertionState) -> InsertionResult<T, A> {
         // Adjust own mbr - the element will most likely become a child of this node
         self.update_mbr_with_element(&t.mbr());
-        if t.depth() + 1 == self.depth {
+        let result = if t.depth() + 1 == self.depth {
             // Force insertion into this node
             self.add_children(vec![t]);
-            return self.resolve_overflow(state);
-        }
-        let expand = {
-            let follow = self.choose_subtree(&t);
-            follow.insert(t, state)
+            self.resolve_overflow(state)
+        } else {
+            let expand = {
+                let follow = self.choose_subtree(&t);
+                follow.insert(t, state)
+            };
+            match expand {
+                InsertionResult::Split(child) => {
+                    // Insert into own list
+                    self.add_children(vec![child]);
+                    self.resolve_overflow(state)
+                },
+                result @ InsertionResult::Reinsert(_) => {
+                    // Reinsertion can shrink the mbr
+                    self.update_mbr();
+                    result
+                },
+                complete => complete,
+            }
         };
-        match expand {
-            InsertionResult::Split(child) => {
-                // Insert into own list
-                self.add_children(vec![child]);
-                self.resolve_overflow(state)
-            },
-            result @ InsertionResult::Reinsert(_) => {
-                // Reinsertion can shrink the mbr
-                self.update_mbr();
-                result
-            },
-            complete => complete,
-        }
+        // Folding the children keeps the aggregate equal to a full recomputation
+        self.update_aggregate();
+        result
     }
 
-    fn resolve_overflow(&mut self, state: &mut InsertionState) -> InsertionResult<T> {
+    fn resolve_overflow(&mut self, state: &mut InsertionState) -> InsertionResult<T, A> {
         if self.children.len() > self.options.max_size {
-            if state.did_reinsert(self.depth) {
+            if !self.options.split_strategy.uses_reinsertion() ||
+                state.did_reinsert(self.depth) {
                 // We did already reinsert on that level - split this node
                 let offsplit = self.split();
                 InsertionResult::Split(offsplit)
@@ -256,42 +329,7 @@ impl <T> DirectoryNodeData<T>
     }
 
     #[inline(never)]
-    fn split(&mut self) -> RTreeNode<T> {
-        let axis = self.get_split_axis();
-        assert!(self.children.len() >= 2);
-        // Sort along axis
-        self.children.sort_by(|l, r| l.mbr().lower().nth(axis).partial_cmp(&r.mbr().lower().nth(axis)).unwrap());
-        let mut best = (zero(), zero());
-        let mut best_index = self.options.min_size;
-
-        for k in self.options.min_size .. self.children.len() - self.options.min_size + 1 {
-            let mut first_mbr = self.children[k - 1].mbr();
-            let mut second_mbr = self.children[k].mbr();
-            let (l, r) = self.children.split_at(k);
-            for child in l {
-                first_mbr.add_rect(&child.mbr());
-            }
-            for child in r {
-                second_mbr.add_rect(&child.mbr());
-            }
-
-            let overlap_value = first_mbr.intersect(&second_mbr).area();
-            let area_value = first_mbr.area() + second_mbr.area();
-            let new_best = (overlap_value, area_value);
-            if new_best < best || k == self.options.min_size{
-                best = new_best;
-                best_index = k;
-            }
-        }
-        let offsplit = Box::new(self.children.split_off(best_index));
-        let result = RTreeNode::DirectoryNode(DirectoryNodeData::new_parent(offsplit, self.depth,
-                                                                            self.options.clone()));
-        self.update_mbr();
-        result
-    }
-
-    #[inline(never)]
-    fn reinsert(&mut self) -> Vec<RTreeNode<T>> {
+    fn reinsert(&mut self) -> Vec<RTreeNode<T, A>> {
         let center = self.mbr().center();
         // Sort with increasing order so we can use Vec::split_off
         self.children.sort_by(|l, r| {
@@ -305,35 +343,7 @@ impl <T> DirectoryNodeData<T>
         result
     }
 
-    fn get_split_axis(&mut self) -> usize {
-        let mut best_goodness = zero();
-        let mut best_axis = 0;
-        for axis in 0 .. T::Point::dimensions() {
-            // Sort children along the current axis
-            self.children.sort_by(|l, r| l.mbr().lower().nth(axis)
-                                  .partial_cmp(&r.mbr().lower().nth(axis)).unwrap());
-            for k in self.options.min_size .. self.children.len() - self.options.min_size + 1 {
-                let mut first_mbr = self.children[k - 1].mbr();
-                let mut second_mbr = self.children[k].mbr();
-                let (l, r) = self.children.split_at(k);
-                for child in l {
-                    first_mbr.add_rect(&child.mbr());
-                }
-                for child in r {
-                    second_mbr.add_rect(&child.mbr());
-                }
-
-                let margin_value = first_mbr.half_margin() + second_mbr.half_margin();
-                if best_goodness > margin_value || axis == 0 {
-                    best_axis = axis;
-                    best_goodness = margin_value;
-                }
-            }
-        }
-        best_axis
-    }
-
-    fn choose_subtree(&mut self, node: &RTreeNode<T>) -> &mut DirectoryNodeData<T> {
+    fn choose_subtree(&mut self, node: &RTreeNode<T, A>) -> &mut DirectoryNodeData<T, A> {
         assert!(self.depth >= 2, "Cannot choose subtree on this level");
         let insertion_mbr = node.mbr();
         let mut inclusion_count = 0;
@@ -353,8 +363,10 @@ impl <T> DirectoryNodeData<T>
             }
         }
         if inclusion_count == 0 {
-            // No inclusion found, subtree depends on overlap and area increase
-            let all_leaves = self.depth <= 2;
+            // No inclusion found, subtree depends on overlap and area increase.
+            // Only r*-tree like strategies consider the overlap increase.
+            let all_leaves = self.depth <= 2 &&
+                self.options.split_strategy.uses_reinsertion();
             let mut min = (zero(), zero(), zero());
 
             for (index, child1) in self.children.iter().enumerate() {
@@ -395,7 +407,7 @@ impl <T> DirectoryNodeData<T>
         }
     }
 
-    fn add_children(&mut self, mut new_children: Vec<RTreeNode<T>>) {
+    fn add_children(&mut self, mut new_children: Vec<RTreeNode<T, A>>) {
         if let &mut Some(ref mut bb) = &mut self.bounding_box {
             for child in &new_children {
                 bb.add_rect(&child.mbr());
@@ -548,27 +560,33 @@ impl <T> DirectoryNodeData<T>
     fn lookup_and_remove(&mut self, point: &T::Point) -> Option<T> {
         let contains = self.bounding_box.as_ref().map(|bb | bb.contains_point(point)).unwrap_or(false);
         if contains {
-            let mut children = ::std::mem::replace(&mut self.children, 
-                                                   Box::new(Vec::new()));
             let mut result = None;
-            for child in children.drain(..) {
-                match child {
-                    RTreeNode::DirectoryNode(mut data) => {
-                        result = data.lookup_and_remove(point).or(result);
-                        if !data.children.is_empty() {
-                            // Don't add a node if it has become empty
-                            self.children.push(RTreeNode::DirectoryNode(data));
+            let mut remove_index = None;
+            for (index, child) in self.children.iter_mut().enumerate() {
+                match *child {
+                    RTreeNode::DirectoryNode(ref mut data) => {
+                        result = data.lookup_and_remove(point);
+                        if result.is_some() {
+                            if data.children.is_empty() {
+                                // Mark this child for removal as it has become empty
+                                remove_index = Some(index);
+                            }
+                            break;
                         }
                     },
-                    RTreeNode::Leaf(b) => {
+                    RTreeNode::Leaf(ref b) => {
                         if b.contains(point) {
-                            result = Some(b);
-                        } else {
-                            self.children.push(RTreeNode::Leaf(b))
+                            remove_index = Some(index);
+                            break;
                         }
                     }
                 }
             }
+            if let Some(index) = remove_index {
+                if let RTreeNode::Leaf(b) = self.children.remove(index) {
+                    result = Some(b);
+                }
+            }
             if result.is_some() {
                 // Update the mbr if we did remove an element
                 self.update_mbr();
@@ -636,8 +654,9 @@ impl <T> DirectoryNodeData<T>
     }
 }
 
-impl <T> DirectoryNodeData<T>
-    where T: SpatialObject {
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     fn lookup_mut(&mut self, point: &T::Point) -> Option<&mut T> {
         let mut todo_list = Vec::with_capacity(40);
         todo_list.push(self);
@@ -659,13 +678,62 @@ impl <T> DirectoryNodeData<T>
         }
         None
     }
+
+    // Applies `f` to all selected leaves below this node. Leaves whose mbr has
+    // changed are removed and added to `moved`, empty child nodes are removed.
+    // Returns true if this node has changed.
+    fn update_selected<P, L, F>(&mut self, unpack_parent: &P, select_leaf: &L,
+                                f: &mut F, moved: &mut Vec<T>) -> bool
+        where P: Fn(&BoundingRect<T::Point>) -> bool,
+              L: Fn(&T) -> bool,
+              F: FnMut(&mut T) {
+        let mut changed = false;
+        let mut index = 0;
+        while index < self.children.len() {
+            let remove = match self.children[index] {
+                RTreeNode::DirectoryNode(ref mut data) => {
+                    if unpack_parent(&data.mbr()) &&
+                        data.update_selected(unpack_parent, select_leaf, f, moved) {
+                        changed = true;
+                        data.children.is_empty()
+                    } else {
+                        false
+                    }
+                },
+                RTreeNode::Leaf(ref mut t) => {
+                    if select_leaf(t) {
+                        let old_mbr = t.mbr();
+                        f(t);
+                        changed = true;
+                        // The object must be relocated
+                        t.mbr() != old_mbr
+                    } else {
+                        false
+                    }
+                },
+            };
+            if remove {
+                if let RTreeNode::Leaf(t) = self.children.swap_remove(index) {
+                    moved.push(t);
+                }
+            } else {
+                index += 1;
+            }
+        }
+        if changed {
+            // The objects' aggregates may have changed as well
+            self.update_mbr();
+        }
+        changed
+    }
 }
 
 #[doc(hidden)]
-impl <T> DirectoryNodeData<T>
-    where T: SpatialObject + PartialEq {
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject + PartialEq,
+          A: Aggregate<T> {
 
-    pub fn remove(&mut self, to_remove: &T) -> bool {
+    fn remove(&mut self, to_remove: &T) -> bool {
         let contains = self.bounding_box.as_ref().map(
             |bb| bb.contains_rect(&to_remove.mbr())).unwrap_or(false);
         if contains {
@@ -694,6 +762,8 @@ impl <T> DirectoryNodeData<T>
             }
             if let Some(to_remove) = remove_index {
                 self.children.remove(to_remove);
+            }
+            if result {
                 self.update_mbr();
             }
             result
@@ -702,6 +772,59 @@ impl <T> DirectoryNodeData<T>
         }
     }
 
+    // Relocated objects are removed and returned
+    fn update<F>(&mut self, obj: &T, f: &mut Option<F>) -> UpdateResult<T>
+        where F: FnOnce(&mut T) {
+        let contains = self.bounding_box.as_ref().map(
+            |bb| bb.contains_rect(&obj.mbr())).unwrap_or(false);
+        if !contains {
+            return UpdateResult::NotFound;
+        }
+        let mut result = UpdateResult::NotFound;
+        let mut remove_index = None;
+        for (index, child) in self.children.iter_mut().enumerate() {
+            match *child {
+                RTreeNode::DirectoryNode(ref mut data) => {
+                    result = data.update(obj, f);
+                    if let UpdateResult::NotFound = result {
+                        continue;
+                    }
+                    if data.children.is_empty() {
+                        // Mark this child for removal as it has become empty
+                        remove_index = Some(index);
+                    }
+                    break;
+                },
+                RTreeNode::Leaf(ref mut t) => {
+                    if t == obj {
+                        let old_mbr = t.mbr();
+                        if let Some(f) = f.take() {
+                            f(t);
+                        }
+                        if t.mbr() != old_mbr {
+                            // The object must be relocated
+                            remove_index = Some(index);
+                        }
+                        result = UpdateResult::Updated;
+                        break;
+                    }
+                },
+            }
+        }
+        if let UpdateResult::NotFound = result {
+            // Nothing has changed
+            return result;
+        }
+        if let Some(index) = remove_index {
+            if let RTreeNode::Leaf(t) = self.children.remove(index) {
+                result = UpdateResult::Moved(t);
+            }
+        }
+        // The object's aggregate may have changed as well
+        self.update_mbr();
+        result
+    }
+
     fn contains(&self, obj: &T) -> bool {
         let contains = self.bounding_box.as_ref().map(
             |bb| bb.contains_rect(&obj.mbr())).unwrap_or(false);
@@ -725,11 +848,18 @@ impl <T> DirectoryNodeData<T>
     }
 }
 
-enum InsertionResult<T>
-    where T: SpatialObject {
+enum UpdateResult<T> {
+    NotFound,
+    Updated,
+    Moved(T),
+}
+
+enum InsertionResult<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     Complete,
-    Split(RTreeNode<T>),
-    Reinsert(Vec<RTreeNode<T>>),
+    Split(RTreeNode<T, A>),
+    Reinsert(Vec<RTreeNode<T, A>>),
 }
 
 struct InsertionState {
@@ -755,8 +885,9 @@ impl InsertionState {
 }
 
 #[doc(hidden)]
-impl <T> RTreeNode<T>
-    where T: SpatialObject {
+impl <T, A> RTreeNode<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     pub fn depth(&self) -> usize {
         match self {
             &RTreeNode::DirectoryNode(ref data) => data.depth,
@@ -771,6 +902,13 @@ impl <T> RTreeNode<T>
         }
     }
 
+    fn aggregate(&self) -> A::Value {
+        match *self {
+            RTreeNode::DirectoryNode(ref data) => data.aggregate.clone(),
+            RTreeNode::Leaf(ref t) => A::leaf(t),
+        }
+    }
+
     fn nearest_neighbor(&self, point: &T::Point, nearest_distance: Option<<T::Point as PointN>::Scalar>) 
                         -> Option<&T> {
         match self {
@@ -819,20 +957,23 @@ impl <T> RTreeNode<T>
 
 #[doc(hidden)]
 #[derive(Clone)]
-pub struct DirectoryNodeData<T>
-    where T: SpatialObject {
+pub struct DirectoryNodeData<T, A = ()>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     bounding_box: Option<BoundingRect<T::Point>>,
-    children: Box<Vec<RTreeNode<T>>>,
+    children: Box<Vec<RTreeNode<T, A>>>,
     depth: usize,
-    options: Arc<RTreeOptions>,
+    options: Arc<RTreeOptions<T::Point>>,
+    aggregate: A::Value,
 }
 
 #[doc(hidden)]
 #[derive(Clone)]
-pub enum RTreeNode<T>
-    where T: SpatialObject {
+pub enum RTreeNode<T, A = ()>
+    where T: SpatialObject,
+          A: Aggregate<T> {
     Leaf(T),
-    DirectoryNode(DirectoryNodeData<T>),
+    DirectoryNode(DirectoryNodeData<T, A>),
 }
 
 
@@ -894,8 +1035,10 @@ pub enum RTreeNode<T>
 /// ```
 
 #[derive(Clone)]
-pub struct RTree<T> where T: SpatialObject {
-    root: DirectoryNodeData<T>,
+pub struct RTree<T, A = ()>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    root: DirectoryNodeData<T, A>,
     size: usize,
 }
 
@@ -905,20 +1048,39 @@ impl<T> Default for RTree<T> where T: SpatialObject {
     }
 }
 
-impl<T> RTree<T> 
+impl<T> RTree<T>
     where T: SpatialObject {
     /// Creates an empty r*-tree.
     pub fn new() -> RTree<T> {
         RTree::new_with_options(Default::default())
     }
 
+    /// Searches for an element at a given position and returns a mutable
+    /// reference.
+    /// If `query_point` is contained by multiple objects in the tree,
+    /// one of them will be returned.
+    /// *Do not change the object's minimal bounding box*, use `update` instead.
+    /// Only available for trees without aggregates since a modified object
+    /// would leave the cached aggregates stale.
+    pub fn lookup_mut(&mut self, query_point: &T::Point) -> Option<&mut T> {
+        if self.size > 0 {
+            self.root.lookup_mut(query_point)
+        } else {
+            None
+        }
+    }
+}
+
+impl <T, A> RTree<T, A> 
+    where T: SpatialObject,
+          A: Aggregate<T> {
     /// Returns the trees minimal bounding box.
     pub fn mbr(&self) -> Option<BoundingRect<T::Point>> {
         self.root.bounding_box.clone()
     }
 
     #[doc(hidden)]
-    pub fn new_with_options(options: RTreeOptions) -> RTree<T> {
+    pub fn new_with_options(options: RTreeOptions<T::Point>) -> RTree<T, A> {
         let options = Arc::new(options);
         RTree {
             root: DirectoryNodeData::new(1, options),
@@ -932,12 +1094,12 @@ impl<T> RTree<T>
     }
 
     /// Returns an iterator over all contained elements.
-    pub fn iter(&self) -> RTreeIterator<T> {
+    pub fn iter(&self) -> RTreeIterator<T, A> {
         RTreeIterator::new(&self.root)
     }
-    
+
     #[doc(hidden)]
-    pub fn root(&self) -> &DirectoryNodeData<T> {
+    pub fn root(&self) -> &DirectoryNodeData<T, A> {
         // This access is only needed for one of the examples
         &self.root
     }
@@ -999,15 +1161,23 @@ impl<T> RTree<T>
     pub fn lookup_in_circle(&self, circle_origin: &T::Point, 
                             radius2: &<T::Point as PointN>::Scalar) -> Vec<&T> {
         let mut result = Vec::new();
+        self.lookup_in_circle_into(&mut result, circle_origin, radius2);
+        result
+    }
+
+    // Appends all objects (partially) contained in a circle to an existing buffer
+    pub(crate) fn lookup_in_circle_into<'b>(&'b self, result: &mut Vec<&'b T>,
+                                            circle_origin: &T::Point,
+                                            radius2: &<T::Point as PointN>::Scalar) {
         if self.size > 0 {
-            self.root.lookup_in_circle(&mut result, circle_origin.into(), radius2);
+            self.root.lookup_in_circle(result, circle_origin, radius2);
         }
-        result
     }
 }
 
-impl<T> RTree<T> 
-    where T: SpatialObject {
+impl <T, A> RTree<T, A> 
+    where T: SpatialObject,
+          A: Aggregate<T> {
     /// Searches for an element at a given position.
     ///
     /// If `query_point` is contained by one object in the tree, this object will be returned.
@@ -1020,16 +1190,67 @@ impl<T> RTree<T>
         }
     }
 
-    /// Searches for an element at a given position and returns a mutable
-    /// reference.
-    /// If `query_point` is contained by multiple objects in the tree,
-    /// one of them will be returned.
-    /// *Do not change the object's minimal bounding box*.
-    pub fn lookup_mut(&mut self, query_point: &T::Point) -> Option<&mut T> {
-        if self.size > 0 {
-            self.root.lookup_mut(query_point)
-        } else {
-            None
+    /// Applies a function to all contained elements.
+    ///
+    /// Like `update`, elements whose minimal bounding box has changed are
+    /// relocated within the tree.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// for x in 0 .. 10 {
+    ///   tree.insert(Point2::new(x, 0));
+    /// }
+    /// tree.update_all(|p| p.y = p.x);
+    /// assert!(tree.lookup(&Point2::new(3, 3)).is_some());
+    /// # }
+    /// ```
+    pub fn update_all<F>(&mut self, f: F)
+        where F: FnMut(&mut T) {
+        self.update_selected(&|_| true, &|_| true, f);
+    }
+
+    /// Applies a function to all objects (partially) contained in a rectangle.
+    ///
+    /// Like `update`, objects whose minimal bounding box has changed are
+    /// relocated within the tree.
+    pub fn update_in_rectangle<F>(&mut self, query_rect: &BoundingRect<T::Point>, f: F)
+        where F: FnMut(&mut T) {
+        self.update_with_selection_function(&SelectInRectangle { query_rect }, f);
+    }
+
+    /// Applies a function to all objects selected by a selection function.
+    ///
+    /// Like `update`, objects whose minimal bounding box has changed are
+    /// relocated within the tree.
+    pub fn update_with_selection_function<S, F>(&mut self, selection: &S, f: F)
+        where S: SelectionFunction<T>,
+              F: FnMut(&mut T) {
+        self.update_selected(&|mbr| selection.should_unpack_parent(mbr),
+                             &|t| selection.should_unpack_leaf(t), f);
+    }
+
+    // Applies `f` to all selected elements in a single pass and reinserts
+    // the elements that have moved afterwards.
+    fn update_selected<P, L, F>(&mut self, unpack_parent: &P, select_leaf: &L, mut f: F)
+        where P: Fn(&BoundingRect<T::Point>) -> bool,
+              L: Fn(&T) -> bool,
+              F: FnMut(&mut T) {
+        let mut moved = Vec::new();
+        if self.size > 0 && unpack_parent(&self.root.mbr()) {
+            self.root.update_selected(unpack_parent, select_leaf, &mut f, &mut moved);
+        }
+        if self.root.children.is_empty() {
+            self.root.depth = 1;
+        }
+        for t in moved {
+            self.insert_node(RTreeNode::Leaf(t));
         }
     }
 
@@ -1038,8 +1259,38 @@ impl<T> RTree<T>
     /// This will require `O(log(n))` operations on average, where n is the number of
     /// elements contained in the tree.
     pub fn insert(&mut self, t: T) {
+        self.insert_node(RTreeNode::Leaf(t));
+        self.size += 1;
+    }
+
+    // Reinserts the entries of dissolved nodes at their level, as prescribed by
+    // the r*-tree's condense operation. The tree's height shrinks if necessary.
+    fn condense(&mut self, mut orphans: Vec<RTreeNode<T, A>>) {
+        if self.root.children.is_empty() {
+            self.root.depth = 1;
+        }
+        // Reinsert higher subtrees first
+        orphans.sort_by_key(|node| node.depth());
+        while let Some(orphan) = orphans.pop() {
+            if orphan.depth() < self.root.depth {
+                self.insert_node(orphan);
+            } else if let RTreeNode::DirectoryNode(mut data) = orphan {
+                // The tree has become too low to hold this subtree
+                orphans.extend(data.children.drain(..));
+            }
+        }
+        // Shorten the tree if the root has only one directory child
+        while self.root.children.len() == 1 && self.root.depth > 1 {
+            if let Some(RTreeNode::DirectoryNode(child)) = self.root.children.pop() {
+                self.root = child;
+            }
+        }
+    }
+
+    // Inserts a leaf or a subtree at its level, the tree's size is not changed.
+    fn insert_node(&mut self, node: RTreeNode<T, A>) {
         let mut state = InsertionState::new(self.root.depth + 1);
-        let mut insertion_stack = vec![RTreeNode::Leaf(t)];
+        let mut insertion_stack = vec![node];
         loop {
             if let Some(next) = insertion_stack.pop() {
                 match self.root.insert(next, &mut state) {
@@ -1049,8 +1300,10 @@ impl<T> RTree<T>
                         let options = self.root.options.clone();
                         let old_root = ::std::mem::replace(
                             &mut self.root, DirectoryNodeData::new(
-                                new_depth, options));
-                        self.root.add_children(vec![RTreeNode::DirectoryNode(old_root), node]);
+                                new_depth, options.clone()));
+                        self.root = DirectoryNodeData::new_parent(
+                            Box::new(vec![RTreeNode::DirectoryNode(old_root), node]),
+                            new_depth, options);
                     },
                     InsertionResult::Reinsert(nodes) => {
                         // Schedule elements for reinsertion
@@ -1062,7 +1315,6 @@ impl<T> RTree<T>
                 break;
             }
         }
-        self.size += 1;
     }
 
     /// Searches for an element and removes it.
@@ -1071,23 +1323,23 @@ impl<T> RTree<T>
     /// and returned. If the point is contained by multiple objects, only one of them is removed and
     /// returned.
     pub fn lookup_and_remove(&mut self, query_point: &T::Point) -> Option<T> {
-        if self.size > 0 {
-            let result = self.root.lookup_and_remove(query_point);
-            if result.is_some() {
-                if self.root.children.is_empty() {
-                    self.root.depth = 1;
-                }
-                self.size -= 1;
-            }
-            result
-        } else {
-            None
+        if self.size == 0 {
+            return None;
+        }
+        let result = self.root.lookup_and_remove(query_point);
+        if result.is_some() {
+            self.size -= 1;
         }
+        if self.root.children.is_empty() {
+            self.root.depth = 1;
+        }
+        result
     }
 }
 
-impl <T> RTree<T>
-    where T: SpatialObject + PartialEq {
+impl <T, A> RTree<T, A>
+    where T: SpatialObject + PartialEq,
+          A: Aggregate<T> {
 
     /// Removes an object from the tree.
     ///
@@ -1113,17 +1365,83 @@ impl <T> RTree<T>
     pub fn contains(&self, obj: &T) -> bool {
         self.root.contains(obj)
     }
+
+    /// Updates an object contained in this tree.
+    ///
+    /// Locates an object equal to `obj` and calls `f` on it. `f` may change any
+    /// part of the object, including its position. If the object's minimal bounding
+    /// box has changed, the object is relocated within the tree.
+    /// Returns `false` if no object equal to `obj` is contained.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use spade::HasPosition;
+    /// use cgmath::Point2;
+    ///
+    /// #[derive(PartialEq, Clone)]
+    /// struct Vehicle {
+    ///   position: Point2<f32>,
+    ///   speed: f32,
+    /// }
+    ///
+    /// impl HasPosition for Vehicle {
+    ///   type Point = Point2<f32>;
+    ///   fn position(&self) -> Point2<f32> {
+    ///     self.position
+    ///   }
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// let vehicle = Vehicle { position: Point2::new(0.0, 0.0), speed: 0.0 };
+    /// tree.insert(vehicle.clone());
+    /// assert!(tree.update(&vehicle, |v| {
+    ///   v.position = Point2::new(5.0, 5.0);
+    ///   v.speed = 2.0;
+    /// }));
+    /// assert_eq!(tree.lookup(&Point2::new(5.0, 5.0)).unwrap().speed, 2.0);
+    /// # }
+    /// ```
+    pub fn update<F>(&mut self, obj: &T, f: F) -> bool
+        where F: FnOnce(&mut T) {
+        if self.size == 0 {
+            return false;
+        }
+        let mut f = Some(f);
+        match self.root.update(obj, &mut f) {
+            UpdateResult::NotFound => false,
+            UpdateResult::Updated => true,
+            UpdateResult::Moved(t) => {
+                if self.root.children.is_empty() {
+                    self.root.depth = 1;
+                }
+                self.insert_node(RTreeNode::Leaf(t));
+                true
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{RTree};
+    use super::{RTree, Count};
+    use traits::{HasPosition, SpatialObject};
     use boundingvolume::BoundingRect;
     use primitives::{SimpleTriangle, SimpleEdge};
     use cgmath::{Point2, InnerSpace};
     use num::Float;
     use testutils::*;
 
+    pub fn check_tree<T: SpatialObject>(tree: &RTree<T>) {
+        if let Err(error) = tree.validate() {
+            panic!("invalid tree: {}", error);
+        }
+        assert_eq!(tree.iter().count(), tree.size());
+    }
+
     #[test]
     fn test_tree_with_integral_points() {
         // This test should compile
@@ -1253,8 +1571,11 @@ mod test {
             assert_eq!(tree.lookup(point), Some(point));
         }
         // Now remove all points
-        for point in &points {
+        for (index, point) in points.iter().enumerate() {
             assert_eq!(tree.lookup_and_remove(point).as_ref(), Some(point));
+            if index % 1000 == 0 {
+                check_tree(&tree);
+            }
         }
         assert!(tree.root.children.is_empty());
         tree.insert(Point2::new(20., 10.));
@@ -1339,4 +1660,139 @@ mod test {
             assert_eq!(tree.nearest_neighbor(entry), Some(entry))
         }
     }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Payload {
+        position: Point2<f64>,
+        value: u32,
+    }
+
+    impl HasPosition for Payload {
+        type Point = Point2<f64>;
+        fn position(&self) -> Point2<f64> {
+            self.position
+        }
+    }
+
+    fn create_payload_tree(size: usize, seed: [u32; 4]) -> (RTree<Payload>, Vec<Payload>) {
+        let points = random_points_with_seed(size, seed);
+        let payloads: Vec<_> = points.into_iter().map(
+            |position| Payload { position, value: 0 }).collect();
+        let mut tree = RTree::new();
+        for payload in &payloads {
+            tree.insert(payload.clone());
+        }
+        (tree, payloads)
+    }
+
+    #[test]
+    fn test_update_all() {
+        let (mut tree, payloads) = create_payload_tree(500, [7, 7, 1, 3]);
+        let mut index = 0;
+        tree.update_all(|payload| {
+            payload.value = index;
+            index += 1;
+        });
+        assert_eq!(index as usize, payloads.len());
+        let mut values: Vec<_> = tree.iter().map(|p| p.value).collect();
+        values.sort();
+        assert_eq!(values, (0 .. payloads.len() as u32).collect::<Vec<_>>());
+        let mut empty: RTree<Point2<f64>> = RTree::new();
+        empty.update_all(|_| panic!("The tree is empty"));
+    }
+
+    #[test]
+    fn test_update_in_rectangle() {
+        let (mut tree, payloads) = create_payload_tree(500, [8, 1, 9, 2]);
+        let rect = BoundingRect::from_corners(&Point2::new(-0.3, -0.2), &Point2::new(0.5, 0.6));
+        let mut count = 0;
+        tree.update_in_rectangle(&rect, |payload| {
+            payload.value = 1;
+            count += 1;
+        });
+        for payload in tree.iter() {
+            assert_eq!(payload.value == 1, rect.contains_point(&payload.position));
+        }
+        let expected = payloads.iter().filter(|p| rect.contains_point(&p.position)).count();
+        assert_eq!(count, expected);
+    }
+
+    #[test]
+    fn test_update_all_relocates_objects() {
+        let (mut tree, payloads) = create_payload_tree(1000, [2, 4, 6, 8]);
+        let rect = BoundingRect::from_corners(&Point2::new(-0.5, -0.5), &Point2::new(0.5, 0.5));
+        // Move all objects within the rectangle far away
+        tree.update_in_rectangle(&rect, |payload| payload.position.x += 10.);
+        assert_eq!(tree.validate(), Ok(()));
+        assert!(tree.lookup_in_rectangle(&rect).is_empty());
+        // Mirror all objects
+        tree.update_all(|payload| payload.position = Point2::new(-payload.position.y,
+                                                                 payload.position.x));
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.size(), payloads.len());
+        for payload in &payloads {
+            let mut expected = payload.position;
+            if rect.contains_point(&expected) {
+                expected.x += 10.;
+            }
+            let expected = Point2::new(-expected.y, expected.x);
+            assert!(tree.lookup(&expected).is_some());
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let (mut tree, payloads) = create_payload_tree(500, [3, 0, 0, 5]);
+        // Payload changes keep the object in place
+        assert!(tree.update(&payloads[0], |p| p.value = 5));
+        assert_eq!(tree.lookup(&payloads[0].position).unwrap().value, 5);
+        assert!(!tree.update(&payloads[0], |p| p.value = 6));
+        // Position changes relocate the object
+        for (index, payload) in payloads.iter().enumerate().skip(1) {
+            let new_position = Point2::new(payload.position.y, -payload.position.x);
+            assert!(tree.update(payload, |p| {
+                p.position = new_position;
+                p.value = index as u32;
+            }));
+        }
+        assert_eq!(tree.size(), payloads.len());
+        assert_eq!(tree.iter().count(), payloads.len());
+        for (index, payload) in payloads.iter().enumerate().skip(1) {
+            let new_position = Point2::new(payload.position.y, -payload.position.x);
+            let found = tree.nearest_neighbor(&new_position).unwrap();
+            assert_eq!(found.position, new_position);
+            assert_eq!(found.value, index as u32);
+        }
+    }
+
+    #[test]
+    fn test_update_after_lookup_and_remove() {
+        let points = random_points_with_seed::<f64>(2000, [8, 1, 4, 4]);
+        let mut tree: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            tree.insert(*point);
+        }
+        let (removed, remaining) = points.split_at(1500);
+        for point in removed {
+            assert_eq!(tree.lookup_and_remove(point), Some(*point));
+        }
+        assert!(tree.validate().is_ok());
+        // Updating missing objects must not change the tree
+        for point in removed {
+            assert!(!tree.update(point, |p| p.x += 1.0));
+        }
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.iter().count(), remaining.len());
+        assert_eq!(tree.aggregate(), remaining.len());
+        for point in remaining {
+            assert!(tree.update(point, |p| *p = Point2::new(p.y, -p.x)));
+        }
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.size(), remaining.len());
+        assert_eq!(tree.iter().count(), remaining.len());
+        assert_eq!(tree.aggregate(), remaining.len());
+        for point in remaining {
+            assert!(tree.contains(&Point2::new(point.y, -point.x)));
+        }
+    }
 }
@@ -0,0 +1,631 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A binary r*-tree format that can be queried without deserialization.
+
+use std::collections::{BinaryHeap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use traits::{SpatialObject, SpadeNum};
+use point_traits::{PointN, PointNExtensions};
+use boundingvolume::BoundingRect;
+use misc::MinHeapEntry;
+use super::{Aggregate, RTree, RTreeNode};
+
+const MAGIC: &[u8; 8] = b"SPADERT\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 48;
+
+/// A scalar type that can be stored in a packed r-tree.
+///
+/// Scalars are stored in little endian byte order.
+pub trait PackedScalar: SpadeNum + Copy {
+    /// The number of bytes used to store a scalar.
+    fn packed_size() -> usize;
+    /// Writes this scalar into the first `packed_size()` bytes of `buffer`.
+    fn write_packed(&self, buffer: &mut [u8]);
+    /// Reads a scalar from the first `packed_size()` bytes of `buffer`.
+    fn read_packed(buffer: &[u8]) -> Self;
+}
+
+/// An object with a fixed size binary representation.
+///
+/// Implemented for all points whose scalar type implements `PackedScalar`.
+/// Custom records must always write and read exactly `record_size()` bytes.
+pub trait PackedRecord: SpatialObject + Sized {
+    /// The number of bytes used to store a record.
+    fn record_size() -> usize;
+    /// Writes this record into the first `record_size()` bytes of `buffer`.
+    fn write_record(&self, buffer: &mut [u8]);
+    /// Reads a record from the first `record_size()` bytes of `buffer`.
+    fn read_record(buffer: &[u8]) -> Self;
+}
+
+fn write_u32(buffer: &mut [u8], value: u32) {
+    for (i, byte) in buffer[.. 4].iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+}
+
+fn read_u32(buffer: &[u8]) -> u32 {
+    buffer[.. 4].iter().rev().fold(0, |acc, byte| (acc << 8) | u32::from(*byte))
+}
+
+fn write_u64(buffer: &mut [u8], value: u64) {
+    for (i, byte) in buffer[.. 8].iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+}
+
+fn read_u64(buffer: &[u8]) -> u64 {
+    buffer[.. 8].iter().rev().fold(0, |acc, byte| (acc << 8) | u64::from(*byte))
+}
+
+// Returns None if the value does not fit into usize
+fn read_usize(buffer: &[u8]) -> Option<usize> {
+    let value = read_u64(buffer);
+    if value > usize::MAX as u64 { None } else { Some(value as usize) }
+}
+
+impl PackedScalar for f32 {
+    fn packed_size() -> usize { 4 }
+    fn write_packed(&self, buffer: &mut [u8]) { write_u32(buffer, self.to_bits()) }
+    fn read_packed(buffer: &[u8]) -> f32 { f32::from_bits(read_u32(buffer)) }
+}
+
+impl PackedScalar for f64 {
+    fn packed_size() -> usize { 8 }
+    fn write_packed(&self, buffer: &mut [u8]) { write_u64(buffer, self.to_bits()) }
+    fn read_packed(buffer: &[u8]) -> f64 { f64::from_bits(read_u64(buffer)) }
+}
+
+impl PackedScalar for i32 {
+    fn packed_size() -> usize { 4 }
+    fn write_packed(&self, buffer: &mut [u8]) { write_u32(buffer, *self as u32) }
+    fn read_packed(buffer: &[u8]) -> i32 { read_u32(buffer) as i32 }
+}
+
+impl PackedScalar for i64 {
+    fn packed_size() -> usize { 8 }
+    fn write_packed(&self, buffer: &mut [u8]) { write_u64(buffer, *self as u64) }
+    fn read_packed(buffer: &[u8]) -> i64 { read_u64(buffer) as i64 }
+}
+
+fn write_point<V>(buffer: &mut [u8], point: &V)
+    where V: PointN,
+          V::Scalar: PackedScalar {
+    let size = V::Scalar::packed_size();
+    for i in 0 .. V::dimensions() {
+        point.nth(i).write_packed(&mut buffer[i * size ..]);
+    }
+}
+
+fn read_point<V>(buffer: &[u8]) -> V
+    where V: PointN,
+          V::Scalar: PackedScalar {
+    let size = V::Scalar::packed_size();
+    let mut result = V::new();
+    for i in 0 .. V::dimensions() {
+        *result.nth_mut(i) = V::Scalar::read_packed(&buffer[i * size ..]);
+    }
+    result
+}
+
+impl <V> PackedRecord for V
+    where V: PointN,
+          V::Scalar: PackedScalar {
+    fn record_size() -> usize {
+        V::dimensions() * V::Scalar::packed_size()
+    }
+
+    fn write_record(&self, buffer: &mut [u8]) {
+        write_point(buffer, self);
+    }
+
+    fn read_record(buffer: &[u8]) -> V {
+        read_point(buffer)
+    }
+}
+
+/// An error that occurs when a packed r-tree is opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackedRTreeError {
+    /// The data does not start with the packed r-tree magic bytes.
+    InvalidMagic,
+    /// The data was written with an unsupported format version.
+    UnsupportedVersion(u32),
+    /// The dimension, scalar size or record size does not match the requested type.
+    IncompatibleType,
+    /// The data is shorter than announced in its header.
+    Truncated,
+    /// The nodes do not form a valid tree.
+    Corrupted,
+}
+
+impl fmt::Display for PackedRTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PackedRTreeError::InvalidMagic => write!(f, "not a packed r-tree"),
+            PackedRTreeError::UnsupportedVersion(version) =>
+                write!(f, "unsupported packed r-tree version {}", version),
+            PackedRTreeError::IncompatibleType =>
+                write!(f, "packed r-tree was written for a different record type"),
+            PackedRTreeError::Truncated => write!(f, "packed r-tree data is truncated"),
+            PackedRTreeError::Corrupted => write!(f, "packed r-tree nodes are corrupted"),
+        }
+    }
+}
+
+impl Error for PackedRTreeError { }
+
+fn node_size<V>() -> usize
+    where V: PointN,
+          V::Scalar: PackedScalar {
+    16 + 2 * V::dimensions() * V::Scalar::packed_size()
+}
+
+impl <T, A> RTree<T, A>
+    where T: PackedRecord,
+          A: Aggregate<T>,
+          <T::Point as PointN>::Scalar: PackedScalar {
+
+    /// Writes this tree in the packed binary format.
+    ///
+    /// The written data can be queried with `PackedRTree` without deserializing it.
+    /// See `PackedRTree` for a description of the format. Cached aggregates are
+    /// not part of the format and are not written.
+    pub fn write_packed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        // Collect all directory nodes in breadth-first order
+        let mut nodes = Vec::new();
+        let mut leaves = Vec::with_capacity(self.size);
+        let mut first_leaf_parent = 0;
+        let mut queue = VecDeque::new();
+        if self.size > 0 {
+            queue.push_back(&self.root);
+        }
+        let mut num_nodes = 1;
+        while let Some(node) = queue.pop_front() {
+            let first_child = if node.depth == 1 {
+                if leaves.is_empty() {
+                    first_leaf_parent = nodes.len();
+                }
+                leaves.len()
+            } else {
+                num_nodes += node.children.len();
+                num_nodes - node.children.len()
+            };
+            nodes.push((first_child, node.children.len(), node.mbr()));
+            for child in node.children.iter() {
+                match *child {
+                    RTreeNode::Leaf(ref t) => leaves.push(t),
+                    RTreeNode::DirectoryNode(ref data) => queue.push_back(data),
+                }
+            }
+        }
+
+        let mut header = [0u8; HEADER_SIZE];
+        header[.. 8].copy_from_slice(MAGIC);
+        write_u32(&mut header[8 ..], VERSION);
+        write_u32(&mut header[12 ..], T::Point::dimensions() as u32);
+        write_u32(&mut header[16 ..], <T::Point as PointN>::Scalar::packed_size() as u32);
+        write_u32(&mut header[20 ..], T::record_size() as u32);
+        write_u64(&mut header[24 ..], nodes.len() as u64);
+        write_u64(&mut header[32 ..], leaves.len() as u64);
+        write_u64(&mut header[40 ..], first_leaf_parent as u64);
+        writer.write_all(&header)?;
+
+        let point_size = T::Point::dimensions() * <T::Point as PointN>::Scalar::packed_size();
+        let mut buffer = vec![0u8; node_size::<T::Point>()];
+        for &(first_child, child_count, ref mbr) in &nodes {
+            write_u64(&mut buffer, first_child as u64);
+            write_u64(&mut buffer[8 ..], child_count as u64);
+            write_point(&mut buffer[16 ..], &mbr.lower());
+            write_point(&mut buffer[16 + point_size ..], &mbr.upper());
+            writer.write_all(&buffer)?;
+        }
+        let mut buffer = vec![0u8; T::record_size()];
+        for leaf in leaves {
+            leaf.write_record(&mut buffer);
+            writer.write_all(&buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// An r*-tree that is queried directly from its packed binary representation.
+///
+/// A packed tree does not copy or deserialize its data, only the nodes and records
+/// visited by a query are read. This allows to query trees that exceed the available
+/// memory, e.g. by using a memory mapped file as data.
+/// Packed trees are written with `RTree::write_packed` and are read only.
+///
+/// # Format
+/// All values are stored in little endian byte order. The data starts with a 48 byte
+/// header:
+///
+/// | Offset | Size | Content |
+/// |--------|------|---------|
+/// | 0      | 8    | Magic bytes `SPADERT\0` |
+/// | 8      | 4    | Format version, currently `1` |
+/// | 12     | 4    | Number of dimensions `d` |
+/// | 16     | 4    | Scalar size `s` in bytes |
+/// | 20     | 4    | Record size `r` in bytes |
+/// | 24     | 8    | Number of directory nodes `n` |
+/// | 32     | 8    | Number of records `m` |
+/// | 40     | 8    | Index of the first node whose children are records |
+///
+/// The header is followed by `n` directory nodes in breadth-first order, starting
+/// with the root. Each node takes `16 + 2 * d * s` bytes: The index of its first child
+/// (8 bytes), the number of its children (8 bytes), the lower and the upper corner
+/// of its bounding rectangle. The children of a node are stored consecutively. All
+/// nodes at or after the index given in the header refer to records, all other nodes
+/// refer to directory nodes.
+/// The nodes are followed by `m` records of `r` bytes each.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::{RTree, PackedRTree};
+/// use cgmath::Point2;
+///
+/// # fn main() {
+/// let mut tree = RTree::new();
+/// tree.insert(Point2::new(0.0f64, 1.0));
+/// tree.insert(Point2::new(2.0, 0.5));
+/// let mut data = Vec::new();
+/// tree.write_packed(&mut data).unwrap();
+/// // data could also be a memory mapped file
+/// let packed: PackedRTree<Point2<f64>> = PackedRTree::from_bytes(&data).unwrap();
+/// assert_eq!(packed.nearest_neighbor(&Point2::new(1.5, 0.0)), Some(Point2::new(2.0, 0.5)));
+/// # }
+/// ```
+pub struct PackedRTree<'a, T> {
+    data: &'a [u8],
+    num_nodes: usize,
+    num_records: usize,
+    first_leaf_parent: usize,
+    object_type: PhantomData<T>,
+}
+
+enum PackedEntry<T> {
+    Node(usize),
+    Record(T),
+}
+
+impl <'a, T> PackedRTree<'a, T>
+    where T: PackedRecord,
+          <T::Point as PointN>::Scalar: PackedScalar {
+
+    /// Opens a packed r-tree.
+    ///
+    /// Checks the header for compatibility with the record type `T` and the data's
+    /// length. Only the header is read, queries skip nodes with invalid child ranges.
+    /// Use `validate` to check all nodes.
+    pub fn from_bytes(data: &'a [u8]) -> Result<PackedRTree<'a, T>, PackedRTreeError> {
+        if data.len() < HEADER_SIZE {
+            return Err(if data.starts_with(&MAGIC[.. data.len().min(8)]) {
+                PackedRTreeError::Truncated
+            } else {
+                PackedRTreeError::InvalidMagic
+            });
+        }
+        if &data[.. 8] != MAGIC {
+            return Err(PackedRTreeError::InvalidMagic);
+        }
+        let version = read_u32(&data[8 ..]);
+        if version != VERSION {
+            return Err(PackedRTreeError::UnsupportedVersion(version));
+        }
+        if read_u32(&data[12 ..]) as usize != T::Point::dimensions() ||
+            read_u32(&data[16 ..]) as usize != <T::Point as PointN>::Scalar::packed_size() ||
+            read_u32(&data[20 ..]) as usize != T::record_size() {
+            return Err(PackedRTreeError::IncompatibleType);
+        }
+        let (num_nodes, num_records, first_leaf_parent) =
+            match (read_usize(&data[24 ..]), read_usize(&data[32 ..]), read_usize(&data[40 ..])) {
+                (Some(n), Some(m), Some(f)) => (n, m, f),
+                _ => return Err(PackedRTreeError::Truncated),
+            };
+        let end = num_nodes.checked_mul(node_size::<T::Point>())
+            .and_then(|nodes| nodes.checked_add(HEADER_SIZE))
+            .and_then(|offset| num_records.checked_mul(T::record_size())
+                      .and_then(|records| records.checked_add(offset)));
+        if end.map(|end| data.len() < end).unwrap_or(true) {
+            return Err(PackedRTreeError::Truncated);
+        }
+        if first_leaf_parent > num_nodes || (num_nodes == 0) != (num_records == 0) {
+            return Err(PackedRTreeError::Corrupted);
+        }
+        Ok(PackedRTree {
+            data,
+            num_nodes,
+            num_records,
+            first_leaf_parent,
+            object_type: PhantomData,
+        })
+    }
+
+    /// Checks that the nodes are stored as written by `RTree::write_packed`.
+    ///
+    /// The children of all nodes must be stored consecutively in node order and
+    /// every node and record except the root must be the child of exactly one node.
+    /// This reads all nodes but no records, the records are not validated.
+    pub fn validate(&self) -> Result<(), PackedRTreeError> {
+        let mut next_node = 1;
+        let mut next_record = 0;
+        for index in 0 .. self.num_nodes {
+            let node = self.node(index);
+            let (first, count) = match (read_usize(node), read_usize(&node[8 ..])) {
+                (Some(first), Some(count)) => (first, count),
+                _ => return Err(PackedRTreeError::Corrupted),
+            };
+            let next = if index < self.first_leaf_parent { &mut next_node } else { &mut next_record };
+            // Directory nodes must refer to nodes behind themselves
+            if first != *next || (index < self.first_leaf_parent && first <= index) {
+                return Err(PackedRTreeError::Corrupted);
+            }
+            *next = first.checked_add(count).ok_or(PackedRTreeError::Corrupted)?;
+        }
+        if self.num_nodes > 0 && (next_node != self.num_nodes || next_record != self.num_records) {
+            return Err(PackedRTreeError::Corrupted);
+        }
+        Ok(())
+    }
+
+    fn records_offset(&self) -> usize {
+        HEADER_SIZE + self.num_nodes * node_size::<T::Point>()
+    }
+
+    fn node(&self, index: usize) -> &'a [u8] {
+        let offset = HEADER_SIZE + index * node_size::<T::Point>();
+        &self.data[offset .. offset + node_size::<T::Point>()]
+    }
+
+    // Returns an empty range for corrupted nodes whose children are out of bounds.
+    // Directory nodes must refer to nodes behind themselves, otherwise a traversal
+    // might not terminate.
+    fn node_children(&self, index: usize) -> ::std::ops::Range<usize> {
+        let node = self.node(index);
+        let (min_first, limit) = if index < self.first_leaf_parent {
+            (index + 1, self.num_nodes)
+        } else {
+            (0, self.num_records)
+        };
+        match (read_usize(node), read_usize(&node[8 ..])) {
+            (Some(first), Some(count)) if first >= min_first
+                && count <= limit && first <= limit - count => first .. first + count,
+            _ => 0 .. 0,
+        }
+    }
+
+    fn node_mbr(&self, index: usize) -> BoundingRect<T::Point> {
+        let node = self.node(index);
+        let point_size = T::Point::dimensions() * <T::Point as PointN>::Scalar::packed_size();
+        BoundingRect::from_corners(&read_point(&node[16 ..]),
+                                   &read_point(&node[16 + point_size ..]))
+    }
+
+    /// Reads the record with a given index.
+    ///
+    /// Records are stored in breadth-first order of their parent nodes.
+    /// Panics if `index >= self.size()`.
+    pub fn record(&self, index: usize) -> T {
+        assert!(index < self.num_records, "Record index out of bounds");
+        let offset = self.records_offset() + index * T::record_size();
+        T::read_record(&self.data[offset .. offset + T::record_size()])
+    }
+
+    /// Returns the number of records contained in this tree.
+    pub fn size(&self) -> usize {
+        self.num_records
+    }
+
+    /// Returns the tree's minimal bounding box.
+    pub fn mbr(&self) -> Option<BoundingRect<T::Point>> {
+        if self.num_records == 0 {
+            None
+        } else {
+            Some(self.node_mbr(0))
+        }
+    }
+
+    /// Returns all records (partially) contained in a rectangle.
+    pub fn lookup_in_rectangle(&self, query_rect: &BoundingRect<T::Point>) -> Vec<T> {
+        let mut result = Vec::new();
+        if self.num_records == 0 || !self.node_mbr(0).intersects(query_rect) {
+            return result;
+        }
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            if node >= self.first_leaf_parent {
+                for index in self.node_children(node) {
+                    let record = self.record(index);
+                    if record.mbr().intersects(query_rect) {
+                        result.push(record);
+                    }
+                }
+            } else {
+                stack.extend(self.node_children(node).filter(
+                    |child| self.node_mbr(*child).intersects(query_rect)));
+            }
+        }
+        result
+    }
+
+    /// Returns the nearest neighbor.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn nearest_neighbor(&self, query_point: &T::Point) -> Option<T> {
+        self.nearest_n_neighbors(query_point, 1).pop()
+    }
+
+    /// Returns the nearest n neighbors, sorted by their distance.
+    pub fn nearest_n_neighbors(&self, query_point: &T::Point, n: usize) -> Vec<T> {
+        let mut result = Vec::with_capacity(n);
+        if self.num_records == 0 || n == 0 {
+            return result;
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push(MinHeapEntry {
+            distance2: self.node_mbr(0).min_dist2(query_point),
+            entry: PackedEntry::Node(0),
+        });
+        while let Some(MinHeapEntry { entry, .. }) = heap.pop() {
+            match entry {
+                PackedEntry::Record(record) => {
+                    result.push(record);
+                    if result.len() == n {
+                        break;
+                    }
+                },
+                PackedEntry::Node(node) => {
+                    for child in self.node_children(node) {
+                        if node >= self.first_leaf_parent {
+                            let record = self.record(child);
+                            heap.push(MinHeapEntry {
+                                distance2: record.distance2(query_point),
+                                entry: PackedEntry::Record(record),
+                            });
+                        } else {
+                            heap.push(MinHeapEntry {
+                                distance2: self.node_mbr(child).min_dist2(query_point),
+                                entry: PackedEntry::Node(child),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PackedRTree, PackedRTreeError, HEADER_SIZE, write_u64};
+    use super::super::{RTree, Count};
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_packed_queries() {
+        let (tree, points) = create_random_tree::<f64>(1000, [6, 6, 2, 1]);
+        let mut data = Vec::new();
+        tree.write_packed(&mut data).unwrap();
+        let packed: PackedRTree<Point2<f64>> = PackedRTree::from_bytes(&data).unwrap();
+        assert_eq!(packed.size(), points.len());
+        assert_eq!(packed.mbr(), tree.mbr());
+        for point in &points {
+            assert_eq!(packed.nearest_neighbor(point), Some(*point));
+        }
+        let queries = random_points_with_seed::<f64>(100, [3, 2, 8, 4]);
+        for query in &queries {
+            let expected: Vec<_> = tree.nearest_n_neighbors(query, 5).into_iter()
+                .cloned().collect();
+            assert_eq!(packed.nearest_n_neighbors(query, 5), expected);
+            let rect = BoundingRect::from_corners(query, &Point2::new(0.1, 0.2));
+            let mut found = packed.lookup_in_rectangle(&rect);
+            assert_eq!(found.len(), tree.lookup_in_rectangle(&rect).len());
+            assert!(found.drain(..).all(|p| rect.contains_point(&p)));
+        }
+    }
+
+    #[test]
+    fn test_packed_integer_points() {
+        let mut tree = RTree::new();
+        for x in 0 .. 20 {
+            for y in 0 .. 20 {
+                tree.insert([x, y]);
+            }
+        }
+        let mut data = Vec::new();
+        tree.write_packed(&mut data).unwrap();
+        let packed: PackedRTree<[i32; 2]> = PackedRTree::from_bytes(&data).unwrap();
+        let rect = BoundingRect::from_corners(&[-3, 2], &[4, 5]);
+        assert_eq!(packed.lookup_in_rectangle(&rect).len(), 5 * 4);
+        assert_eq!(packed.nearest_neighbor(&[25, -3]), Some([19, 0]));
+    }
+
+    #[test]
+    fn test_packed_aggregated_tree() {
+        let (tree, points) = create_random_tree::<f64>(500, [7, 1, 7, 1]);
+        let mut counted: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in &points {
+            counted.insert(*point);
+        }
+        let mut data = Vec::new();
+        counted.write_packed(&mut data).unwrap();
+        let mut expected = Vec::new();
+        tree.write_packed(&mut expected).unwrap();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_packed_errors() {
+        let (tree, _) = create_random_tree::<f64>(100, [1, 2, 3, 4]);
+        let mut data = Vec::new();
+        tree.write_packed(&mut data).unwrap();
+        assert_eq!(PackedRTree::<Point2<f32>>::from_bytes(&data).err(),
+                   Some(PackedRTreeError::IncompatibleType));
+        assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&data[.. data.len() - 1]).err(),
+                   Some(PackedRTreeError::Truncated));
+        assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&data[.. 10]).err(),
+                   Some(PackedRTreeError::Truncated));
+        assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&[0u8; 100]).err(),
+                   Some(PackedRTreeError::InvalidMagic));
+        data[8] = 2;
+        assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&data).err(),
+                   Some(PackedRTreeError::UnsupportedVersion(2)));
+        data[8] = 1;
+        assert!(PackedRTree::<Point2<f64>>::from_bytes(&data).is_ok());
+
+        // Header counts whose sizes overflow
+        for &(offset, value) in &[(24, u64::MAX), (32, u64::MAX / 8),
+                                  (32, u64::MAX)] {
+            let mut corrupted = data.clone();
+            write_u64(&mut corrupted[offset ..], value);
+            assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&corrupted).err(),
+                       Some(PackedRTreeError::Truncated));
+        }
+        // Invalid first leaf parent and record count
+        for &(offset, value) in &[(40, 1000), (32, 0)] {
+            let mut corrupted = data.clone();
+            write_u64(&mut corrupted[offset ..], value);
+            assert_eq!(PackedRTree::<Point2<f64>>::from_bytes(&corrupted).err(),
+                       Some(PackedRTreeError::Corrupted));
+        }
+        // Invalid child ranges are only detected by validate, queries skip them
+        let root_children = HEADER_SIZE + 8;
+        let query = Point2::new(0.5, 0.5);
+        let rect = BoundingRect::from_corners(&Point2::new(0.0, 0.0), &Point2::new(1.0, 1.0));
+        for &(offset, value) in &[(40, 0), (root_children, 1000),
+                                  (root_children, u64::MAX), (HEADER_SIZE, 0)] {
+            let mut corrupted = data.clone();
+            write_u64(&mut corrupted[offset ..], value);
+            let packed = PackedRTree::<Point2<f64>>::from_bytes(&corrupted).unwrap();
+            assert_eq!(packed.validate(), Err(PackedRTreeError::Corrupted));
+            packed.nearest_n_neighbors(&query, 10);
+            packed.lookup_in_rectangle(&rect);
+        }
+        let packed = PackedRTree::<Point2<f64>>::from_bytes(&data).unwrap();
+        assert_eq!(packed.validate(), Ok(()));
+
+        let empty: RTree<Point2<f64>> = RTree::new();
+        let mut data = Vec::new();
+        empty.write_packed(&mut data).unwrap();
+        let packed: PackedRTree<Point2<f64>> = PackedRTree::from_bytes(&data).unwrap();
+        assert_eq!(packed.size(), 0);
+        assert_eq!(packed.nearest_neighbor(&Point2::new(0.0, 0.0)), None);
+        assert!(packed.lookup_in_rectangle(
+            &BoundingRect::from_point(Point2::new(0.0, 0.0))).is_empty());
+    }
+}
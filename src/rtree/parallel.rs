@@ -0,0 +1,158 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parallel batch queries and bulk loading, requires the `rayon` feature.
+
+use std::sync::Arc;
+use rayon::prelude::*;
+use traits::SpatialObject;
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTree, RTreeNode, RTreeOptions, DirectoryNodeData};
+use super::bulk_load::{bulk_load_node, compare_along_axis, partition_children, root_depth};
+
+// Subtrees with fewer elements are loaded on the current thread
+const PARALLEL_THRESHOLD: usize = 4096;
+
+impl <T> RTree<T>
+    where T: SpatialObject + Sync,
+          T::Point: Sync {
+
+    /// Returns the nearest neighbor of each query point.
+    ///
+    /// The queries are distributed among rayon's thread pool, the result is
+    /// in the same order as `query_points`.
+    /// Requires the `rayon` feature.
+    pub fn par_nearest_neighbors(&self, query_points: &[T::Point]) -> Vec<Option<&T>> {
+        query_points.par_iter().map(|point| self.nearest_neighbor(point)).collect()
+    }
+
+    /// Returns all objects (partially) contained in each query rectangle.
+    ///
+    /// The queries are distributed among rayon's thread pool, the result is
+    /// in the same order as `query_rects`.
+    /// Requires the `rayon` feature.
+    pub fn par_lookup_in_rectangle(&self, query_rects: &[BoundingRect<T::Point>])
+                                   -> Vec<Vec<&T>> {
+        query_rects.par_iter().map(|rect| self.lookup_in_rectangle(rect)).collect()
+    }
+}
+
+impl <T> RTree<T>
+    where T: SpatialObject + Send,
+          T::Point: Send {
+
+    /// Creates an r*-tree containing all given elements, using rayon's thread pool.
+    ///
+    /// The elements are partitioned like in `RTree::bulk_load`, independent
+    /// subtrees are built in parallel and joined under a common root. The
+    /// resulting tree is identical to the one created by `RTree::bulk_load`.
+    /// Requires the `rayon` feature.
+    pub fn par_bulk_load(elements: Vec<T>) -> RTree<T> {
+        RTree::par_bulk_load_with_options(elements, Default::default())
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject + Send,
+          T::Point: Send,
+          A: Aggregate<T>,
+          A::Value: Send {
+
+    /// Creates an r*-tree that caches aggregates of type `A` and contains all
+    /// given elements, using rayon's thread pool.
+    ///
+    /// The resulting tree is identical to the one created by
+    /// `RTree::bulk_load_aggregated`.
+    /// Requires the `rayon` feature.
+    pub fn par_bulk_load_aggregated(elements: Vec<T>) -> RTree<T, A> {
+        RTree::par_bulk_load_with_options(elements, Default::default())
+    }
+
+    #[doc(hidden)]
+    pub fn par_bulk_load_with_options(elements: Vec<T>, options: RTreeOptions<T::Point>)
+                                      -> RTree<T, A> {
+        if elements.is_empty() {
+            return RTree::new_with_options(options);
+        }
+        let size = elements.len();
+        let options = Arc::new(options);
+        let depth = root_depth(size, options.max_size);
+        RTree {
+            root: par_bulk_load_node(elements, depth, &options),
+            size,
+        }
+    }
+}
+
+fn par_sort_along_axis<T>(elements: &mut [T], axis: usize)
+    where T: SpatialObject + Send {
+    elements.par_sort_by(|l, r| compare_along_axis(l, r, axis));
+}
+
+fn par_bulk_load_node<T, A>(elements: Vec<T>, depth: usize,
+                            options: &Arc<RTreeOptions<T::Point>>) -> DirectoryNodeData<T, A>
+    where T: SpatialObject + Send,
+          T::Point: Send,
+          A: Aggregate<T>,
+          A::Value: Send {
+    if depth <= 1 || elements.len() < PARALLEL_THRESHOLD {
+        return bulk_load_node(elements, depth, options);
+    }
+    let children = partition_children(elements, depth, options.max_size, &par_sort_along_axis)
+        .into_par_iter()
+        .map(|group| RTreeNode::DirectoryNode(par_bulk_load_node(group, depth - 1, options)))
+        .collect();
+    DirectoryNodeData::new_parent(Box::new(children), depth, options.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{RTree, Count};
+    use super::super::test::check_tree;
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_par_queries() {
+        let (tree, _) = create_random_tree::<f64>(1000, [2, 2, 4, 4]);
+        let queries = random_points_with_seed::<f64>(500, [8, 8, 1, 1]);
+        let nearest = tree.par_nearest_neighbors(&queries);
+        assert_eq!(nearest.len(), queries.len());
+        for (query, result) in queries.iter().zip(nearest) {
+            assert_eq!(result, tree.nearest_neighbor(query));
+        }
+        let rects: Vec<_> = queries.iter().map(
+            |q| BoundingRect::from_corners(q, &Point2::new(q.x + 0.1, q.y + 0.1))).collect();
+        let found = tree.par_lookup_in_rectangle(&rects);
+        for (rect, result) in rects.iter().zip(found) {
+            assert_eq!(result, tree.lookup_in_rectangle(rect));
+        }
+    }
+
+    #[test]
+    fn test_par_bulk_load() {
+        for &size in &[0, 10, 5000, 50000] {
+            let points = random_points_with_seed::<f64>(size, [6, 2, 8, 3]);
+            let tree = RTree::par_bulk_load(points.clone());
+            check_tree(&tree);
+            let sequential = RTree::bulk_load(points);
+            assert!(tree.iter().eq(sequential.iter()));
+        }
+    }
+
+    #[test]
+    fn test_par_bulk_load_aggregated() {
+        let points = random_points_with_seed::<f64>(20000, [4, 6, 2, 8]);
+        let tree: RTree<_, Count<_>> = RTree::par_bulk_load_aggregated(points.clone());
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.aggregate(), points.len());
+        let rect = BoundingRect::from_corners(&Point2::new(-0.5, -0.5), &Point2::new(0.5, 0.5));
+        assert_eq!(tree.aggregate_in_rectangle(&rect), tree.lookup_in_rectangle(&rect).len());
+    }
+}
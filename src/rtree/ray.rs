@@ -0,0 +1,202 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Ray casting queries on r*-trees.
+
+use smallvec::SmallVec;
+use traits::{SpatialObject, RayIntersect, SpadeFloat};
+use point_traits::PointN;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+type Scalar<T> = <<T as SpatialObject>::Point as PointN>::Scalar;
+type SortedChildren<'a, T, A> = SmallVec<[(&'a RTreeNode<T, A>, Scalar<T>); 8]>;
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: RayIntersect,
+          A: Aggregate<T>,
+          Scalar<T>: SpadeFloat {
+    // Returns all children whose bounding rectangles are hit, sorted front to back
+    fn sorted_by_ray<'b>(&'b self, origin: &T::Point, direction: &T::Point)
+                         -> SortedChildren<'b, T, A> {
+        let mut sorted: SmallVec<[_; 8]> = self.children.iter().filter_map(|child| {
+            child.mbr().ray_intersection(origin, direction).map(|t| (child, t))
+        }).collect();
+        sorted.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+        sorted
+    }
+
+    fn ray_cast<'b>(&'b self, origin: &T::Point, direction: &T::Point,
+                    nearest: &mut Option<(&'b T, Scalar<T>)>) {
+        for (child, t_enter) in self.sorted_by_ray(origin, direction) {
+            if nearest.map(|n| t_enter >= n.1).unwrap_or(false) {
+                // All remaining children are hit behind the nearest hit
+                break;
+            }
+            match *child {
+                RTreeNode::DirectoryNode(ref data) => data.ray_cast(origin, direction, nearest),
+                RTreeNode::Leaf(ref t) => {
+                    if let Some(hit) = t.ray_intersection(origin, direction) {
+                        if nearest.map(|n| hit < n.1).unwrap_or(true) {
+                            *nearest = Some((t, hit));
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn ray_intersections<'b>(&'b self, origin: &T::Point, direction: &T::Point,
+                             result: &mut Vec<(&'b T, Scalar<T>)>) {
+        for (child, _) in self.sorted_by_ray(origin, direction) {
+            match *child {
+                RTreeNode::DirectoryNode(ref data) =>
+                    data.ray_intersections(origin, direction, result),
+                RTreeNode::Leaf(ref t) => {
+                    if let Some(hit) = t.ray_intersection(origin, direction) {
+                        result.push((t, hit));
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: RayIntersect,
+          A: Aggregate<T>,
+          Scalar<T>: SpadeFloat {
+
+    /// Returns the first object hit by a ray.
+    ///
+    /// The ray starts at `origin` and contains all points `origin + direction * t`
+    /// for non negative `t`. Returns the hit object together with its `t`, or `None`
+    /// if the ray misses all objects. Nodes are traversed front to back, nodes that
+    /// lie behind the nearest hit found so far are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use spade::primitives::SimpleCircle;
+    /// use cgmath::Point3;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// tree.insert(SimpleCircle::new(Point3::new(5.0, 0.0, 0.0), 1.0));
+    /// tree.insert(SimpleCircle::new(Point3::new(10.0, 0.0, 0.0), 1.0));
+    /// let (sphere, t) = tree.ray_cast(&Point3::new(0.0, 0.0, 0.0),
+    ///                                 &Point3::new(1.0, 0.0, 0.0)).unwrap();
+    /// assert_eq!(sphere.center, Point3::new(5.0, 0.0, 0.0));
+    /// assert_eq!(t, 4.0);
+    /// # }
+    /// ```
+    pub fn ray_cast(&self, origin: &T::Point, direction: &T::Point) -> Option<(&T, Scalar<T>)> {
+        let mut nearest = None;
+        if self.size > 0 {
+            self.root.ray_cast(origin, direction, &mut nearest);
+        }
+        nearest
+    }
+
+    /// Returns all objects hit by a ray, sorted by their distance from the ray's origin.
+    ///
+    /// Each object is returned together with the `t` of its first hit point
+    /// `origin + direction * t`, see `RTree::ray_cast`.
+    pub fn ray_intersections(&self, origin: &T::Point, direction: &T::Point)
+                             -> Vec<(&T, Scalar<T>)> {
+        let mut result = Vec::new();
+        if self.size > 0 {
+            self.root.ray_intersections(origin, direction, &mut result);
+        }
+        result.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::RTree;
+    use traits::RayIntersect;
+    use primitives::{SimpleCircle, SimpleEdge, SimpleTriangle};
+    use cgmath::{Point2, Point3};
+    use testutils::*;
+
+    #[test]
+    fn test_ray_cast_circles() {
+        let centers = random_points_with_seed::<f64>(500, [1, 4, 1, 5]);
+        let circles: Vec<_> = centers.iter().map(|c| SimpleCircle::new(*c, 0.02)).collect();
+        let mut tree = RTree::new();
+        for circle in &circles {
+            tree.insert(SimpleCircle::new(circle.center, circle.radius));
+        }
+        let origins = random_points_in_range(2.0, 100, [9, 2, 6, 5]);
+        let targets = random_points_with_seed::<f64>(100, [3, 5, 8, 9]);
+        for (origin, target) in origins.iter().zip(targets.iter()) {
+            let direction = Point2::new(target.x - origin.x, target.y - origin.y);
+            let mut expected: Vec<_> = circles.iter()
+                .filter_map(|c| c.ray_intersection(origin, &direction).map(|t| (c.center, t)))
+                .collect();
+            expected.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+            let hits: Vec<_> = tree.ray_intersections(origin, &direction).into_iter()
+                .map(|(c, t)| (c.center, t)).collect();
+            assert_eq!(hits, expected);
+            let first = tree.ray_cast(origin, &direction).map(|(c, t)| (c.center, t));
+            assert_eq!(first, expected.first().cloned());
+        }
+    }
+
+    #[test]
+    fn test_ray_cast_edges_and_triangles() {
+        let mut edges = RTree::new();
+        for x in 1 .. 10 {
+            let x = x as f64;
+            edges.insert(SimpleEdge::new(Point2::new(x, -1.0), Point2::new(x, 1.0)));
+        }
+        let (edge, t) = edges.ray_cast(&Point2::new(0.0, 0.0), &Point2::new(2.0, 0.5)).unwrap();
+        assert_eq!(edge.from, Point2::new(1.0, -1.0));
+        assert_eq!(t, 0.5);
+        assert_eq!(edges.ray_intersections(&Point2::new(0.0, 0.0),
+                                           &Point2::new(1.0, 0.0)).len(), 9);
+        assert!(edges.ray_cast(&Point2::new(0.0, 0.0), &Point2::new(-1.0, 0.0)).is_none());
+        assert!(edges.ray_cast(&Point2::new(0.0, 2.0), &Point2::new(1.0, 0.0)).is_none());
+
+        let mut triangles = RTree::new();
+        triangles.insert(SimpleTriangle::new(Point2::new(2.0, -1.0), Point2::new(4.0, -1.0),
+                                             Point2::new(3.0, 1.0)));
+        let hit = triangles.ray_cast(&Point2::new(0.0, 0.0), &Point2::new(1.0, 0.0));
+        assert_eq!(hit.map(|h| h.1), Some(2.5));
+        let hit = triangles.ray_cast(&Point2::new(3.0, 0.0), &Point2::new(1.0, 0.0));
+        assert_eq!(hit.map(|h| h.1), Some(0.0));
+        assert!(triangles.ray_cast(&Point2::new(0.0, 0.0), &Point2::new(0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn test_ray_cast_3d() {
+        let mut tree = RTree::new();
+        for x in 0 .. 10 {
+            for y in 0 .. 10 {
+                for z in 0 .. 10 {
+                    let center = Point3::new(x as f64, y as f64, z as f64);
+                    tree.insert(SimpleCircle::new(center, 0.25));
+                }
+            }
+        }
+        let origin = Point3::new(-1.0, 3.0, 4.0);
+        let (sphere, t) = tree.ray_cast(&origin, &Point3::new(1.0, 0.0, 0.0)).unwrap();
+        assert_eq!(sphere.center, Point3::new(0.0, 3.0, 4.0));
+        assert_eq!(t, 0.75);
+        let hits = tree.ray_intersections(&origin, &Point3::new(1.0, 0.0, 0.0));
+        let xs: Vec<_> = hits.iter().map(|h| h.0.center.x).collect();
+        assert_eq!(xs, (0 .. 10).map(|x| x as f64).collect::<Vec<_>>());
+        let diagonal = tree.ray_intersections(&Point3::new(-1.0, -1.0, -1.0),
+                                              &Point3::new(1.0, 1.0, 1.0));
+        assert_eq!(diagonal.len(), 10);
+        assert!(tree.ray_cast(&origin, &Point3::new(-1.0, 0.0, 0.0)).is_none());
+    }
+}
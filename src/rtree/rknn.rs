@@ -0,0 +1,266 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reverse nearest neighbor queries on two dimensional r*-trees.
+
+use std::f64::consts::PI;
+use std::ptr;
+use num::{cast, zero};
+use traits::{HasPosition, SpadeFloat};
+use point_traits::{PointN, PointNExtensions, TwoDimensional};
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+type Scalar<T> = <<T as HasPosition>::Point as PointN>::Scalar;
+
+// Divides the plane around a query point into six sectors of 60 degrees.
+//
+// If two objects o and p lie in the same sector and o is closer to the query
+// point than p, o is also closer to p than the query point (unless o coincides
+// with the query point). Thus, only the n closest objects of each sector can have
+// the query point among their n nearest neighbors.
+struct Sectors<V>
+    where V: TwoDimensional {
+    origin: V,
+    // directions[i] and directions[i + 1] bound sector i
+    directions: [V; 7],
+}
+
+fn cross<V: TwoDimensional>(first: &V, second: &V) -> V::Scalar {
+    first.nth(0).clone() * second.nth(1).clone() - first.nth(1).clone() * second.nth(0).clone()
+}
+
+impl <V> Sectors<V>
+    where V: TwoDimensional,
+          V::Scalar: SpadeFloat {
+    fn new(origin: V) -> Sectors<V> {
+        let direction = |i: usize| {
+            let angle = PI / 3.0 * i as f64;
+            let mut result = V::new();
+            *result.nth_mut(0) = cast(angle.cos()).unwrap();
+            *result.nth_mut(1) = cast(angle.sin()).unwrap();
+            result
+        };
+        Sectors {
+            origin,
+            directions: [direction(0), direction(1), direction(2), direction(3),
+                         direction(4), direction(5), direction(0)],
+        }
+    }
+
+    // Returns the sector of a point that does not coincide with the origin.
+    // Sector i contains its lower bounding ray but not its upper bounding ray.
+    fn sector(&self, point: &V) -> usize {
+        let v = point.sub(&self.origin);
+        (0 .. 6).find(|&i| cross(&self.directions[i], &v) >= zero()
+                      && cross(&self.directions[i + 1], &v) < zero()).unwrap_or(0)
+    }
+
+    // Returns true if a rectangle intersects the closure of a sector
+    fn intersects(&self, sector: usize, rect: &BoundingRect<V>) -> bool {
+        let (lower, upper) = (rect.lower(), rect.upper());
+        for corner in 0 .. 4 {
+            let mut point = lower.clone();
+            if corner & 1 != 0 {
+                *point.nth_mut(0) = *upper.nth(0);
+            }
+            if corner & 2 != 0 {
+                *point.nth_mut(1) = *upper.nth(1);
+            }
+            let v = point.sub(&self.origin);
+            if cross(&self.directions[sector], &v) >= zero()
+                && cross(&self.directions[sector + 1], &v) <= zero() {
+                return true;
+            }
+        }
+        // Otherwise, a bounding ray must cross the rectangle
+        rect.ray_intersection(&self.origin, &self.directions[sector]).is_some()
+            || rect.ray_intersection(&self.origin, &self.directions[sector + 1]).is_some()
+    }
+}
+
+// The candidates of a single sector, sorted by their squared distance to the query
+// point. Contains the n closest objects and all objects tied with the n-th closest.
+struct SectorCandidates<'a, T>
+    where T: HasPosition + 'a {
+    candidates: Vec<(&'a T, Scalar<T>)>,
+}
+
+impl <'a, T> SectorCandidates<'a, T>
+    where T: HasPosition + 'a {
+    fn accepts(&self, distance2: &Scalar<T>, n: usize) -> bool {
+        self.candidates.len() < n || *distance2 <= self.candidates[n - 1].1
+    }
+
+    fn insert(&mut self, object: &'a T, distance2: Scalar<T>, n: usize) {
+        let index = self.candidates.iter().position(|c| c.1 > distance2)
+            .unwrap_or(self.candidates.len());
+        self.candidates.insert(index, (object, distance2));
+        if self.candidates.len() > n {
+            let max_distance2 = self.candidates[n - 1].1.clone();
+            while self.candidates.last().map(|c| c.1 > max_distance2).unwrap_or(false) {
+                self.candidates.pop();
+            }
+        }
+    }
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: HasPosition,
+          T::Point: TwoDimensional,
+          Scalar<T>: SpadeFloat,
+          A: Aggregate<T> {
+    fn reverse_nearest_n_candidates<'b>(&'b self, sectors: &Sectors<T::Point>, n: usize,
+                                        candidates: &mut [SectorCandidates<'b, T>],
+                                        coincident: &mut Vec<&'b T>) {
+        let mut sorted: Vec<_> = self.children.iter()
+            .map(|child| (child, child.mbr().min_dist2(&sectors.origin)))
+            .collect();
+        sorted.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+        for (child, min_dist2) in sorted {
+            match *child {
+                RTreeNode::DirectoryNode(ref data) => {
+                    let mbr = data.mbr();
+                    if (0 .. 6).any(|sector| candidates[sector].accepts(&min_dist2, n)
+                                    && sectors.intersects(sector, &mbr)) {
+                        data.reverse_nearest_n_candidates(sectors, n, candidates, coincident);
+                    }
+                },
+                RTreeNode::Leaf(ref t) => {
+                    if min_dist2 == zero() {
+                        coincident.push(t);
+                        continue;
+                    }
+                    let sector = sectors.sector(&t.position());
+                    if candidates[sector].accepts(&min_dist2, n) {
+                        candidates[sector].insert(t, min_dist2, n);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: HasPosition,
+          T::Point: TwoDimensional,
+          Scalar<T>: SpadeFloat,
+          A: Aggregate<T> {
+
+    /// Returns all objects that have a query point among their n nearest neighbors.
+    ///
+    /// The query point is among an object's n nearest neighbors if less than `n`
+    /// other objects are strictly closer to that object than the query point.
+    /// The returned objects are sorted by their distance to the query point.
+    ///
+    /// Candidates are found with the six-region approach: The plane around the query
+    /// point is divided into six sectors of 60 degrees, only the n closest objects
+    /// of each sector can be reverse nearest neighbors. Each candidate is then
+    /// verified with a circle query.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// tree.insert(Point2::new(0.0, 0.0));
+    /// tree.insert(Point2::new(1.0, 0.0));
+    /// tree.insert(Point2::new(5.0, 0.0));
+    /// // (5, 0) is closer to (3.5, 0) than to (1, 0)
+    /// let result = tree.reverse_nearest_n_neighbors(&Point2::new(3.5, 0.0), 1);
+    /// assert_eq!(result, vec![&Point2::new(5.0, 0.0)]);
+    /// # }
+    /// ```
+    pub fn reverse_nearest_n_neighbors(&self, query_point: &T::Point, n: usize) -> Vec<&T> {
+        if self.size == 0 || n == 0 {
+            return Vec::new();
+        }
+        let sectors = Sectors::new(query_point.clone());
+        let mut candidates: Vec<_> = (0 .. 6)
+            .map(|_| SectorCandidates { candidates: Vec::with_capacity(n + 1) })
+            .collect();
+        // Objects at the query point are always reverse nearest neighbors
+        let mut result = Vec::new();
+        self.root.reverse_nearest_n_candidates(&sectors, n, &mut candidates, &mut result);
+
+        let mut verified: Vec<_> = result.drain(..).map(|t| (t, zero())).collect();
+        let mut closer = Vec::new();
+        for (candidate, distance2) in candidates.into_iter().flat_map(|c| c.candidates) {
+            closer.clear();
+            self.lookup_in_circle_into(&mut closer, &candidate.position(), &distance2);
+            if closer.iter().filter(|t| !ptr::eq(**t, candidate)).count() < n {
+                verified.push((candidate, distance2));
+            }
+        }
+        verified.sort_by(|l, r| l.1.partial_cmp(&r.1).unwrap());
+        verified.into_iter().map(|v| v.0).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+    use super::super::RTree;
+    use traits::SpatialObject;
+    use cgmath::Point2;
+    use testutils::*;
+
+    fn brute_force<'a>(points: &'a [Point2<f64>], query: &Point2<f64>, n: usize)
+                       -> Vec<&'a Point2<f64>> {
+        let mut result: Vec<_> = points.iter().filter(|p| {
+            let distance2 = p.distance2(query);
+            points.iter().filter(|o| !ptr::eq(*o, *p) && o.distance2(p) < distance2).count() < n
+        }).collect();
+        result.sort_by(|l, r| l.distance2(query).partial_cmp(&r.distance2(query)).unwrap());
+        result
+    }
+
+    #[test]
+    fn test_reverse_nearest_n_neighbors() {
+        let (tree, points) = create_random_tree::<f64>(300, [5, 3, 2, 9]);
+        let queries = random_points_in_range(1.2, 20, [7, 7, 1, 3]);
+        for query in &queries {
+            for n in 1 .. 5 {
+                let result = tree.reverse_nearest_n_neighbors(query, n);
+                assert_eq!(result, brute_force(&points, query, n));
+            }
+        }
+        assert!(tree.reverse_nearest_n_neighbors(&queries[0], 0).is_empty());
+    }
+
+    #[test]
+    fn test_reverse_nearest_n_neighbors_on_grid() {
+        let mut tree = RTree::new();
+        let mut points = Vec::new();
+        for x in 0 .. 20 {
+            for y in 0 .. 20 {
+                let point = Point2::new(x as f64, y as f64);
+                tree.insert(point);
+                points.push(point);
+            }
+        }
+        // Many ties and queries located at objects
+        let queries = [Point2::new(5.0, 5.0), Point2::new(5.5, 5.0), Point2::new(5.5, 5.5),
+                       Point2::new(-3.0, 7.0), Point2::new(0.0, 19.0)];
+        for query in &queries {
+            for n in 1 .. 6 {
+                let result = tree.reverse_nearest_n_neighbors(query, n);
+                let expected = brute_force(&points, query, n);
+                assert_eq!(result.len(), expected.len());
+                for point in &expected {
+                    assert!(result.contains(point));
+                }
+            }
+        }
+        assert!(RTree::<Point2<f64>>::new().reverse_nearest_n_neighbors(&queries[0], 1).is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Custom r*-tree traversals.
+
+use traits::SpatialObject;
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+/// Selects the elements of a custom r*-tree query.
+///
+/// A selection function decides which parts of the tree are visited during a
+/// traversal. A directory node is only visited if `should_unpack_parent` returns
+/// `true` for its bounding rectangle, an element is only selected if
+/// `should_unpack_leaf` returns `true`. Since a directory node's bounding rectangle
+/// contains all elements below it, `should_unpack_parent` must return `true` if
+/// any of these elements could be selected.
+///
+/// Selection functions are used by `RTree::locate_with_selection_function`,
+/// `RTree::locate_with_selection_function_mut`,
+/// `RTree::update_with_selection_function` and
+/// `RTree::remove_with_selection_function`.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::{RTree, SelectionFunction};
+/// use spade::BoundingRect;
+/// use cgmath::Point2;
+///
+/// // Selects all points with a positive x coordinate.
+/// struct RightHalfPlane;
+///
+/// impl SelectionFunction<Point2<f32>> for RightHalfPlane {
+///     fn should_unpack_parent(&self, mbr: &BoundingRect<Point2<f32>>) -> bool {
+///         mbr.upper().x > 0.0
+///     }
+///
+///     fn should_unpack_leaf(&self, point: &Point2<f32>) -> bool {
+///         point.x > 0.0
+///     }
+/// }
+///
+/// # fn main() {
+/// let mut tree = RTree::new();
+/// tree.insert(Point2::new(-1.0, 0.0));
+/// tree.insert(Point2::new(2.0, 1.0));
+/// let selected = tree.locate_with_selection_function(&RightHalfPlane);
+/// assert_eq!(selected, vec![&Point2::new(2.0, 1.0)]);
+/// # }
+/// ```
+pub trait SelectionFunction<T>
+    where T: SpatialObject {
+    /// Returns `true` if a directory node with the given bounding rectangle
+    /// may contain selected elements.
+    fn should_unpack_parent(&self, parent_mbr: &BoundingRect<T::Point>) -> bool;
+
+    /// Returns `true` if an element is selected.
+    fn should_unpack_leaf(&self, leaf: &T) -> bool;
+}
+
+// Selects all objects whose bounding rectangles intersect a query rectangle
+pub struct SelectInRectangle<'a, T>
+    where T: SpatialObject + 'a {
+    pub query_rect: &'a BoundingRect<T::Point>,
+}
+
+impl <'a, T> SelectionFunction<T> for SelectInRectangle<'a, T>
+    where T: SpatialObject {
+    fn should_unpack_parent(&self, parent_mbr: &BoundingRect<T::Point>) -> bool {
+        parent_mbr.intersects(self.query_rect)
+    }
+
+    fn should_unpack_leaf(&self, leaf: &T) -> bool {
+        leaf.mbr().intersects(self.query_rect)
+    }
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn locate_with_selection_function<'b, S>(&'b self, selection: &S,
+                                             result: &mut Vec<&'b T>)
+        where S: SelectionFunction<T> {
+        for child in self.children.iter() {
+            match *child {
+                RTreeNode::DirectoryNode(ref data) => {
+                    if selection.should_unpack_parent(&data.mbr()) {
+                        data.locate_with_selection_function(selection, result);
+                    }
+                },
+                RTreeNode::Leaf(ref t) => {
+                    if selection.should_unpack_leaf(t) {
+                        result.push(t);
+                    }
+                },
+            }
+        }
+    }
+
+    fn locate_with_selection_function_mut<'b, S>(&'b mut self, selection: &S,
+                                                 result: &mut Vec<&'b mut T>)
+        where S: SelectionFunction<T> {
+        for child in self.children.iter_mut() {
+            match *child {
+                RTreeNode::DirectoryNode(ref mut data) => {
+                    if selection.should_unpack_parent(&data.mbr()) {
+                        data.locate_with_selection_function_mut(selection, result);
+                    }
+                },
+                RTreeNode::Leaf(ref mut t) => {
+                    if selection.should_unpack_leaf(t) {
+                        result.push(t);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Returns all elements selected by a selection function.
+    ///
+    /// Only directory nodes for which `selection.should_unpack_parent` returns
+    /// `true` are visited.
+    pub fn locate_with_selection_function<S>(&self, selection: &S) -> Vec<&T>
+        where S: SelectionFunction<T> {
+        let mut result = Vec::new();
+        if self.size > 0 && selection.should_unpack_parent(&self.root.mbr()) {
+            self.root.locate_with_selection_function(selection, &mut result);
+        }
+        result
+    }
+
+    /// Removes and returns all elements selected by a selection function.
+    ///
+    /// Underfull nodes are condensed, see `RTree::drain_with_selection`.
+    pub fn remove_with_selection_function<S>(&mut self, selection: &S) -> Vec<T>
+        where S: SelectionFunction<T> {
+        self.drain_with_selection(selection).collect()
+    }
+}
+
+impl <T> RTree<T>
+    where T: SpatialObject {
+    /// Returns mutable references to all elements selected by a selection function.
+    ///
+    /// *Do not change the objects' minimal bounding boxes*, use
+    /// `update_with_selection_function` instead. Only available for trees
+    /// without aggregates since modified objects would leave cached aggregates stale.
+    pub fn locate_with_selection_function_mut<S>(&mut self, selection: &S) -> Vec<&mut T>
+        where S: SelectionFunction<T> {
+        let mut result = Vec::new();
+        if self.size > 0 && selection.should_unpack_parent(&self.root.mbr()) {
+            self.root.locate_with_selection_function_mut(selection, &mut result);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SelectionFunction;
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    struct InCircle {
+        center: Point2<f64>,
+        radius2: f64,
+    }
+
+    impl SelectionFunction<Point2<f64>> for InCircle {
+        fn should_unpack_parent(&self, mbr: &BoundingRect<Point2<f64>>) -> bool {
+            mbr.min_dist2(&self.center) <= self.radius2
+        }
+
+        fn should_unpack_leaf(&self, point: &Point2<f64>) -> bool {
+            let (dx, dy) = (point.x - self.center.x, point.y - self.center.y);
+            dx * dx + dy * dy <= self.radius2
+        }
+    }
+
+    #[test]
+    fn test_locate_with_selection_function() {
+        let (mut tree, points) = create_random_tree::<f64>(1000, [3, 2, 1, 0]);
+        let selection = InCircle { center: Point2::new(0.2, -0.3), radius2: 0.25 };
+        let expected = points.iter().filter(|p| selection.should_unpack_leaf(p)).count();
+        assert!(expected > 0);
+        assert_eq!(tree.locate_with_selection_function(&selection).len(), expected);
+        for point in tree.locate_with_selection_function_mut(&selection) {
+            assert!(selection.should_unpack_leaf(point));
+        }
+        let removed = tree.remove_with_selection_function(&selection);
+        assert_eq!(removed.len(), expected);
+        assert_eq!(tree.size(), points.len() - expected);
+        assert_eq!(tree.iter().count(), tree.size());
+        assert!(tree.locate_with_selection_function(&selection).is_empty());
+        for point in &points {
+            assert_eq!(tree.lookup(point).is_some(), !selection.should_unpack_leaf(point));
+        }
+    }
+
+    #[test]
+    fn test_remove_all_with_selection_function() {
+        let (mut tree, points) = create_random_tree::<f64>(200, [5, 4, 3, 2]);
+        let selection = InCircle { center: Point2::new(0.0, 0.0), radius2: 10.0 };
+        assert_eq!(tree.remove_with_selection_function(&selection).len(), points.len());
+        assert_eq!(tree.size(), 0);
+        assert!(tree.mbr().is_none());
+        tree.insert(Point2::new(0.5, 0.5));
+        assert_eq!(tree.nearest_neighbor(&Point2::new(0.0, 0.0)), Some(&Point2::new(0.5, 0.5)));
+    }
+}
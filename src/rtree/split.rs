@@ -0,0 +1,548 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Node split strategies of r*-trees.
+
+use std::cmp::{self, Ordering};
+use std::fmt;
+use num::{zero, one, Signed};
+use traits::SpatialObject;
+use point_traits::PointN;
+use boundingvolume::BoundingRect;
+use super::{Aggregate, RTreeNode, DirectoryNodeData};
+
+/// Determines how an r-tree resolves overflowing nodes.
+///
+/// A split strategy divides the children of an overflowing node into two
+/// groups, the second group is moved into a newly created sibling node.
+/// Spade provides the `RStar`, `Quadratic`, `Linear` and `Hilbert` strategies
+/// for all point types. The r*-tree strategy yields the best query performance,
+/// the other strategies trade some query performance for faster insertion.
+/// The strategy is chosen with `RTreeOptions::set_split_strategy`, r-trees
+/// use the r*-tree strategy by default.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::rtree::{RTree, RTreeOptions, SplitNode, SplitStrategy, Quadratic};
+/// use cgmath::Point2;
+///
+/// // Moves the children with the largest x coordinates into the new node
+/// #[derive(Debug)]
+/// struct SplitAlongX;
+///
+/// impl SplitStrategy<Point2<f32>> for SplitAlongX {
+///     fn split(&self, node: &SplitNode<Point2<f32>>) -> Vec<usize> {
+///         let mut indices: Vec<_> = (0 .. node.len()).collect();
+///         indices.sort_by(|&l, &r| node.mbr(l).lower().x.partial_cmp(&node.mbr(r).lower().x)
+///                         .unwrap());
+///         indices.split_off(node.len() / 2)
+///     }
+/// }
+///
+/// # fn main() {
+/// let mut tree: RTree<Point2<f32>> = RTreeOptions::new()
+///     .set_split_strategy(Quadratic)
+///     .build();
+/// tree.insert(Point2::new(0.0, 1.0));
+///
+/// let mut tree: RTree<Point2<f32>> = RTreeOptions::new()
+///     .set_split_strategy(SplitAlongX)
+///     .build();
+/// for x in 0 .. 100 {
+///     tree.insert(Point2::new(x as f32, 0.0));
+/// }
+/// assert!(tree.validate().is_ok());
+/// # }
+/// ```
+pub trait SplitStrategy<V: PointN>: fmt::Debug + Send + Sync {
+    /// Splits the children of an overflowing node into two groups.
+    ///
+    /// Returns the indices of all children that are moved into the new sibling
+    /// node. Both groups must not be empty and should contain at least
+    /// `node.min_size()` children.
+    fn split(&self, node: &SplitNode<V>) -> Vec<usize>;
+
+    /// Returns true if overflowing nodes try to reinsert some of their children
+    /// before they are split.
+    ///
+    /// Trees using such a strategy also consider the overlap increase when
+    /// choosing the subtree of an inserted object, as r*-trees do.
+    fn uses_reinsertion(&self) -> bool {
+        false
+    }
+}
+
+/// The r*-tree's split.
+///
+/// Chooses the split axis with the smallest margin and the split with the least
+/// overlap along that axis. Overflowing nodes try to reinsert some of their
+/// children before they are split.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RStar;
+
+/// Guttman's quadratic split.
+///
+/// Starts with the two children that would waste the most area if grouped
+/// together and assigns the remaining children by their area increase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quadratic;
+
+/// Guttman's linear split.
+///
+/// Starts with the two children that are separated most along any axis, the
+/// remaining children are assigned in linear time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Linear;
+
+/// Orders the children along a Hilbert curve and splits them at the position
+/// with the least overlap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hilbert;
+
+/// The children of an overflowing r-tree node, see `SplitStrategy`.
+///
+/// Each child is described by its bounding rectangle.
+#[derive(Clone, Debug)]
+pub struct SplitNode<V: PointN> {
+    children: Vec<BoundingRect<V>>,
+    min_size: usize,
+}
+
+impl <V> SplitNode<V>
+    where V: PointN {
+    fn new<T, A>(children: &[RTreeNode<T, A>], min_size: usize) -> SplitNode<V>
+        where T: SpatialObject<Point=V>,
+              A: Aggregate<T> {
+        SplitNode {
+            children: children.iter().map(|child| child.mbr()).collect(),
+            min_size,
+        }
+    }
+
+    /// Returns the number of children.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns true if the node has no children.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the tree's minimum node size.
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// Returns the bounding rectangle of a child.
+    pub fn mbr(&self, child: usize) -> &BoundingRect<V> {
+        &self.children[child]
+    }
+
+    // Returns the minimum group size that can be satisfied by a split
+    fn group_size(&self) -> usize {
+        cmp::max(1, cmp::min(self.min_size, self.len() / 2))
+    }
+
+    fn bounds(&self, indices: &[usize]) -> BoundingRect<V> {
+        let mut result = self.children[indices[0]].clone();
+        for &index in &indices[1 ..] {
+            result.add_rect(&self.children[index]);
+        }
+        result
+    }
+
+    // Returns the children's indices sorted by their lower coordinate along an axis
+    fn sorted_along(&self, axis: usize) -> Vec<usize> {
+        let mut result: Vec<_> = (0 .. self.len()).collect();
+        result.sort_by(|&l, &r| self.children[l].lower().nth(axis)
+                       .partial_cmp(self.children[r].lower().nth(axis))
+                       .unwrap_or(Ordering::Equal));
+        result
+    }
+
+    // Returns the bounds of both groups for all valid split positions of the
+    // ordered children
+    fn candidate_splits(&self, order: &[usize]) -> Vec<(usize, BoundingRect<V>, BoundingRect<V>)> {
+        let group_size = self.group_size();
+        (group_size .. order.len() - group_size + 1).map(|k| {
+            let (first, second) = order.split_at(k);
+            (k, self.bounds(first), self.bounds(second))
+        }).collect()
+    }
+
+    // Returns the split position of the ordered children that minimizes the
+    // overlap and the area of both groups
+    fn best_split_index(&self, order: &[usize]) -> usize {
+        let mut best = (zero(), zero());
+        let mut best_index = self.group_size();
+        for (k, first, second) in self.candidate_splits(order) {
+            let overlap_value = first.intersect(&second).area();
+            let area_value = first.area() + second.area();
+            let new_best = (overlap_value, area_value);
+            if new_best < best || k == self.group_size() {
+                best = new_best;
+                best_index = k;
+            }
+        }
+        best_index
+    }
+}
+
+impl <V> SplitStrategy<V> for RStar
+    where V: PointN {
+    fn split(&self, node: &SplitNode<V>) -> Vec<usize> {
+        // Choose the axis with the smallest margin
+        let mut best_goodness = zero();
+        let mut best_axis = 0;
+        for axis in 0 .. V::dimensions() {
+            let order = node.sorted_along(axis);
+            for (_, first, second) in node.candidate_splits(&order) {
+                let margin_value = first.half_margin() + second.half_margin();
+                if best_goodness > margin_value || axis == 0 {
+                    best_axis = axis;
+                    best_goodness = margin_value;
+                }
+            }
+        }
+        let mut order = node.sorted_along(best_axis);
+        let index = node.best_split_index(&order);
+        order.split_off(index)
+    }
+
+    fn uses_reinsertion(&self) -> bool {
+        true
+    }
+}
+
+impl <V> SplitStrategy<V> for Quadratic
+    where V: PointN {
+    fn split(&self, node: &SplitNode<V>) -> Vec<usize> {
+        let (first, second) = quadratic_seeds(node);
+        distribute(node, first, second, true)
+    }
+}
+
+impl <V> SplitStrategy<V> for Linear
+    where V: PointN {
+    fn split(&self, node: &SplitNode<V>) -> Vec<usize> {
+        let (first, second) = linear_seeds(node);
+        distribute(node, first, second, false)
+    }
+}
+
+impl <V> SplitStrategy<V> for Hilbert
+    where V: PointN {
+    fn split(&self, node: &SplitNode<V>) -> Vec<usize> {
+        let indices: Vec<_> = (0 .. node.len()).collect();
+        let bounds = node.bounds(&indices);
+        let mut keyed: Vec<_> = indices.into_iter()
+            .map(|index| (hilbert_index(&node.children[index].center(), &bounds), index))
+            .collect();
+        keyed.sort_by_key(|&(key, _)| key);
+        let mut order: Vec<_> = keyed.into_iter().map(|(_, index)| index).collect();
+        let index = node.best_split_index(&order);
+        order.split_off(index)
+    }
+}
+
+// Returns the area increase of `mbr` when `other` is added
+fn enlargement<V>(mbr: &BoundingRect<V>, other: &BoundingRect<V>) -> V::Scalar
+    where V: PointN {
+    let mut union = mbr.clone();
+    union.add_rect(other);
+    union.area() - mbr.area()
+}
+
+// Returns the position of a point along a Hilbert curve that fills `bounds`
+fn hilbert_index<V>(point: &V, bounds: &BoundingRect<V>) -> u64
+    where V: PointN {
+    let dimensions = V::dimensions();
+    let bits = match 64 / dimensions {
+        0 => 1,
+        bits if bits > 16 => 16,
+        bits => bits,
+    };
+    let two = one::<V::Scalar>() + one();
+    // Quantize the point by bisecting the bounds
+    let mut axes: Vec<u64> = (0 .. dimensions).map(|axis| {
+        let mut lower = bounds.lower().nth(axis).clone();
+        let mut upper = bounds.upper().nth(axis).clone();
+        let value = point.nth(axis);
+        let mut result = 0;
+        for _ in 0 .. bits {
+            let mid = lower.clone() + (upper.clone() - lower.clone()) / two.clone();
+            result <<= 1;
+            if *value >= mid {
+                result |= 1;
+                lower = mid;
+            } else {
+                upper = mid;
+            }
+        }
+        result
+    }).collect();
+    // Convert the axes into the transposed Hilbert index (see J. Skilling,
+    // "Programming the Hilbert curve")
+    let mut q = 1 << (bits - 1);
+    while q > 1 {
+        let p = q - 1;
+        for i in 0 .. dimensions {
+            if axes[i] & q != 0 {
+                axes[0] ^= p;
+            } else {
+                let t = (axes[0] ^ axes[i]) & p;
+                axes[0] ^= t;
+                axes[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+    for i in 1 .. dimensions {
+        axes[i] ^= axes[i - 1];
+    }
+    let mut t = 0;
+    q = 1 << (bits - 1);
+    while q > 1 {
+        if axes[dimensions - 1] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    // Interleave the transposed index
+    let mut result = 0;
+    for bit in (0 .. bits).rev() {
+        for axis in &axes {
+            result = (result << 1) | (((axis ^ t) >> bit) & 1);
+        }
+    }
+    result
+}
+
+// Returns the pair of children whose common bounding rectangle wastes the most area
+fn quadratic_seeds<V>(node: &SplitNode<V>) -> (usize, usize)
+    where V: PointN {
+    let mut best = (0, 1);
+    let mut max_waste = zero();
+    for (i, first) in node.children.iter().enumerate() {
+        for (j, second) in node.children.iter().enumerate().skip(i + 1) {
+            let waste = enlargement(first, second) - second.area();
+            if waste > max_waste || (i, j) == (0, 1) {
+                max_waste = waste;
+                best = (i, j);
+            }
+        }
+    }
+    best
+}
+
+// Returns the pair of children with the greatest normalized separation along any axis
+fn linear_seeds<V>(node: &SplitNode<V>) -> (usize, usize)
+    where V: PointN {
+    let mut best = (0, 1);
+    let mut max_separation = zero();
+    for axis in 0 .. V::dimensions() {
+        let lower = |index: usize| node.children[index].lower().nth(axis).clone();
+        let upper = |index: usize| node.children[index].upper().nth(axis).clone();
+        let compare = |l: &V::Scalar, r: &V::Scalar| l.partial_cmp(r).unwrap_or(Ordering::Equal);
+        let indices = 0 .. node.len();
+        let highest_lower = indices.clone().max_by(|&l, &r| compare(&lower(l), &lower(r))).unwrap();
+        let lowest_upper = indices.clone().filter(|&index| index != highest_lower)
+            .min_by(|&l, &r| compare(&upper(l), &upper(r))).unwrap();
+        let min_lower = indices.clone().map(&lower).min_by(&compare).unwrap();
+        let max_upper = indices.map(&upper).max_by(&compare).unwrap();
+        let width = max_upper - min_lower;
+        if width <= zero() {
+            continue;
+        }
+        let separation = (lower(highest_lower) - upper(lowest_upper)) / width;
+        if separation > max_separation {
+            max_separation = separation;
+            best = (highest_lower, lowest_upper);
+        }
+    }
+    best
+}
+
+// Distributes the children among two groups, starting with a seed for each
+// group, and returns the second group. The remaining children are assigned to
+// the group whose area increases least, the quadratic variant assigns the
+// child with the strongest preference first.
+fn distribute<V>(node: &SplitNode<V>, first_seed: usize, second_seed: usize, quadratic: bool)
+                 -> Vec<usize>
+    where V: PointN {
+    let group_size = node.group_size();
+    let mut remaining: Vec<_> = (0 .. node.len())
+        .filter(|&index| index != first_seed && index != second_seed)
+        .collect();
+    let mut first_len = 1;
+    let mut second = vec![second_seed];
+    let mut first_mbr = node.children[first_seed].clone();
+    let mut second_mbr = node.children[second_seed].clone();
+    while !remaining.is_empty() {
+        if first_len + remaining.len() <= group_size {
+            break;
+        }
+        if second.len() + remaining.len() <= group_size {
+            second.append(&mut remaining);
+            break;
+        }
+        let mut next = 0;
+        if quadratic {
+            let mut max_preference = zero();
+            for (index, &child) in remaining.iter().enumerate() {
+                let mbr = &node.children[child];
+                let preference = (enlargement(&first_mbr, mbr)
+                                  - enlargement(&second_mbr, mbr)).abs();
+                if preference > max_preference || index == 0 {
+                    max_preference = preference;
+                    next = index;
+                }
+            }
+        }
+        let child = remaining.swap_remove(next);
+        let mbr = &node.children[child];
+        let first_enlargement = enlargement(&first_mbr, mbr);
+        let second_enlargement = enlargement(&second_mbr, mbr);
+        let to_first = if first_enlargement != second_enlargement {
+            first_enlargement < second_enlargement
+        } else if first_mbr.area() != second_mbr.area() {
+            first_mbr.area() < second_mbr.area()
+        } else {
+            first_len <= second.len()
+        };
+        if to_first {
+            first_mbr.add_rect(mbr);
+            first_len += 1;
+        } else {
+            second_mbr.add_rect(mbr);
+            second.push(child);
+        }
+    }
+    second
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    // Splits this overflowing node and returns the newly created sibling
+    pub(crate) fn split(&mut self) -> RTreeNode<T, A> {
+        assert!(self.children.len() >= 2);
+        let node = SplitNode::new(&self.children, self.options.min_size);
+        let mut moved = vec![false; self.children.len()];
+        for index in self.options.split_strategy.split(&node) {
+            assert!(!moved[index], "split strategy returned child {} twice", index);
+            moved[index] = true;
+        }
+        assert!(moved.iter().any(|&m| m) && moved.iter().any(|&m| !m),
+                "split strategy returned an empty group");
+        let mut offsplit = Vec::new();
+        let children = ::std::mem::take(&mut *self.children);
+        for (child, moved) in children.into_iter().zip(moved) {
+            if moved {
+                offsplit.push(child);
+            } else {
+                self.children.push(child);
+            }
+        }
+        let result = RTreeNode::DirectoryNode(DirectoryNodeData::new_parent(
+            Box::new(offsplit), self.depth, self.options.clone()));
+        self.update_mbr();
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RStar, Quadratic, Linear, Hilbert, hilbert_index};
+    use super::super::{RTree, RTreeOptions};
+    use super::super::test::check_tree;
+    use boundingvolume::BoundingRect;
+    use point_traits::PointN;
+    use cgmath::Point2;
+    use testutils::*;
+
+    fn options_with_all_strategies<V: PointN>() -> Vec<RTreeOptions<V>> {
+        vec![RTreeOptions::new().set_split_strategy(RStar),
+             RTreeOptions::new().set_split_strategy(Quadratic),
+             RTreeOptions::new().set_split_strategy(Linear),
+             RTreeOptions::new().set_split_strategy(Hilbert)]
+    }
+
+    #[test]
+    fn test_split_strategies() {
+        let points = random_points_with_seed::<f64>(1000, [8, 2, 1, 9]);
+        let queries = random_points_with_seed::<f64>(100, [1, 9, 2, 8]);
+        let reference = RTree::bulk_load(points.clone());
+        for options in options_with_all_strategies() {
+            let mut tree = options.build();
+            for point in &points {
+                tree.insert(*point);
+            }
+            check_tree(&tree);
+            for query in &queries {
+                assert_eq!(tree.nearest_neighbor(query), reference.nearest_neighbor(query));
+            }
+            for point in &points[.. 500] {
+                assert!(tree.remove(point));
+            }
+            check_tree(&tree);
+            for point in &points[500 ..] {
+                assert!(tree.contains(point));
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_strategies_with_integral_points() {
+        for options in options_with_all_strategies() {
+            let mut tree = options.set_max_size(8).set_min_size(3).build();
+            for x in 0 .. 20 {
+                for y in 0 .. 20 {
+                    tree.insert(Point2::new(x, y * 3));
+                }
+            }
+            check_tree(&tree);
+            assert_eq!(tree.size(), 400);
+        }
+    }
+
+    #[test]
+    fn test_options_equality() {
+        let options: Vec<RTreeOptions<Point2<f64>>> = options_with_all_strategies();
+        for (i, first) in options.iter().enumerate() {
+            for (j, second) in options.iter().enumerate() {
+                assert_eq!(first == second, i == j);
+            }
+        }
+        assert_eq!(RTreeOptions::new(), options[0]);
+        assert!(RTreeOptions::new().set_max_size(8) != options[0]);
+    }
+
+    #[test]
+    fn test_hilbert_index() {
+        let bounds = BoundingRect::from_corners(&Point2::new(0.0, 0.0), &Point2::new(8.0, 8.0));
+        let mut cells = Vec::new();
+        for x in 0i32 .. 8 {
+            for y in 0i32 .. 8 {
+                let center = Point2::new(x as f64 + 0.5, y as f64 + 0.5);
+                cells.push((hilbert_index(&center, &bounds), x, y));
+            }
+        }
+        cells.sort();
+        // Cells that are adjacent on the curve must be adjacent in space
+        for pair in cells.windows(2) {
+            let (_, x0, y0) = pair[0];
+            let (_, x1, y1) = pair[1];
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structural statistics of r*-trees.
+
+use num::zero;
+use traits::{SpatialObject, SpadeNum};
+use point_traits::PointN;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+/// Describes the directory nodes of an r*-tree on a single level.
+///
+/// All areas and margins are measured in the coordinate space of the tree's
+/// elements.
+#[derive(Clone, Debug)]
+pub struct LevelStatistics<S>
+    where S: SpadeNum {
+    /// The depth of this level's nodes. Nodes of depth 1 contain only leaves.
+    pub depth: usize,
+    /// The number of directory nodes on this level.
+    pub node_count: usize,
+    /// `fill_histogram[i]` is the number of nodes that have exactly `i` children.
+    pub fill_histogram: Vec<usize>,
+    /// The average ratio between a node's child count and the maximum node size.
+    pub average_fill_factor: f64,
+    /// The sum of all pairwise overlap areas between siblings on the level below,
+    /// summed over all nodes of this level.
+    pub overlap: S,
+    /// The area of this level's nodes that is not covered by their children.
+    ///
+    /// The covered area is estimated as the sum of the children's areas minus their
+    /// pairwise overlap.
+    pub dead_space: S,
+    /// The sum of the nodes' areas.
+    pub area_sum: S,
+    /// The sum of the nodes' half margins, see `BoundingRect::half_margin`.
+    pub margin_sum: S,
+}
+
+/// Describes the structure of an r*-tree, see `RTree::statistics`.
+#[derive(Clone, Debug)]
+pub struct RTreeStatistics<S>
+    where S: SpadeNum {
+    /// The number of elements contained in the tree.
+    pub size: usize,
+    /// The number of directory levels, this is 0 for an empty tree.
+    pub depth: usize,
+    /// Statistics for each level, starting with the root.
+    pub levels: Vec<LevelStatistics<S>>,
+}
+
+impl <S> LevelStatistics<S>
+    where S: SpadeNum {
+    fn new(depth: usize, max_size: usize) -> LevelStatistics<S> {
+        LevelStatistics {
+            depth,
+            node_count: 0,
+            fill_histogram: vec![0; max_size + 1],
+            average_fill_factor: 0.0,
+            overlap: zero(),
+            dead_space: zero(),
+            area_sum: zero(),
+            margin_sum: zero(),
+        }
+    }
+}
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    fn collect_statistics(&self, levels: &mut Vec<LevelStatistics<<T::Point as PointN>::Scalar>>) {
+        let index = levels[0].depth - self.depth;
+        if index == levels.len() {
+            levels.push(LevelStatistics::new(self.depth, self.options.max_size));
+        }
+        let mut children_area: <T::Point as PointN>::Scalar = zero();
+        let mut overlap: <T::Point as PointN>::Scalar = zero();
+        for (i, child) in self.children.iter().enumerate() {
+            let child_mbr = child.mbr();
+            children_area = children_area + child_mbr.area();
+            for sibling in &self.children[i + 1 ..] {
+                overlap = overlap + child_mbr.intersect(&sibling.mbr()).area();
+            }
+        }
+        {
+            let level = &mut levels[index];
+            level.fill_histogram[self.children.len()] += 1;
+            level.node_count += 1;
+            let mbr = self.mbr();
+            let covered = children_area - overlap.clone();
+            if mbr.area() > covered {
+                level.dead_space = level.dead_space.clone() + mbr.area() - covered;
+            }
+            level.overlap = level.overlap.clone() + overlap;
+            level.area_sum = level.area_sum.clone() + mbr.area();
+            level.margin_sum = level.margin_sum.clone() + mbr.half_margin();
+        }
+        for child in self.children.iter() {
+            if let RTreeNode::DirectoryNode(ref data) = *child {
+                data.collect_statistics(levels);
+            }
+        }
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Returns structural statistics of this tree.
+    ///
+    /// The statistics describe how well the tree's nodes are filled and how much
+    /// they overlap. They can be used to tune a tree's options on a given data set.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate spade;
+    /// use spade::rtree::RTree;
+    /// use cgmath::Point2;
+    ///
+    /// # fn main() {
+    /// let mut tree = RTree::new();
+    /// for x in 0 .. 100 {
+    ///   tree.insert(Point2::new(x as f32, (x * x) as f32));
+    /// }
+    /// let statistics = tree.statistics();
+    /// assert_eq!(statistics.size, 100);
+    /// assert_eq!(statistics.levels[0].node_count, 1);
+    /// # }
+    /// ```
+    pub fn statistics(&self) -> RTreeStatistics<<T::Point as PointN>::Scalar> {
+        let mut levels = Vec::new();
+        if self.size > 0 {
+            levels.push(LevelStatistics::new(self.root.depth, self.root.options.max_size));
+            self.root.collect_statistics(&mut levels);
+        }
+        for level in &mut levels {
+            let max_size = level.fill_histogram.len() - 1;
+            let children: usize = level.fill_histogram.iter().enumerate()
+                .map(|(count, nodes)| count * nodes).sum();
+            level.average_fill_factor = children as f64 / (level.node_count * max_size) as f64;
+        }
+        RTreeStatistics {
+            size: self.size,
+            depth: levels.len(),
+            levels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{RTree, RTreeOptions};
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_statistics() {
+        let (tree, points) = create_random_tree::<f64>(2000, [1, 7, 2, 8]);
+        let statistics = tree.statistics();
+        assert_eq!(statistics.size, points.len());
+        assert_eq!(statistics.depth, tree.root().depth());
+        assert_eq!(statistics.levels[0].node_count, 1);
+        let mut children = 1;
+        for level in &statistics.levels {
+            assert_eq!(level.node_count, children);
+            assert_eq!(level.fill_histogram.iter().sum::<usize>(), level.node_count);
+            assert!(level.average_fill_factor > 0.0 && level.average_fill_factor <= 1.0);
+            assert!(level.overlap >= 0.0 && level.dead_space >= 0.0);
+            children = level.fill_histogram.iter().enumerate()
+                .map(|(count, nodes)| count * nodes).sum();
+        }
+        assert_eq!(children, points.len());
+        assert_eq!(statistics.levels.last().unwrap().depth, 1);
+    }
+
+    #[test]
+    fn test_statistics_small_trees() {
+        let tree: RTree<Point2<f64>> = RTree::new();
+        let statistics = tree.statistics();
+        assert_eq!(statistics.depth, 0);
+        assert!(statistics.levels.is_empty());
+
+        let mut tree = RTreeOptions::new().set_max_size(4).set_min_size(2).build();
+        tree.insert(Point2::new(0.0, 0.0));
+        tree.insert(Point2::new(1.0, 1.0));
+        let statistics = tree.statistics();
+        assert_eq!(statistics.depth, 1);
+        let root = &statistics.levels[0];
+        assert_eq!(root.fill_histogram, vec![0, 0, 1, 0, 0]);
+        assert_eq!(root.average_fill_factor, 0.5);
+        assert_eq!(root.area_sum, 1.0);
+        assert_eq!(root.margin_sum, 2.0);
+        assert_eq!(root.dead_space, 1.0);
+        assert_eq!(root.overlap, 0.0);
+    }
+}
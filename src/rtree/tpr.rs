@@ -0,0 +1,649 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A time parameterized r-tree for moving objects.
+
+use std::cmp;
+use std::mem;
+use num::{cast, zero};
+use traits::{MovingObject, SpadeFloat};
+use point_traits::{PointN, PointNExtensions};
+use boundingvolume::BoundingRect;
+use misc::{min_inline, max_inline};
+use super::RTreeOptions;
+
+type Scalar<T> = <<T as MovingObject>::Point as PointN>::Scalar;
+
+// A bounding rectangle whose corners move with the minimal and maximal velocity
+// of its contents. The corners are given at the tree's reference time.
+#[derive(Clone, Debug)]
+struct MovingRect<V: PointN> {
+    lower: V,
+    upper: V,
+    min_velocity: V,
+    max_velocity: V,
+}
+
+impl <V> MovingRect<V> where V: PointN, V::Scalar: SpadeFloat {
+    fn from_object<T>(object: &T, reference_time: &V::Scalar) -> MovingRect<V>
+        where T: MovingObject<Point=V> {
+        let position = object.position_at(reference_time);
+        let velocity = object.velocity();
+        MovingRect {
+            lower: position.clone(),
+            upper: position,
+            min_velocity: velocity.clone(),
+            max_velocity: velocity,
+        }
+    }
+
+    fn union(rects: &[MovingRect<V>]) -> MovingRect<V> {
+        let mut result = rects[0].clone();
+        for rect in &rects[1 .. ] {
+            result.add_rect(rect);
+        }
+        result
+    }
+
+    fn add_rect(&mut self, other: &MovingRect<V>) {
+        self.lower = self.lower.min_point(&other.lower);
+        self.upper = self.upper.max_point(&other.upper);
+        self.min_velocity = self.min_velocity.min_point(&other.min_velocity);
+        self.max_velocity = self.max_velocity.max_point(&other.max_velocity);
+    }
+
+    fn contains_at_reference_time(&self, point: &V) -> bool {
+        self.lower.all_comp_wise(point, |l, r| l <= r)
+            && self.upper.all_comp_wise(point, |u, r| u >= r)
+    }
+
+    // Returns the rectangle at a time relative to the reference time. Before the
+    // reference time, the lower corner moves with the maximal velocity and vice versa.
+    fn at(&self, elapsed: V::Scalar) -> BoundingRect<V> {
+        let (lower_velocity, upper_velocity) = if elapsed >= zero() {
+            (&self.min_velocity, &self.max_velocity)
+        } else {
+            (&self.max_velocity, &self.min_velocity)
+        };
+        BoundingRect::from_corners(&self.lower.add(&lower_velocity.mul(elapsed)),
+                                   &self.upper.add(&upper_velocity.mul(elapsed)))
+    }
+
+    // Integrates the area from the reference time until the horizon with simpson's
+    // rule, which is exact for up to three dimensions.
+    fn integrated_area(&self, horizon: V::Scalar) -> V::Scalar {
+        let two: V::Scalar = cast(2.0).unwrap();
+        let four: V::Scalar = cast(4.0).unwrap();
+        let six: V::Scalar = cast(6.0).unwrap();
+        (self.at(zero()).area() + self.at(horizon / two).area() * four
+         + self.at(horizon).area()) * horizon / six
+    }
+
+    // Checks if the rectangle intersects a query rectangle at any time between start
+    // and end, both given relative to the reference time.
+    fn intersects_during(&self, rect: &BoundingRect<V>, start: V::Scalar, end: V::Scalar) -> bool {
+        (start < zero() && intersects_linear(&self.lower, &self.max_velocity,
+                                             &self.upper, &self.min_velocity,
+                                             rect, start, min_inline(end, zero())))
+            || (end >= zero() && intersects_linear(&self.lower, &self.min_velocity,
+                                                   &self.upper, &self.max_velocity,
+                                                   rect, max_inline(start, zero()), end))
+    }
+}
+
+// Restricts a time interval to all times t with offset + slope * t <= bound.
+fn restrict<S: SpadeFloat>((start, end): (S, S), offset: S, slope: S, bound: S)
+                           -> Option<(S, S)> {
+    let (start, end) = if slope > zero() {
+        (start, min_inline(end, (bound - offset) / slope))
+    } else if slope < zero() {
+        (max_inline(start, (bound - offset) / slope), end)
+    } else if offset > bound {
+        return None;
+    } else {
+        (start, end)
+    };
+    if start <= end { Some((start, end)) } else { None }
+}
+
+// Checks if a rectangle with linearly moving corners intersects a query rectangle
+// at any time between start and end.
+fn intersects_linear<V>(lower: &V, lower_velocity: &V, upper: &V, upper_velocity: &V,
+                        rect: &BoundingRect<V>, start: V::Scalar, end: V::Scalar) -> bool
+    where V: PointN, V::Scalar: SpadeFloat {
+    let (rect_lower, rect_upper) = (rect.lower(), rect.upper());
+    let mut interval = Some((start, end));
+    for i in 0 .. V::dimensions() {
+        interval = interval.and_then(|interval| {
+            restrict(interval, *lower.nth(i), *lower_velocity.nth(i), *rect_upper.nth(i))
+        }).and_then(|interval| {
+            restrict(interval, -*upper.nth(i), -*upper_velocity.nth(i), -*rect_lower.nth(i))
+        });
+    }
+    interval.is_some()
+}
+
+struct TprParameters<V: PointN> {
+    reference_time: V::Scalar,
+    horizon: V::Scalar,
+    options: RTreeOptions<V>,
+}
+
+enum TprNode<T: MovingObject> {
+    Leaf(T),
+    Directory(TprDirectory<T>),
+}
+
+struct TprDirectory<T: MovingObject> {
+    children: Vec<TprNode<T>>,
+    bounds: Option<MovingRect<T::Point>>,
+    depth: usize,
+}
+
+impl <T> TprNode<T> where T: MovingObject, Scalar<T>: SpadeFloat {
+    fn bounds(&self, reference_time: &Scalar<T>) -> MovingRect<T::Point> {
+        match *self {
+            TprNode::Leaf(ref t) => MovingRect::from_object(t, reference_time),
+            TprNode::Directory(ref data) => data.bounds.clone().unwrap(),
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match *self {
+            TprNode::Leaf(_) => 0,
+            TprNode::Directory(ref data) => data.depth,
+        }
+    }
+
+    fn into_leaves(self, result: &mut Vec<T>) {
+        match self {
+            TprNode::Leaf(t) => result.push(t),
+            TprNode::Directory(data) => {
+                for child in data.children {
+                    child.into_leaves(result);
+                }
+            },
+        }
+    }
+}
+
+impl <T> TprDirectory<T> where T: MovingObject, Scalar<T>: SpadeFloat {
+    fn new(depth: usize) -> TprDirectory<T> {
+        TprDirectory {
+            children: Vec::new(),
+            bounds: None,
+            depth,
+        }
+    }
+
+    fn update_bounds(&mut self, reference_time: &Scalar<T>) {
+        let bounds: Vec<_> = self.children.iter().map(|c| c.bounds(reference_time)).collect();
+        self.bounds = if bounds.is_empty() { None } else { Some(MovingRect::union(&bounds)) };
+    }
+
+    fn tighten(&mut self, reference_time: &Scalar<T>) {
+        for child in &mut self.children {
+            if let TprNode::Directory(ref mut data) = *child {
+                data.tighten(reference_time);
+            }
+        }
+        self.update_bounds(reference_time);
+    }
+
+    // Returns a new sibling if this node had to be split
+    fn insert(&mut self, node: TprNode<T>, bounds: MovingRect<T::Point>,
+              parameters: &TprParameters<T::Point>) -> Option<TprDirectory<T>> {
+        match self.bounds {
+            Some(ref mut own_bounds) => own_bounds.add_rect(&bounds),
+            None => self.bounds = Some(bounds.clone()),
+        }
+        if node.depth() + 1 == self.depth {
+            self.children.push(node);
+        } else {
+            let index = self.choose_subtree(&bounds, parameters);
+            let sibling = match self.children[index] {
+                TprNode::Directory(ref mut data) => data.insert(node, bounds, parameters),
+                TprNode::Leaf(_) => unreachable!(),
+            };
+            if let Some(sibling) = sibling {
+                self.children.push(TprNode::Directory(sibling));
+            }
+        }
+        if self.children.len() > parameters.options.max_size {
+            Some(self.split(parameters))
+        } else {
+            None
+        }
+    }
+
+    // Chooses the child whose integrated area grows least
+    fn choose_subtree(&self, bounds: &MovingRect<T::Point>,
+                      parameters: &TprParameters<T::Point>) -> usize {
+        let mut best: Option<(usize, Scalar<T>, Scalar<T>)> = None;
+        for (index, child) in self.children.iter().enumerate() {
+            let mut enlarged = child.bounds(&parameters.reference_time);
+            let area = enlarged.integrated_area(parameters.horizon);
+            enlarged.add_rect(bounds);
+            let enlargement = enlarged.integrated_area(parameters.horizon) - area;
+            if best.map(|(_, e, a)| enlargement < e || (enlargement == e && area < a))
+                .unwrap_or(true) {
+                best = Some((index, enlargement, area));
+            }
+        }
+        best.unwrap().0
+    }
+
+    fn sort_by_center(&mut self, axis: usize, parameters: &TprParameters<T::Point>) {
+        let two: Scalar<T> = cast(2.0).unwrap();
+        let elapsed = parameters.horizon / two;
+        let reference_time = &parameters.reference_time;
+        self.children.sort_by(|l, r| {
+            let l = *l.bounds(reference_time).at(elapsed).center().nth(axis);
+            let r = *r.bounds(reference_time).at(elapsed).center().nth(axis);
+            l.partial_cmp(&r).unwrap()
+        });
+    }
+
+    // Splits along the axis and index that minimize the integrated area of both
+    // resulting nodes. Children are ordered by their center in the middle of the horizon.
+    fn split(&mut self, parameters: &TprParameters<T::Point>) -> TprDirectory<T> {
+        let len = self.children.len();
+        let min_size = cmp::min(parameters.options.min_size, len / 2);
+        let mut best: Option<(usize, usize, Scalar<T>)> = None;
+        for axis in 0 .. T::Point::dimensions() {
+            self.sort_by_center(axis, parameters);
+            let bounds: Vec<_> = self.children.iter()
+                .map(|c| c.bounds(&parameters.reference_time)).collect();
+            for index in min_size .. len - min_size + 1 {
+                let cost = MovingRect::union(&bounds[.. index]).integrated_area(parameters.horizon)
+                    + MovingRect::union(&bounds[index .. ]).integrated_area(parameters.horizon);
+                if best.map(|b| cost < b.2).unwrap_or(true) {
+                    best = Some((axis, index, cost));
+                }
+            }
+        }
+        let (axis, index, _) = best.unwrap();
+        self.sort_by_center(axis, parameters);
+        let mut sibling = TprDirectory {
+            children: self.children.split_off(index),
+            bounds: None,
+            depth: self.depth,
+        };
+        sibling.update_bounds(&parameters.reference_time);
+        self.update_bounds(&parameters.reference_time);
+        sibling
+    }
+
+    // Removes an object and collects the leaves of all underfull nodes in orphans
+    fn remove(&mut self, object: &T, position: &T::Point,
+              parameters: &TprParameters<T::Point>, orphans: &mut Vec<T>) -> Option<T>
+        where T: PartialEq {
+        let mut found = None;
+        let mut removed = None;
+        for (index, child) in self.children.iter_mut().enumerate() {
+            match *child {
+                TprNode::Leaf(ref t) => if t == object {
+                    found = Some(index);
+                    break;
+                },
+                TprNode::Directory(ref mut data) => {
+                    let contains = data.bounds.as_ref()
+                        .map(|b| b.contains_at_reference_time(position)).unwrap_or(false);
+                    if contains {
+                        removed = data.remove(object, position, parameters, orphans);
+                        if removed.is_some() {
+                            found = Some(index);
+                            break;
+                        }
+                    }
+                },
+            }
+        }
+        let index = found?;
+        let underfull = match self.children[index] {
+            TprNode::Leaf(_) => true,
+            TprNode::Directory(ref data) => data.children.len() < parameters.options.min_size,
+        };
+        if underfull {
+            match self.children.swap_remove(index) {
+                TprNode::Leaf(t) => removed = Some(t),
+                directory => directory.into_leaves(orphans),
+            }
+        }
+        self.update_bounds(&parameters.reference_time);
+        removed
+    }
+
+    fn lookup_in_rectangle_at<'b>(&'b self, rect: &BoundingRect<T::Point>, time: &Scalar<T>,
+                                  elapsed: Scalar<T>, result: &mut Vec<&'b T>) {
+        for child in &self.children {
+            match *child {
+                TprNode::Leaf(ref t) => {
+                    if rect.contains_point(&t.position_at(time)) {
+                        result.push(t);
+                    }
+                },
+                TprNode::Directory(ref data) => {
+                    if data.bounds.as_ref().unwrap().at(elapsed).intersects(rect) {
+                        data.lookup_in_rectangle_at(rect, time, elapsed, result);
+                    }
+                },
+            }
+        }
+    }
+
+    fn lookup_in_rectangle_during<'b>(&'b self, rect: &BoundingRect<T::Point>,
+                                      parameters: &TprParameters<T::Point>,
+                                      start: Scalar<T>, end: Scalar<T>,
+                                      result: &mut Vec<&'b T>) {
+        for child in &self.children {
+            match *child {
+                TprNode::Leaf(ref t) => {
+                    let position = t.position_at(&parameters.reference_time);
+                    let velocity = t.velocity();
+                    if intersects_linear(&position, &velocity, &position, &velocity,
+                                         rect, start, end) {
+                        result.push(t);
+                    }
+                },
+                TprNode::Directory(ref data) => {
+                    if data.bounds.as_ref().unwrap().intersects_during(rect, start, end) {
+                        data.lookup_in_rectangle_during(rect, parameters, start, end, result);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// A time parameterized r-tree (TPR-tree) for objects that move with constant velocity.
+///
+/// Each node stores a bounding rectangle at the tree's reference time together
+/// with the minimal and maximal velocity of its contents. The rectangle expands
+/// with these velocities as time passes, so it contains all objects at any time.
+/// Insertions and splits minimize the area integrated from the reference time
+/// until a time horizon, which should roughly match the time between updates of
+/// the tree.
+/// Since the bounds grow over time, they should be tightened periodically by
+/// calling `tighten` with the current time.
+///
+/// Objects are moved by removing them and inserting their updated versions.
+///
+/// # Example
+/// ```
+/// # extern crate cgmath;
+/// # extern crate spade;
+/// use spade::BoundingRect;
+/// use spade::rtree::TprTree;
+/// use spade::primitives::SimpleMovingPoint;
+/// use cgmath::Point2;
+///
+/// # fn main() {
+/// let mut tree = TprTree::new(10.0);
+/// // Located at (0, 0) at time 0, moves one unit per time unit to the right
+/// tree.insert(SimpleMovingPoint::new(Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), 0.0));
+/// tree.insert(SimpleMovingPoint::new(Point2::new(10.0, 5.0), Point2::new(0.0, -1.0), 0.0));
+/// let rect = BoundingRect::from_corners(&Point2::new(4.0, -1.0), &Point2::new(6.0, 1.0));
+/// assert_eq!(tree.lookup_in_rectangle_at(&rect, &5.0).len(), 1);
+/// assert!(tree.lookup_in_rectangle_at(&rect, &8.0).is_empty());
+/// assert_eq!(tree.lookup_in_rectangle_during(&rect, &0.0, &8.0).len(), 1);
+/// // Tighten the bounds after some time has passed
+/// tree.tighten(5.0);
+/// assert_eq!(tree.reference_time(), 5.0);
+/// # }
+/// ```
+pub struct TprTree<T>
+    where T: MovingObject,
+          Scalar<T>: SpadeFloat {
+    root: TprDirectory<T>,
+    size: usize,
+    parameters: TprParameters<T::Point>,
+}
+
+impl <T> TprTree<T>
+    where T: MovingObject,
+          Scalar<T>: SpadeFloat {
+
+    /// Creates an empty tree with reference time zero.
+    ///
+    /// `horizon` is the time span over which insertions optimize the tree's
+    /// bounding rectangles. Panics if `horizon` is not positive.
+    pub fn new(horizon: Scalar<T>) -> TprTree<T> {
+        assert!(horizon > zero(), "The horizon must be positive");
+        TprTree {
+            root: TprDirectory::new(1),
+            size: 0,
+            parameters: TprParameters {
+                reference_time: zero(),
+                horizon,
+                options: RTreeOptions::new(),
+            },
+        }
+    }
+
+    /// Returns the number of objects contained in this tree.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the time at which the bounding rectangles were last tightened.
+    pub fn reference_time(&self) -> Scalar<T> {
+        self.parameters.reference_time
+    }
+
+    /// Returns the time span over which insertions optimize the tree.
+    pub fn horizon(&self) -> Scalar<T> {
+        self.parameters.horizon
+    }
+
+    /// Returns a bounding rectangle that contains all objects at a given time.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn mbr_at(&self, time: &Scalar<T>) -> Option<BoundingRect<T::Point>> {
+        let elapsed = *time - self.parameters.reference_time;
+        self.root.bounds.as_ref().map(|b| b.at(elapsed))
+    }
+
+    /// Inserts a new object into the tree.
+    pub fn insert(&mut self, object: T) {
+        self.insert_node(TprNode::Leaf(object));
+        self.size += 1;
+    }
+
+    fn insert_node(&mut self, node: TprNode<T>) {
+        let bounds = node.bounds(&self.parameters.reference_time);
+        if let Some(sibling) = self.root.insert(node, bounds, &self.parameters) {
+            let depth = self.root.depth + 1;
+            let old_root = mem::replace(&mut self.root, TprDirectory::new(depth));
+            self.root.children.push(TprNode::Directory(old_root));
+            self.root.children.push(TprNode::Directory(sibling));
+            self.root.update_bounds(&self.parameters.reference_time);
+        }
+    }
+
+    /// Removes an object from the tree.
+    ///
+    /// Returns `true` if the object was contained in the tree. Objects of underfull
+    /// nodes are reinserted.
+    pub fn remove(&mut self, object: &T) -> bool where T: PartialEq {
+        let position = object.position_at(&self.parameters.reference_time);
+        let mut orphans = Vec::new();
+        let removed = self.root.remove(object, &position, &self.parameters, &mut orphans);
+        if removed.is_none() {
+            return false;
+        }
+        self.size -= 1;
+        if self.root.children.is_empty() {
+            self.root = TprDirectory::new(1);
+        }
+        while self.root.depth > 1 && self.root.children.len() == 1 {
+            match self.root.children.pop() {
+                Some(TprNode::Directory(data)) => self.root = data,
+                _ => unreachable!(),
+            }
+        }
+        for orphan in orphans {
+            self.insert_node(TprNode::Leaf(orphan));
+        }
+        true
+    }
+
+    /// Recomputes all bounding rectangles at a new reference time.
+    ///
+    /// Tightened rectangles are minimal at the given time, which should usually be
+    /// the current time. This keeps queries around that time efficient.
+    pub fn tighten(&mut self, time: Scalar<T>) {
+        self.parameters.reference_time = time;
+        self.root.tighten(&time);
+    }
+
+    /// Returns all objects that are located inside a rectangle at a given time.
+    pub fn lookup_in_rectangle_at(&self, rect: &BoundingRect<T::Point>, time: &Scalar<T>)
+                                  -> Vec<&T> {
+        let mut result = Vec::new();
+        if self.size > 0 {
+            let elapsed = *time - self.parameters.reference_time;
+            self.root.lookup_in_rectangle_at(rect, time, elapsed, &mut result);
+        }
+        result
+    }
+
+    /// Returns all objects that are located inside a rectangle at any time
+    /// between `start` and `end`.
+    pub fn lookup_in_rectangle_during(&self, rect: &BoundingRect<T::Point>,
+                                      start: &Scalar<T>, end: &Scalar<T>) -> Vec<&T> {
+        let mut result = Vec::new();
+        if self.size > 0 {
+            let reference_time = self.parameters.reference_time;
+            self.root.lookup_in_rectangle_during(rect, &self.parameters, *start - reference_time,
+                                                 *end - reference_time, &mut result);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TprTree, TprNode, TprDirectory, intersects_linear};
+    use traits::MovingObject;
+    use primitives::SimpleMovingPoint;
+    use boundingvolume::BoundingRect;
+    use cgmath::Point2;
+    use testutils::*;
+
+    fn check_bounds(node: &TprDirectory<SimpleMovingPoint<Point2<f64>>>, reference_time: f64) {
+        let bounds = node.bounds.as_ref().unwrap();
+        for child in &node.children {
+            let child_bounds = child.bounds(&reference_time);
+            for &elapsed in &[-10.0, -1.0, 0.0, 1.0, 10.0] {
+                assert!(bounds.at(elapsed).contains_rect(&child_bounds.at(elapsed)));
+            }
+            if let TprNode::Directory(ref data) = *child {
+                assert_eq!(data.depth + 1, node.depth);
+                check_bounds(data, reference_time);
+            }
+        }
+    }
+
+    fn random_moving_points(size: usize, seed: [u32; 4]) -> Vec<SimpleMovingPoint<Point2<f64>>> {
+        let positions = random_points_with_seed::<f64>(size, seed);
+        let velocities = random_points_in_range(0.1, size, [seed[1], seed[0], 3, 7]);
+        let times = random_points_in_range(5.0, size, [seed[2], seed[3], 1, 2]);
+        positions.iter().zip(velocities.iter()).zip(times.iter())
+            .map(|((p, v), t)| SimpleMovingPoint::new(*p, *v, t.x)).collect()
+    }
+
+    fn check_queries(tree: &TprTree<SimpleMovingPoint<Point2<f64>>>,
+                     points: &[SimpleMovingPoint<Point2<f64>>]) {
+        let corners = random_points_in_range(1.5, 40, [4, 2, 8, 1]);
+        let times = random_points_in_range(10.0, 20, [2, 9, 9, 3]);
+        let mut found = 0;
+        for (corners, times) in corners.chunks(2).zip(times.iter()) {
+            let rect = BoundingRect::from_corners(&corners[0], &corners[1]);
+            let (start, end) = if times.x < times.y { (times.x, times.y) } else { (times.y, times.x) };
+
+            let mut result = tree.lookup_in_rectangle_at(&rect, &start);
+            let mut expected: Vec<_> = points.iter()
+                .filter(|p| rect.contains_point(&p.position_at(&start))).collect();
+            assert_eq!(sorted(&mut result), sorted(&mut expected));
+
+            let mut result = tree.lookup_in_rectangle_during(&rect, &start, &end);
+            let reference_time = tree.reference_time();
+            let mut expected: Vec<_> = points.iter().filter(|p| {
+                let position = p.position_at(&reference_time);
+                intersects_linear(&position, &p.velocity, &position, &p.velocity, &rect,
+                                  start - reference_time, end - reference_time)
+            }).collect();
+            assert_eq!(sorted(&mut result), sorted(&mut expected));
+            found += result.len();
+            // All objects that are inside the rectangle at some sampled time must be found
+            for step in 0 .. 11 {
+                let time = start + (end - start) * step as f64 / 10.0;
+                for point in points {
+                    if rect.contains_point(&point.position_at(&time)) {
+                        assert!(result.contains(&point));
+                    }
+                }
+            }
+        }
+        assert!(found > 0);
+    }
+
+    fn sorted<'a, 'b>(points: &'b mut Vec<&'a SimpleMovingPoint<Point2<f64>>>)
+                      -> &'b Vec<&'a SimpleMovingPoint<Point2<f64>>> {
+        points.sort_by(|l, r| l.position.x.partial_cmp(&r.position.x).unwrap());
+        points
+    }
+
+    #[test]
+    fn test_tpr_tree_queries() {
+        let points = random_moving_points(1000, [3, 1, 4, 1]);
+        let mut tree = TprTree::new(5.0);
+        for point in &points {
+            tree.insert(point.clone());
+        }
+        assert_eq!(tree.size(), 1000);
+        assert!(tree.root.depth > 1);
+        check_bounds(&tree.root, 0.0);
+        check_queries(&tree, &points);
+
+        tree.tighten(3.0);
+        assert_eq!(tree.reference_time(), 3.0);
+        check_bounds(&tree.root, 3.0);
+        check_queries(&tree, &points);
+        let mbr = tree.mbr_at(&3.0).unwrap();
+        for point in &points {
+            assert!(mbr.contains_point(&point.position_at(&3.0)));
+        }
+    }
+
+    #[test]
+    fn test_tpr_tree_remove() {
+        let points = random_moving_points(500, [2, 7, 1, 8]);
+        let mut tree = TprTree::new(2.0);
+        for point in &points {
+            tree.insert(point.clone());
+        }
+        tree.tighten(1.5);
+        let (removed, remaining) = points.split_at(300);
+        for point in removed {
+            assert!(tree.remove(point));
+            assert!(!tree.remove(point));
+        }
+        assert_eq!(tree.size(), 200);
+        check_bounds(&tree.root, 1.5);
+        check_queries(&tree, remaining);
+        for point in remaining {
+            assert!(tree.remove(point));
+        }
+        assert_eq!(tree.size(), 0);
+        assert!(tree.mbr_at(&0.0).is_none());
+        let rect = BoundingRect::from_corners(&Point2::new(-1.0, -1.0), &Point2::new(1.0, 1.0));
+        assert!(tree.lookup_in_rectangle_during(&rect, &0.0, &1.0).is_empty());
+    }
+}
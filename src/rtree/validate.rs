@@ -0,0 +1,231 @@
+// Copyright 2017 The Spade Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Invariant checks for r*-trees.
+
+use std::error::Error;
+use std::fmt;
+use traits::SpatialObject;
+use super::{Aggregate, RTree, RTreeNode, DirectoryNodeData};
+
+/// Describes a violated r*-tree invariant, see `RTree::validate`.
+///
+/// A node's path contains the child indices leading from the root to that
+/// directory node, it is empty for the root itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RTreeValidationError {
+    /// A node's bounding rectangle is not the union of its children's bounding rectangles.
+    NonTightMbr {
+        /// The child indices leading to the offending node.
+        path: Vec<usize>,
+    },
+    /// A child's depth is not one less than the depth of its parent.
+    InconsistentDepth {
+        /// The child indices leading to the offending node.
+        path: Vec<usize>,
+        /// The depth of the offending node.
+        depth: usize,
+        /// The depth of the offending child.
+        child_depth: usize,
+    },
+    /// A node's cached aggregate differs from the combined aggregates of its children.
+    StaleAggregate {
+        /// The child indices leading to the offending node.
+        path: Vec<usize>,
+    },
+    /// A non-root node has no children.
+    EmptyNode {
+        /// The child indices leading to the offending node.
+        path: Vec<usize>,
+    },
+    /// A node has more children than the tree's maximum node size.
+    Overfull {
+        /// The child indices leading to the offending node.
+        path: Vec<usize>,
+        /// The number of children of the offending node.
+        children: usize,
+        /// The tree's maximum node size.
+        max_size: usize,
+    },
+    /// The number of leaves does not match the tree's size.
+    SizeMismatch {
+        /// The size stored in the tree.
+        size: usize,
+        /// The number of leaves found.
+        leaf_count: usize,
+    },
+}
+
+impl fmt::Display for RTreeValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RTreeValidationError::NonTightMbr { ref path } =>
+                write!(f, "bounding rectangle of node {:?} is not tight", path),
+            RTreeValidationError::InconsistentDepth { ref path, depth, child_depth } =>
+                write!(f, "node {:?} of depth {} has a child of depth {}",
+                       path, depth, child_depth),
+            RTreeValidationError::StaleAggregate { ref path } =>
+                write!(f, "aggregate of node {:?} is stale", path),
+            RTreeValidationError::EmptyNode { ref path } =>
+                write!(f, "node {:?} has no children", path),
+            RTreeValidationError::Overfull { ref path, children, max_size } =>
+                write!(f, "node {:?} has {} children, the maximum is {}",
+                       path, children, max_size),
+            RTreeValidationError::SizeMismatch { size, leaf_count } =>
+                write!(f, "tree has size {} but contains {} leaves", size, leaf_count),
+        }
+    }
+}
+
+impl Error for RTreeValidationError { }
+
+impl <T, A> DirectoryNodeData<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+    // Returns the number of leaves below this node
+    fn validate(&self, path: &mut Vec<usize>, is_root: bool)
+                -> Result<usize, RTreeValidationError> {
+        let children = self.children.len();
+        if !is_root && children == 0 {
+            return Err(RTreeValidationError::EmptyNode { path: path.clone() });
+        }
+        if children > self.options.max_size {
+            return Err(RTreeValidationError::Overfull {
+                path: path.clone(),
+                children,
+                max_size: self.options.max_size,
+            });
+        }
+        let mut mbr = self.children.first().map(|child| child.mbr());
+        if let Some(ref mut mbr) = mbr {
+            for child in &self.children[1 ..] {
+                mbr.add_rect(&child.mbr());
+            }
+        }
+        if mbr != self.bounding_box {
+            return Err(RTreeValidationError::NonTightMbr { path: path.clone() });
+        }
+        let mut aggregate = A::empty();
+        for child in self.children.iter() {
+            aggregate = A::combine(&aggregate, &child.aggregate());
+        }
+        if aggregate != self.aggregate {
+            return Err(RTreeValidationError::StaleAggregate { path: path.clone() });
+        }
+        let mut leaf_count = 0;
+        for (index, child) in self.children.iter().enumerate() {
+            if child.depth() + 1 != self.depth {
+                return Err(RTreeValidationError::InconsistentDepth {
+                    path: path.clone(),
+                    depth: self.depth,
+                    child_depth: child.depth(),
+                });
+            }
+            leaf_count += match *child {
+                RTreeNode::DirectoryNode(ref data) => {
+                    path.push(index);
+                    let count = data.validate(path, false)?;
+                    path.pop();
+                    count
+                },
+                RTreeNode::Leaf(_) => 1,
+            };
+        }
+        Ok(leaf_count)
+    }
+}
+
+impl <T, A> RTree<T, A>
+    where T: SpatialObject,
+          A: Aggregate<T> {
+
+    /// Checks the structural invariants of this tree.
+    ///
+    /// Every directory node's bounding rectangle must equal the union of its
+    /// children's bounding rectangles, its cached aggregate must equal the
+    /// combined aggregates of its children, each child must be one level below its
+    /// parent, no node may exceed the maximum node size, all nodes except the
+    /// root must have children and the number of leaves must match `size()`.
+    /// Removals may leave nodes with less children than the tree's minimum
+    /// node size, this is not considered a violation.
+    /// A violation usually indicates that an object's minimal bounding box has
+    /// changed after insertion, e.g. by a `SpatialObject` implementation whose
+    /// `mbr()` is not deterministic, or that an aggregated property has been
+    /// modified without updating the tree. Queries on such a tree may miss objects.
+    ///
+    /// Returns the first violation found during a depth first traversal.
+    pub fn validate(&self) -> Result<(), RTreeValidationError> {
+        let leaf_count = self.root.validate(&mut Vec::new(), true)?;
+        if leaf_count != self.size {
+            return Err(RTreeValidationError::SizeMismatch {
+                size: self.size,
+                leaf_count,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RTreeValidationError;
+    use super::super::{RTree, Count};
+    use cgmath::Point2;
+    use testutils::*;
+
+    #[test]
+    fn test_validate() {
+        let (mut tree, points) = create_random_tree::<f64>(1000, [4, 2, 4, 2]);
+        assert_eq!(tree.validate(), Ok(()));
+        for point in &points[.. 700] {
+            assert!(tree.remove(point));
+            assert_eq!(tree.validate(), Ok(()));
+        }
+        for point in &points[700 ..] {
+            tree.update(point, |p| p.x = -p.x);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(RTree::<Point2<f64>>::new().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_detects_moved_objects() {
+        let (mut tree, points) = create_random_tree::<f64>(100, [1, 1, 2, 3]);
+        for point in &points {
+            // Moving an object through a mutable reference invalidates the tree
+            tree.lookup_mut(point).unwrap().x += 10.0;
+        }
+        match tree.validate() {
+            Err(RTreeValidationError::NonTightMbr { .. }) => {},
+            other => panic!("unexpected validation result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_detects_stale_aggregates() {
+        let mut tree: RTree<_, Count<_>> = RTree::new_aggregated();
+        for point in random_points_with_seed::<f64>(100, [5, 3, 2, 1]) {
+            tree.insert(point);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        tree.root.aggregate += 1;
+        assert_eq!(tree.validate(), Err(RTreeValidationError::StaleAggregate {
+            path: Vec::new(),
+        }));
+    }
+
+    #[test]
+    fn test_validate_detects_size_mismatch() {
+        let (mut tree, _) = create_random_tree::<f64>(100, [3, 5, 8, 13]);
+        tree.size -= 1;
+        assert_eq!(tree.validate(), Err(RTreeValidationError::SizeMismatch {
+            size: 99,
+            leaf_count: 100,
+        }));
+    }
+}
@@ -67,6 +67,45 @@ pub trait SpatialObject {
     }
 }
 
+/// Describes objects that can be intersected with a ray.
+///
+/// Required by `RTree::ray_cast` and `RTree::ray_intersections`. A ray starts at an
+/// origin and contains all points `origin + direction * t` for non negative `t`.
+/// See the `primitives` module for some implementations.
+pub trait RayIntersect: SpatialObject {
+    /// Returns the smallest non negative `t` for which `origin + direction * t` lies
+    /// on this object or `None` if the ray misses the object.
+    ///
+    /// Solid objects should return zero if they contain the ray's origin.
+    fn ray_intersection(&self, origin: &Self::Point, direction: &Self::Point)
+                        -> Option<<Self::Point as PointN>::Scalar>;
+}
+
+/// Describes objects that move with a constant velocity.
+///
+/// Required by `rtree::TprTree`. An object is located at `position()` at time
+/// `reference_time()` and moves by `velocity()` per time unit.
+/// See `primitives::SimpleMovingPoint` for an implementation.
+pub trait MovingObject {
+    /// The object's point type.
+    type Point: PointN;
+
+    /// Returns the object's position at its reference time.
+    fn position(&self) -> Self::Point;
+
+    /// Returns the object's velocity.
+    fn velocity(&self) -> Self::Point;
+
+    /// Returns the time at which the object is located at `position()`.
+    fn reference_time(&self) -> <Self::Point as PointN>::Scalar;
+
+    /// Returns the object's position at a given time.
+    fn position_at(&self, time: &<Self::Point as PointN>::Scalar) -> Self::Point {
+        let elapsed = time.clone() - self.reference_time();
+        self.position().add(&self.velocity().mul(elapsed))
+    }
+}
+
 /// An object that has a position.
 /// 
 /// Describes a point like object that has a well defined position.
@@ -105,6 +144,14 @@ pub trait HasPosition {
     fn position(&self) -> Self::Point;
 }
 
+/// An object with a position that can be changed.
+///
+/// Required by `DelaunayTriangulation::move_vertex(..)`.
+pub trait HasPositionMut: HasPosition {
+    /// Sets the object's position.
+    fn set_position(&mut self, position: Self::Point);
+}
+
 /// An object with a two dimensional position.
 ///
 /// This trait is similar to `HasPosition`, but is only implemented for two dimensional points.
@@ -119,6 +166,12 @@ impl <V> HasPosition for V where V: PointN {
     }
 }
 
+impl <V> HasPositionMut for V where V: PointN {
+    fn set_position(&mut self, position: V) {
+        *self = position;
+    }
+}
+
 impl <S>  SpatialObject for S where S: HasPosition {
     type Point = S::Point;
 